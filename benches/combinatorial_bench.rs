@@ -1,5 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
 use p455w0rd::combinatorics::{calculate_total_combinations, CombinatorialConfig};
+use p455w0rd::transform::TransformChain;
 
 fn benchmark_combinatorial_calculation(c: &mut Criterion) {
     let mut group = c.benchmark_group("combinatorial_calculation");
@@ -19,7 +20,7 @@ fn benchmark_combinatorial_calculation(c: &mut Criterion) {
                         b.iter(|| {
                             let config = CombinatorialConfig {
                                 max_words: *max_words,
-                                include_special_chars: true,
+                                ..Default::default()
                             };
                             calculate_total_combinations(black_box(words), black_box(&config)).unwrap()
                         })
@@ -43,13 +44,14 @@ fn benchmark_word_variants(c: &mut Criterion) {
         "testing".to_string(),
     ];
 
+    let transforms = TransformChain::default();
     for word in test_words {
         group.bench_with_input(
-            BenchmarkId::new("calculate_actual_word_variants", &word),
+            BenchmarkId::new("word_variants", &word),
             &word,
             |b, word| {
                 b.iter(|| {
-                    p455w0rd::combinatorics::calculate_actual_word_variants(black_box(word))
+                    transforms.word_variants(black_box(word))
                 })
             },
         );
@@ -1,20 +1,238 @@
-use clap::Parser;
+use crate::combinatorics::CombinatorialConfig;
+use crate::config::PRESET_NAMES;
+use crate::dedup::DedupMode;
+use crate::error::{Error, Result};
+use crate::filter::compile_regex_filters;
+use crate::display::StatusEvents;
+use crate::hashing::{HashAlgorithm, HashOutput};
+use crate::markov::DEFAULT_ORDER;
+use crate::output::{is_stdout, Compression, SplitLimit, STDOUT_PATH};
+use crate::policy::{PasswordPolicy, PolicyPreset};
+use crate::profile::TargetProfile;
+use crate::report::ReportFormat;
+use crate::transform::TransformChain;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    #[command(flatten)]
+    pub model: ModelArgs,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+// Parsed once at startup, so variant sizes do not matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+pub enum Command {
+    /// Generate password candidates (the default when no subcommand is given)
+    Generate(GenerateArgs),
+    /// Count the candidates a word list would produce, without generating them
+    Analyze(AnalyzeArgs),
+    /// Summarize an existing wordlist: lengths, character sets and masks
+    Stats(StatsArgs),
+    /// Generate candidates from facts about a target (names, dates, pets, ...)
+    Profile(ProfileArgs),
+    /// Apply hashcat-style rule files to a wordlist
+    Rules(RulesArgs),
+    /// Check generated candidates against a file of unsalted hashes
+    Crack(CrackArgs),
+    /// Verify generated passphrases against a captured WPA/WPA2 handshake
+    Wpa2(Wpa2Args),
+    /// Train a Markov model (for --markov) or PCFG (for --pcfg) on a plaintext password list
+    Train(TrainArgs),
+}
+
+#[derive(clap::Args)]
+pub struct GenerateArgs {
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    #[command(flatten)]
+    pub model: ModelArgs,
+}
+
+#[derive(clap::Args)]
+pub struct AnalyzeArgs {
+    #[command(flatten)]
+    pub keyspace: KeyspaceArgs,
+
+    /// Print the analysis as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args)]
+pub struct StatsArgs {
+    /// Wordlist to summarize
+    pub wordlist: String,
+
+    /// Number of most common masks to list
+    #[arg(long, default_value = "10")]
+    pub top: usize,
+
+    /// Print the statistics as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args)]
+pub struct ProfileArgs {
+    /// Target's first name
+    #[arg(long)]
+    pub first_name: Option<String>,
+
+    /// Target's last name
+    #[arg(long)]
+    pub last_name: Option<String>,
+
+    /// Target's nickname or username
+    #[arg(long)]
+    pub nickname: Option<String>,
+
+    /// Partner's name
+    #[arg(long)]
+    pub partner: Option<String>,
+
+    /// Child's name (repeatable)
+    #[arg(long)]
+    pub child: Vec<String>,
+
+    /// Pet's name (repeatable)
+    #[arg(long)]
+    pub pet: Vec<String>,
+
+    /// Employer or organization
+    #[arg(long)]
+    pub company: Option<String>,
+
+    /// Significant date such as a birthday, as YYYY-MM-DD (repeatable)
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    pub date: Vec<String>,
+
+    /// Any other word tied to the target: hobby, team, city (repeatable)
+    #[arg(long)]
+    pub keyword: Vec<String>,
+
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(clap::Args)]
+pub struct RulesArgs {
+    /// Wordlist the rules are applied to
+    #[arg(short, long, value_name = "FILE")]
+    pub input: String,
+
+    /// Hashcat-style rule file (repeatable)
+    #[arg(short, long = "rules", value_name = "FILE", required = true)]
+    pub rule_files: Vec<String>,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(clap::Args)]
+pub struct CrackArgs {
+    /// File of hashes to recover, one per line (user:hash and pwdump lines are accepted)
+    #[arg(long, value_name = "FILE")]
+    pub hashes: String,
+
+    /// Algorithm the hashes were made with
+    #[arg(long, value_enum)]
+    pub hash_type: HashAlgorithm,
+
+    /// Potfile that recovered hash:plaintext pairs are appended to
+    #[arg(long, value_name = "FILE", default_value = "p455w0rd.potfile")]
+    pub potfile: String,
+
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+}
+
+#[derive(clap::Args)]
+pub struct Wpa2Args {
+    /// Hashcat 22000 file with WPA*01 (PMKID) or WPA*02 (EAPOL) lines
+    #[arg(long, value_name = "FILE")]
+    pub handshake: String,
+
+    #[command(flatten)]
+    pub generation: GenerationArgs,
+}
+
+#[derive(clap::Args)]
+pub struct TrainArgs {
+    /// Plaintext password list to learn from, one password per line
+    #[arg(short, long, value_name = "FILE")]
+    pub input: String,
+
+    /// Where to save the model [default: p455w0rd.markov or p455w0rd.pcfg]
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<String>,
+
+    /// Kind of model to train
+    #[arg(long, value_enum, default_value = "markov")]
+    pub kind: ModelKind,
+
+    /// Markov n-gram length: each character is predicted from the ORDER - 1 before it (2-5)
+    #[arg(long, default_value_t = DEFAULT_ORDER)]
+    pub order: usize,
+}
+
+impl TrainArgs {
+    pub fn get_output(&self) -> &str {
+        self.output.as_deref().unwrap_or(match self.kind {
+            ModelKind::Markov => "p455w0rd.markov",
+            ModelKind::Pcfg => "p455w0rd.pcfg",
+        })
+    }
+}
+
+/// Models `train` can build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ModelKind {
+    /// Character n-grams, for --markov
+    Markov,
+    /// Weir-style base structures with digit and symbol runs, for --pcfg
+    Pcfg,
+}
+
+// Where the base words come from.
+#[derive(clap::Args)]
+pub struct WordArgs {
     /// Input file containing words (one per line) or comma-separated words
     #[arg(short, long)]
     pub input: Option<String>,
 
     /// Words provided directly as arguments
     pub words: Vec<String>,
+}
 
-    /// Output file path
-    #[arg(short, long, default_value = "passwords.txt")]
-    pub output: String,
+// The words, lengths and transforms that make up the keyspace, shared by
+// generation and `analyze`.
+#[derive(clap::Args)]
+pub struct KeyspaceArgs {
+    #[command(flatten)]
+    pub words: WordArgs,
 
-    /// Generate passwords for WPA2 (8-63 characters)
+    /// Use WPA2 passphrase lengths (8-63 characters) instead of --min-length/--max-length
     #[arg(long)]
     pub wpa2: bool,
 
@@ -26,6 +244,26 @@ pub struct Args {
     #[arg(long, default_value = "20")]
     pub max_length: usize,
 
+    /// Maximum number of words to combine (default: unlimited)
+    #[arg(long, default_value = "0")]
+    pub max_words: usize,
+
+    /// Transforms to apply, in order (comma-separated: leet, case, pad).
+    /// Word transforms must come before pad, which runs on joined words
+    #[arg(long, value_name = "LIST", value_parser = TransformChain::parse, default_value = "leet,case,pad")]
+    pub transforms: TransformChain,
+
+    /// Skip special character padding
+    #[arg(long)]
+    pub no_special_chars: bool,
+}
+
+// Options that decide which candidates are produced.
+#[derive(clap::Args)]
+pub struct GenerationArgs {
+    #[command(flatten)]
+    pub keyspace: KeyspaceArgs,
+
     /// Maximum number of combinations to generate (0 = unlimited)
     #[arg(long, default_value = "0")]
     pub limit: usize,
@@ -38,24 +276,167 @@ pub struct Args {
     #[arg(long)]
     pub quiet: bool,
 
-    /// Append to output file instead of overwriting
-    #[arg(long)]
-    pub append: bool,
+    /// Emit JSON-lines progress events to stderr instead of the status display
+    #[arg(long, conflicts_with = "status_file")]
+    pub status_json: bool,
 
-    /// Maximum number of words to combine (default: unlimited)
-    #[arg(long, default_value = "0")]
-    pub max_words: usize,
+    /// Append JSON-lines progress events to this file
+    #[arg(long, value_name = "PATH")]
+    pub status_file: Option<String>,
 
-    /// Skip special character padding
+    /// Remove duplicates across the whole output (exact output is sorted)
+    #[arg(long, value_enum, default_value = "none")]
+    pub dedup: DedupMode,
+
+    /// Target false-positive rate for --dedup bloom
+    #[arg(long, default_value = "0.001")]
+    pub dedup_fp_rate: f64,
+
+    /// Memory cap for the --dedup bloom filter (e.g. 512M, 2G)
+    #[arg(long, value_parser = parse_size, default_value = "1G")]
+    pub dedup_max_memory: u64,
+
+    /// Directory for temporary files spilled by --dedup exact
     #[arg(long)]
-    pub no_special_chars: bool,
+    pub temp_dir: Option<String>,
+
+    /// Wordlist of already-tried passwords to leave out of the output (repeatable)
+    #[arg(long, value_name = "FILE")]
+    pub exclude: Vec<String>,
+
+    /// Only keep passwords meeting a built-in policy
+    #[arg(long, value_enum, conflicts_with = "policy_file")]
+    pub policy: Option<PolicyPreset>,
+
+    /// Only keep passwords meeting the policy in this TOML file
+    #[arg(long, value_name = "FILE")]
+    pub policy_file: Option<String>,
+
+    /// Require at least this many uppercase letters
+    #[arg(long, value_name = "N")]
+    pub require_upper: Option<usize>,
+
+    /// Require at least this many lowercase letters
+    #[arg(long, value_name = "N")]
+    pub require_lower: Option<usize>,
+
+    /// Require at least this many digits
+    #[arg(long, value_name = "N")]
+    pub require_digit: Option<usize>,
+
+    /// Require at least this many symbols
+    #[arg(long, value_name = "N")]
+    pub require_symbol: Option<usize>,
+
+    /// Require this many of the four character classes (upper, lower, digit, symbol)
+    #[arg(long, value_name = "N")]
+    pub min_classes: Option<usize>,
+
+    /// Reject passwords with more than N identical characters in a row
+    #[arg(long, value_name = "N")]
+    pub max_repeat: Option<usize>,
+
+    /// Reject passwords containing this text, case-insensitively (repeatable, e.g. the username)
+    #[arg(long, value_name = "TEXT")]
+    pub forbid: Vec<String>,
+
+    /// Only keep passwords matching this regex (repeatable; all must match)
+    #[arg(long = "match", value_name = "REGEX")]
+    pub match_patterns: Vec<String>,
+
+    /// Drop passwords matching this regex (repeatable)
+    #[arg(long = "reject", value_name = "REGEX")]
+    pub reject_patterns: Vec<String>,
 
     /// Skip confirmation prompt for large generation jobs
     #[arg(long)]
     pub force: bool,
+
+    /// Print the analysis report and exit without generating
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Report format for --dry-run
+    #[arg(long, value_enum, default_value = "json", requires = "dry_run")]
+    pub format: ReportFormat,
 }
 
-impl Args {
+// Candidate sources other than word combinations.
+#[derive(clap::Args)]
+pub struct ModelArgs {
+    /// Enumerate candidates from a Markov model built by `train`, most probable first, instead of combining words
+    #[arg(long, value_name = "MODEL")]
+    pub markov: Option<String>,
+
+    /// Highest Markov level to enumerate (default: every level; bound the run with --limit)
+    #[arg(long, value_name = "LEVEL", requires = "markov")]
+    pub markov_max_level: Option<u32>,
+
+    /// Generate from a PCFG built by `train --kind pcfg`, most probable first, filling its letter runs with the input words
+    #[arg(long, value_name = "MODEL", conflicts_with = "markov")]
+    pub pcfg: Option<String>,
+
+    /// Chain the input words PRINCE-style, each any number of times, smallest keyspace first (--max-words caps the chain, default 8)
+    #[arg(long, conflicts_with_all = ["markov", "pcfg"])]
+    pub prince: bool,
+}
+
+// Job files: accepted after a subcommand name, or before it when no other
+// option comes first. Options given on the command line override the file,
+// which overrides the preset.
+#[derive(clap::Args)]
+pub struct ConfigArgs {
+    /// Read options from a TOML job file (keys are option names, e.g. min_length = 8)
+    #[arg(long, value_name = "FILE", global = true)]
+    pub config: Option<String>,
+
+    /// Start from a built-in job preset
+    #[arg(long, value_name = "NAME", value_parser = PRESET_NAMES, global = true)]
+    pub preset: Option<String>,
+
+    /// Write the effective configuration to a TOML job file and exit
+    #[arg(long, value_name = "FILE", global = true)]
+    pub save_config: Option<String>,
+}
+
+// Options that decide where and how candidates are written. (A doc comment
+// here would replace the program description in --help.)
+#[derive(clap::Args)]
+pub struct OutputArgs {
+    /// Output file path ("-" writes to stdout)
+    #[arg(short = 'o', long = "output", default_value = "passwords.txt")]
+    pub path: String,
+
+    /// Stream candidates to stdout (same as --output -)
+    #[arg(long)]
+    pub stdout: bool,
+
+    /// Compress output (default: inferred from .gz, .zst or .xz extension)
+    #[arg(long, value_enum)]
+    pub compress: Option<Compression>,
+
+    /// Write hash:plaintext lines using this unsalted hash instead of plaintext
+    #[arg(long, value_enum)]
+    pub hash: Option<HashAlgorithm>,
+
+    /// With --hash, write only the hash on each line
+    #[arg(long, requires = "hash")]
+    pub hash_only: bool,
+
+    /// Roll over to a new part file after this much uncompressed output (e.g. 4G, 500M)
+    #[arg(long, value_parser = parse_size, conflicts_with = "split_lines")]
+    pub split_size: Option<u64>,
+
+    /// Roll over to a new part file after this many passwords (e.g. 100M, 50K)
+    #[arg(long, value_parser = parse_count)]
+    pub split_lines: Option<u64>,
+
+    /// Append to output file instead of overwriting
+    #[arg(long)]
+    pub append: bool,
+}
+
+impl KeyspaceArgs {
     pub fn get_length_constraints(&self) -> (usize, usize) {
         if self.wpa2 {
             (8, 63)
@@ -65,10 +446,298 @@ impl Args {
     }
 
     pub fn get_max_words(&self) -> usize {
-        if self.max_words == 0 {
-            usize::MAX // Unlimited
+        max_words_limit(self.max_words)
+    }
+
+    /// The --transforms chain, without padding under --no-special-chars.
+    pub fn get_transforms(&self) -> TransformChain {
+        if self.no_special_chars {
+            self.transforms.clone().without("pad")
+        } else {
+            self.transforms.clone()
+        }
+    }
+
+    pub fn combinatorial_config(&self) -> CombinatorialConfig {
+        let (min_len, max_len) = self.get_length_constraints();
+        CombinatorialConfig {
+            max_words: self.get_max_words(),
+            transforms: self.get_transforms(),
+            min_len,
+            max_len,
+        }
+    }
+}
+
+impl GenerationArgs {
+    /// Build the password policy from a preset or policy file, with the
+    /// individual requirement flags layered on top. None if no rule is set.
+    pub fn get_policy(&self) -> Result<Option<PasswordPolicy>> {
+        let mut policy = match (&self.policy_file, self.policy) {
+            (Some(path), _) => PasswordPolicy::from_file(path)?,
+            (None, Some(preset)) => PasswordPolicy::preset(preset),
+            (None, None) => PasswordPolicy::default(),
+        };
+
+        if let Some(n) = self.require_upper {
+            policy.min_upper = n;
+        }
+        if let Some(n) = self.require_lower {
+            policy.min_lower = n;
+        }
+        if let Some(n) = self.require_digit {
+            policy.min_digits = n;
+        }
+        if let Some(n) = self.require_symbol {
+            policy.min_symbols = n;
+        }
+        if let Some(n) = self.min_classes {
+            policy.min_classes = n;
+        }
+        if self.max_repeat.is_some() {
+            policy.max_repeat = self.max_repeat;
+        }
+        policy.forbidden.extend(self.forbid.iter().cloned());
+
+        if policy == PasswordPolicy::default() {
+            Ok(None)
+        } else {
+            Ok(Some(policy))
+        }
+    }
+
+    /// Check option combinations that clap cannot express on its own.
+    pub fn validate(&self) -> Result<()> {
+        compile_regex_filters(&self.match_patterns, &self.reject_patterns)?;
+        if !(self.dedup_fp_rate > 0.0 && self.dedup_fp_rate < 1.0) {
+            return Err(Error::config("--dedup-fp-rate must be between 0 and 1"));
+        }
+        let (min_len, max_len) = self.keyspace.get_length_constraints();
+        if min_len > max_len {
+            return Err(Error::config(format!("--min-length {} is greater than --max-length {}", min_len, max_len)));
+        }
+        Ok(())
+    }
+
+    pub fn get_status_events(&self) -> Option<StatusEvents> {
+        match &self.status_file {
+            Some(path) => Some(StatusEvents::File(path.clone())),
+            None if self.status_json => Some(StatusEvents::Stderr),
+            None => None,
+        }
+    }
+}
+
+impl ProfileArgs {
+    pub fn profile(&self) -> TargetProfile {
+        TargetProfile {
+            first_name: self.first_name.clone(),
+            last_name: self.last_name.clone(),
+            nickname: self.nickname.clone(),
+            partner: self.partner.clone(),
+            children: self.child.clone(),
+            pets: self.pet.clone(),
+            company: self.company.clone(),
+            dates: self.date.clone(),
+            keywords: self.keyword.clone(),
+        }
+    }
+}
+
+fn max_words_limit(max_words: usize) -> usize {
+    if max_words == 0 {
+        usize::MAX // Unlimited
+    } else {
+        max_words
+    }
+}
+
+impl OutputArgs {
+    pub fn get_output(&self) -> &str {
+        if self.stdout {
+            STDOUT_PATH
         } else {
-            self.max_words
+            &self.path
+        }
+    }
+
+    pub fn get_compression(&self) -> Compression {
+        self.compress
+            .unwrap_or_else(|| Compression::from_path(self.get_output()))
+    }
+
+    pub fn get_hash_output(&self) -> Option<HashOutput> {
+        self.hash.map(|algorithm| HashOutput {
+            algorithm,
+            hash_only: self.hash_only,
+        })
+    }
+
+    pub fn get_split(&self) -> Option<SplitLimit> {
+        match (self.split_size, self.split_lines) {
+            (Some(bytes), _) => Some(SplitLimit::Bytes(bytes)),
+            (None, Some(lines)) => Some(SplitLimit::Lines(lines)),
+            (None, None) => None,
         }
     }
-}
\ No newline at end of file
+
+    /// Check option combinations that clap cannot express on its own.
+    pub fn validate(&self) -> Result<()> {
+        if self.get_split().is_some() {
+            if is_stdout(self.get_output()) {
+                return Err(Error::config("--split-size/--split-lines cannot be used when writing to stdout"));
+            }
+            if self.append {
+                return Err(Error::config("--split-size/--split-lines cannot be combined with --append"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a byte size with an optional binary suffix: 512, 64K, 500M, 4G, 1T.
+/// Used as a clap value parser, so a bad value exits like any bad argument.
+pub fn parse_size(value: &str) -> std::result::Result<u64, String> {
+    parse_with_suffix(value.trim_end_matches(['B', 'b']), 1024)
+}
+
+/// Parse a count with an optional decimal suffix: 5000, 50K, 100M, 1G.
+pub fn parse_count(value: &str) -> std::result::Result<u64, String> {
+    parse_with_suffix(value, 1000)
+}
+
+fn parse_with_suffix(value: &str, base: u64) -> std::result::Result<u64, String> {
+    let value = value.trim();
+    let (digits, exponent) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1),
+        Some('M') => (&value[..value.len() - 1], 2),
+        Some('G') => (&value[..value.len() - 1], 3),
+        Some('T') => (&value[..value.len() - 1], 4),
+        _ => (value, 0),
+    };
+
+    let number: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid number '{}'", value))?;
+    let result = number
+        .checked_mul(base.pow(exponent))
+        .ok_or_else(|| format!("'{}' is too large", value))?;
+
+    if result == 0 {
+        return Err("must be greater than zero".to_string());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("64K").unwrap(), 64 * 1024);
+        assert_eq!(parse_size("4G").unwrap(), 4 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("500MB").unwrap(), 500 * 1024 * 1024);
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("lots").is_err());
+    }
+
+    #[test]
+    fn test_crack_subcommand_takes_generation_options() {
+        let args = Args::try_parse_from([
+            "p455w0rd", "crack", "--hashes", "dump.txt", "--hash-type", "ntlm", "--max-words", "2", "admin", "pass",
+        ])
+        .unwrap();
+
+        match args.command {
+            Some(Command::Crack(crack)) => {
+                assert_eq!(crack.hashes, "dump.txt");
+                assert_eq!(crack.hash_type, HashAlgorithm::Ntlm);
+                assert_eq!(crack.generation.keyspace.words.words, vec!["admin", "pass"]);
+                assert_eq!(crack.generation.keyspace.get_max_words(), 2);
+            }
+            _ => panic!("expected crack subcommand"),
+        }
+
+        let args = Args::try_parse_from(["p455w0rd", "-o", "out.txt", "admin"]).unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.output.get_output(), "out.txt");
+        assert_eq!(args.generation.keyspace.words.words, vec!["admin"]);
+    }
+
+    #[test]
+    fn test_subcommands_have_their_own_options() {
+        let args = Args::try_parse_from(["p455w0rd", "generate", "-o", "out.txt", "--max-words", "2", "admin"]).unwrap();
+        match args.command {
+            Some(Command::Generate(generate)) => {
+                assert_eq!(generate.output.get_output(), "out.txt");
+                assert_eq!(generate.generation.keyspace.get_max_words(), 2);
+            }
+            _ => panic!("expected generate subcommand"),
+        }
+
+        let args = Args::try_parse_from(["p455w0rd", "analyze", "--json", "admin", "pass"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Analyze(ref analyze)) if analyze.json));
+        // Analysis counts the same keyspace generation would produce
+        let args = Args::try_parse_from(["p455w0rd", "analyze", "--wpa2", "admin"]).unwrap();
+        let Some(Command::Analyze(analyze)) = args.command else { panic!("expected analyze") };
+        assert_eq!(analyze.keyspace.get_length_constraints(), (8, 63));
+
+        // Output options belong to the subcommands that write candidates
+        assert!(Args::try_parse_from(["p455w0rd", "analyze", "-o", "out.txt", "admin"]).is_err());
+        assert!(Args::try_parse_from(["p455w0rd", "stats", "--max-words", "2", "list.txt"]).is_err());
+
+        let args = Args::try_parse_from([
+            "p455w0rd", "profile", "--first-name", "John", "--pet", "rex", "--pet", "fido", "--date", "1985-03-12",
+        ])
+        .unwrap();
+        match args.command {
+            Some(Command::Profile(profile)) => {
+                let target = profile.profile();
+                assert_eq!(target.first_name.as_deref(), Some("John"));
+                assert_eq!(target.pets, vec!["rex", "fido"]);
+            }
+            _ => panic!("expected profile subcommand"),
+        }
+
+        assert!(Args::try_parse_from(["p455w0rd", "rules", "-i", "words.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_dry_run_format() {
+        let args = Args::try_parse_from(["p455w0rd", "--dry-run", "--format", "yaml", "admin"]).unwrap();
+        assert!(args.generation.dry_run);
+        assert_eq!(args.generation.format, ReportFormat::Yaml);
+        assert!(Args::try_parse_from(["p455w0rd", "--format", "yaml", "admin"]).is_err());
+    }
+
+    #[test]
+    fn test_status_event_options() {
+        let args = Args::try_parse_from(["p455w0rd", "--status-file", "events.jsonl", "admin"]).unwrap();
+        assert_eq!(args.generation.get_status_events(), Some(StatusEvents::File("events.jsonl".to_string())));
+        let args = Args::try_parse_from(["p455w0rd", "crack", "--hashes", "h.txt", "--hash-type", "md5", "--status-json", "admin"]).unwrap();
+        let Some(Command::Crack(crack)) = args.command else { panic!("expected crack") };
+        assert_eq!(crack.generation.get_status_events(), Some(StatusEvents::Stderr));
+        assert!(Args::try_parse_from(["p455w0rd", "--status-json", "--status-file", "e.jsonl", "admin"]).is_err());
+    }
+
+    #[test]
+    fn test_transform_options() {
+        let args = Args::try_parse_from(["p455w0rd", "--transforms", "case,leet,pad", "--no-special-chars", "admin"]).unwrap();
+        assert_eq!(args.generation.keyspace.get_transforms().names(), ["case", "leet"]);
+        assert_eq!(args.generation.keyspace.combinatorial_config().transforms.names(), ["case", "leet"]);
+        assert!(Args::try_parse_from(["p455w0rd", "--transforms", "pad,leet", "admin"]).is_err());
+        let args = Args::try_parse_from(["p455w0rd", "analyze", "--max-length", "12", "admin"]).unwrap();
+        let Some(Command::Analyze(analyze)) = args.command else { panic!("expected analyze") };
+        assert_eq!(analyze.keyspace.combinatorial_config().max_len, 12);
+        assert_eq!(analyze.keyspace.combinatorial_config().transforms.names(), ["leet", "case", "pad"]);
+    }
+
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(parse_count("5000").unwrap(), 5000);
+        assert_eq!(parse_count("50k").unwrap(), 50_000);
+        assert_eq!(parse_count("100M").unwrap(), 100_000_000);
+    }
+}
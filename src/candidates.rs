@@ -0,0 +1,463 @@
+use crate::filter::CandidateFilter;
+use crate::transform::TransformChain;
+use itertools::{Itertools, Permutations};
+use std::ops::{ControlFlow, Range};
+
+/// Builder for [`PasswordGenerator`]. Defaults match the CLI: lengths 4
+/// to 20, every word count, the leet, case and pad transforms, no limit.
+#[derive(Debug)]
+pub struct PasswordGeneratorBuilder {
+    words: Vec<String>,
+    min_len: usize,
+    max_len: usize,
+    max_words: usize,
+    special_chars: bool,
+    transforms: TransformChain,
+    limit: usize,
+    filter: CandidateFilter,
+}
+
+impl PasswordGeneratorBuilder {
+    /// Shortest candidate in bytes.
+    pub fn min_length(mut self, min_len: usize) -> Self {
+        self.min_len = min_len;
+        self
+    }
+
+    /// Longest candidate in bytes.
+    pub fn max_length(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Most words combined into one candidate; 0 means all of them.
+    pub fn max_words(mut self, max_words: usize) -> Self {
+        self.max_words = max_words;
+        self
+    }
+
+    /// Whether to pad combinations with special characters; false drops
+    /// the `pad` transform from the chain.
+    pub fn special_chars(mut self, special_chars: bool) -> Self {
+        self.special_chars = special_chars;
+        self
+    }
+
+    /// Transforms applied to words and combinations, in order.
+    pub fn transforms(mut self, transforms: TransformChain) -> Self {
+        self.transforms = transforms;
+        self
+    }
+
+    /// Stop after this many accepted candidates; 0 means no limit.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Policy, exclusion and regex checks every candidate must pass.
+    pub fn filter(mut self, filter: CandidateFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn build(self) -> PasswordGenerator {
+        // Duplicate words would only repeat candidates; keep the first of each
+        let mut words = self.words;
+        let mut seen = std::collections::HashSet::new();
+        words.retain(|word| seen.insert(word.clone()));
+
+        let max_words = if self.max_words == 0 { words.len() } else { self.max_words.min(words.len()) };
+        let transforms = if self.special_chars { self.transforms } else { self.transforms.without("pad") };
+        let variants = words.iter().map(|word| transforms.word_variants(word)).collect();
+
+        PasswordGenerator {
+            words,
+            variants,
+            transforms,
+            min_len: self.min_len,
+            max_len: self.max_len,
+            max_words,
+            limit: self.limit,
+            filter: self.filter,
+            k: 0,
+            permutations: None,
+            permutation: Vec::new(),
+            odometer: None,
+            base: String::new(),
+            has_base: false,
+            outputs: Vec::new(),
+            output_count: 0,
+            next_output: 0,
+            accepted: 0,
+        }
+    }
+}
+
+/// Enumerates candidates from a word list without any file or terminal
+/// I/O: every ordering of 1 to `max_words` distinct words, every variant
+/// the word transforms make of each word, and every output of the
+/// candidate transforms for each combination, keeping only candidates
+/// within the length bounds that pass the filter.
+///
+/// Use it as an `Iterator<Item = String>`, or through `for_each_candidate`
+/// to borrow each candidate instead of allocating it. Callers that track
+/// progress can step through word permutations with `next_permutation`
+/// and `next_in_permutation`.
+#[derive(Debug)]
+pub struct PasswordGenerator {
+    words: Vec<String>,
+    /// Word-transform variants of each word, computed once
+    variants: Vec<Vec<String>>,
+    transforms: TransformChain,
+    min_len: usize,
+    max_len: usize,
+    max_words: usize,
+    limit: usize,
+    filter: CandidateFilter,
+    /// Word count of the current tier; 0 before the first permutation
+    k: usize,
+    permutations: Option<Permutations<Range<usize>>>,
+    /// Word indices of the current permutation
+    permutation: Vec<usize>,
+    /// Variant index per word of the current base combination; None before the first
+    odometer: Option<Vec<usize>>,
+    base: String,
+    has_base: bool,
+    /// Candidate-transform outputs for `base`; only the first
+    /// `output_count` are current, the rest are kept for their buffers
+    outputs: Vec<String>,
+    output_count: usize,
+    next_output: usize,
+    accepted: usize,
+}
+
+impl PasswordGenerator {
+    pub fn builder(words: Vec<String>) -> PasswordGeneratorBuilder {
+        PasswordGeneratorBuilder {
+            words,
+            min_len: 4,
+            max_len: 20,
+            max_words: 0,
+            special_chars: true,
+            transforms: TransformChain::default(),
+            limit: 0,
+            filter: CandidateFilter::default(),
+        }
+    }
+
+    /// Distinct input words, in first-seen order.
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// Word counts that will be generated, `1..=max_words`.
+    pub fn max_words(&self) -> usize {
+        self.max_words
+    }
+
+    /// The filter, for its rejection counts.
+    pub fn candidate_filter(&self) -> &CandidateFilter {
+        &self.filter
+    }
+
+    /// Candidates accepted so far.
+    pub fn accepted(&self) -> usize {
+        self.accepted
+    }
+
+    fn limit_reached(&self) -> bool {
+        self.limit > 0 && self.accepted >= self.limit
+    }
+
+    /// Move on to the next ordering of words, returning its word count,
+    /// or None when every permutation is done or the limit is reached.
+    /// Candidates left in the current permutation are dropped.
+    pub fn next_permutation(&mut self) -> Option<usize> {
+        if self.limit_reached() {
+            return None;
+        }
+        loop {
+            if let Some(next) = self.permutations.as_mut().and_then(Iterator::next) {
+                self.permutation = next;
+                self.odometer = None;
+                self.has_base = false;
+                return Some(self.k);
+            }
+            if self.k >= self.max_words {
+                self.permutations = None;
+                return None;
+            }
+            self.k += 1;
+            self.permutations = Some((0..self.words.len()).permutations(self.k));
+        }
+    }
+
+    /// Words of the current permutation, in order.
+    pub fn permutation(&self) -> Vec<&String> {
+        self.permutation.iter().map(|&i| &self.words[i]).collect()
+    }
+
+    /// Abandon the rest of the current word-count tier; the next call to
+    /// `next_permutation` starts the following one.
+    pub fn skip_tier(&mut self) {
+        self.permutations = None;
+        self.permutation.clear();
+        self.odometer = None;
+        self.has_base = false;
+    }
+
+    /// Next accepted candidate of the current permutation, or None when
+    /// the permutation is exhausted or the limit is reached.
+    pub fn next_in_permutation(&mut self) -> Option<&str> {
+        if self.advance() {
+            Some(&self.outputs[self.next_output - 1])
+        } else {
+            None
+        }
+    }
+
+    /// Next accepted candidate, moving through permutations as needed.
+    pub fn next_candidate(&mut self) -> Option<&str> {
+        loop {
+            if self.advance() {
+                return Some(&self.outputs[self.next_output - 1]);
+            }
+            self.next_permutation()?;
+        }
+    }
+
+    /// Call `f` with every remaining candidate, borrowed rather than
+    /// allocated, until it returns `ControlFlow::Break`.
+    pub fn for_each_candidate(&mut self, mut f: impl FnMut(&str) -> ControlFlow<()>) {
+        while let Some(candidate) = self.next_candidate() {
+            if f(candidate).is_break() {
+                break;
+            }
+        }
+    }
+
+    /// Step to the next accepted candidate within the current permutation,
+    /// leaving it just before `self.next_output`.
+    fn advance(&mut self) -> bool {
+        if self.limit_reached() || self.permutation.is_empty() {
+            return false;
+        }
+
+        loop {
+            while self.has_base && self.next_output < self.output_count {
+                let candidate = &self.outputs[self.next_output];
+                self.next_output += 1;
+
+                if (self.min_len..=self.max_len).contains(&candidate.len()) && self.filter.accept(candidate) {
+                    self.accepted += 1;
+                    return true;
+                }
+            }
+
+            if !self.next_base() {
+                return false;
+            }
+        }
+    }
+
+    /// Step the odometer to the next base combination and run the candidate
+    /// transforms on it. Combinations shorter than the minimum length are
+    /// skipped, and so are those already too long when the transforms can
+    /// only make them longer.
+    fn next_base(&mut self) -> bool {
+        loop {
+            let odometer = match &mut self.odometer {
+                None => {
+                    if self.permutation.iter().any(|&word| self.variants[word].is_empty()) {
+                        self.has_base = false;
+                        return false;
+                    }
+                    self.odometer.insert(vec![0; self.permutation.len()])
+                }
+                Some(odometer) => {
+                    // Last word varies fastest
+                    let mut i = odometer.len();
+                    loop {
+                        if i == 0 {
+                            self.has_base = false;
+                            return false;
+                        }
+                        i -= 1;
+                        odometer[i] += 1;
+                        if odometer[i] < self.variants[self.permutation[i]].len() {
+                            break;
+                        }
+                        odometer[i] = 0;
+                    }
+                    odometer
+                }
+            };
+
+            self.base.clear();
+            for (&word, &variant) in self.permutation.iter().zip(odometer.iter()) {
+                self.base.push_str(&self.variants[word][variant]);
+            }
+            if self.base.len() < self.min_len || (self.base.len() > self.max_len && self.transforms.only_lengthens()) {
+                continue;
+            }
+
+            // Reuse the output buffers from the previous combination
+            let (outputs, count) = (&mut self.outputs, &mut self.output_count);
+            *count = 0;
+            self.transforms.apply_candidate(&self.base, &mut |output| {
+                match outputs.get_mut(*count) {
+                    Some(buffer) => {
+                        buffer.clear();
+                        buffer.push_str(output);
+                    }
+                    None => outputs.push(output.to_string()),
+                }
+                *count += 1;
+            });
+            self.has_base = true;
+            self.next_output = 0;
+            return true;
+        }
+    }
+}
+
+/// A generator the streaming pipeline can drive. Candidates come in
+/// groups (a word permutation, one length of a Markov level) within
+/// numbered tiers; progress is reported per group and the run controls
+/// skip whole tiers.
+pub trait CandidateSource {
+    /// Move on to the next group, returning its 1-based tier, or None when
+    /// every group is done. Candidates left in the current group are dropped.
+    fn next_group(&mut self) -> Option<usize>;
+
+    /// What the current group is made of, for the status display.
+    fn group(&self) -> Vec<&String>;
+
+    /// Next accepted candidate of the current group.
+    fn next_in_group(&mut self) -> Option<&str>;
+
+    /// Abandon the rest of the current tier.
+    fn skip_tier(&mut self);
+
+    /// The filter, for its rejection counts.
+    fn candidate_filter(&self) -> &CandidateFilter;
+}
+
+impl CandidateSource for PasswordGenerator {
+    fn next_group(&mut self) -> Option<usize> {
+        self.next_permutation()
+    }
+
+    fn group(&self) -> Vec<&String> {
+        self.permutation()
+    }
+
+    fn next_in_group(&mut self) -> Option<&str> {
+        self.next_in_permutation()
+    }
+
+    fn skip_tier(&mut self) {
+        PasswordGenerator::skip_tier(self)
+    }
+
+    fn candidate_filter(&self) -> &CandidateFilter {
+        PasswordGenerator::candidate_filter(self)
+    }
+}
+
+impl Iterator for PasswordGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.next_candidate().map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exclude::ExclusionList;
+    use crate::policy::PasswordPolicy;
+    use crate::transform::{Pad, Transform};
+
+    #[test]
+    fn test_iterator_and_for_each_agree() {
+        let words = vec!["xy".to_string(), "zw".to_string(), "xy".to_string()];
+        let collected: Vec<String> = PasswordGenerator::builder(words.clone()).min_length(1).build().collect();
+
+        let mut borrowed = Vec::new();
+        PasswordGenerator::builder(words).min_length(1).build().for_each_candidate(|candidate| {
+            borrowed.push(candidate.to_string());
+            ControlFlow::Continue(())
+        });
+
+        assert_eq!(collected, borrowed);
+        assert_eq!(collected[0], "XY");
+        assert!(collected.contains(&"!@xy".to_string()));
+        assert!(collected.contains(&"ZWxy%".to_string()));
+        // Each of the 2 distinct words has 3 case variants and no leet letters; 2 orderings of both
+        let bases = 3 + 3 + 2 * 9;
+        assert_eq!(collected.len() as u64, bases * Pad::default().count(""));
+    }
+
+    #[test]
+    fn test_lengths_limit_and_filter() {
+        let words = vec!["admin".to_string(), "root".to_string()];
+        let mut generator = PasswordGenerator::builder(words.clone())
+            .min_length(5)
+            .max_length(8)
+            .max_words(1)
+            .build();
+        let candidates: Vec<String> = generator.by_ref().collect();
+        assert!(candidates.iter().all(|c| (5..=8).contains(&c.len())));
+        assert!(candidates.contains(&"!admin".to_string()));
+        assert!(candidates.contains(&"admin".to_string()));
+        // Combinations shorter than the minimum are not padded up to it
+        assert!(!candidates.iter().any(|c| c.to_lowercase().contains("r00t") || c.to_lowercase().contains("root")));
+        assert_eq!(generator.accepted(), candidates.len());
+
+        let limited: Vec<String> = PasswordGenerator::builder(words.clone()).limit(7).build().collect();
+        assert_eq!(limited.len(), 7);
+
+        let policy = PasswordPolicy {
+            min_upper: 1,
+            ..Default::default()
+        };
+        let filter = CandidateFilter::new(ExclusionList::default(), Some(policy), Vec::new());
+        let mut generator = PasswordGenerator::builder(words).special_chars(false).filter(filter).build();
+        assert!(generator.by_ref().all(|c| c.chars().any(|ch| ch.is_ascii_uppercase())));
+        assert!(generator.candidate_filter().policy_rejected() > 0);
+    }
+
+    #[test]
+    fn test_permutation_stepping_and_skip() {
+        let words = vec!["b".to_string(), "c".to_string(), "d".to_string()];
+        let mut generator = PasswordGenerator::builder(words).min_length(1).special_chars(false).build();
+
+        assert_eq!(generator.next_permutation(), Some(1));
+        assert_eq!(generator.permutation(), vec!["b"]);
+        assert_eq!(generator.next_in_permutation(), Some("B"));
+        generator.skip_tier();
+
+        assert_eq!(generator.next_permutation(), Some(2));
+        assert_eq!(generator.permutation(), vec!["b", "c"]);
+        let mut count = 0;
+        while generator.next_in_permutation().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn test_custom_transform_chain() {
+        let words = vec!["ab".to_string(), "ol".to_string()];
+        let chain = TransformChain::parse("leet").unwrap();
+        let candidates: Vec<String> = PasswordGenerator::builder(words).min_length(1).transforms(chain).build().collect();
+
+        // No case forms or padding: 2 leet forms of "ab", 4 of "ol", 8 per ordering of both
+        assert_eq!(candidates.len(), 2 + 4 + 2 * 8);
+        assert_eq!(&candidates[..4], ["4b", "ab", "01", "0l"]);
+        assert!(candidates.contains(&"o14b".to_string()));
+    }
+}
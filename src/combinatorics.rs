@@ -1,19 +1,62 @@
+use crate::error::{Error, Result};
+use crate::policy::{ClassCounts, PasswordPolicy};
+use crate::transform::TransformChain;
 use itertools::Itertools;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 
+/// What to count: the same settings the generator runs with.
+#[derive(Debug, Clone)]
 pub struct CombinatorialConfig {
     pub max_words: usize,
-    pub include_special_chars: bool,
+    pub transforms: TransformChain,
+    /// Candidates outside these byte lengths are not generated, so not counted
+    pub min_len: usize,
+    pub max_len: usize,
 }
 
-#[derive(Debug, Clone)]
+impl Default for CombinatorialConfig {
+    /// Every word count and length, with the standard transforms.
+    fn default() -> Self {
+        CombinatorialConfig {
+            max_words: usize::MAX,
+            transforms: TransformChain::default(),
+            min_len: 0,
+            max_len: usize::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct CombinatorialAnalysis {
     pub total_combinations: u64,
     pub estimated_file_size_bytes: u64,
     pub breakdown: CombinationBreakdown,
 }
 
-#[derive(Debug, Clone)]
+impl CombinatorialAnalysis {
+    /// Candidates across every tier. Unlike `total_combinations`, which is
+    /// capped at a billion, this is the whole job; it saturates at `u64::MAX`.
+    pub fn keyspace(&self) -> u64 {
+        self.breakdown
+            .by_word_count
+            .iter()
+            .fold(0u64, |total, b| total.saturating_add(b.combinations))
+    }
+
+    /// Bytes the whole keyspace writes, a newline after each candidate. Like
+    /// `keyspace`, this is uncapped and saturates at `u64::MAX`.
+    pub fn keyspace_bytes(&self) -> u64 {
+        let bytes: f64 = self.breakdown.by_word_count.iter().map(|b| b.combinations as f64 * (b.average_length + 1.0)).sum();
+        // Float-to-int casts saturate
+        bytes as u64
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct CombinationBreakdown {
+    /// Transform names in the order they run
+    pub transforms: Vec<&'static str>,
     pub word_permutations: u64,
     pub leet_variants: u64,
     pub case_variants: u64,
@@ -21,7 +64,7 @@ pub struct CombinationBreakdown {
     pub by_word_count: Vec<WordCountBreakdown>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct WordCountBreakdown {
     pub word_count: usize,
     pub combinations: u64,
@@ -31,84 +74,75 @@ pub struct WordCountBreakdown {
 pub fn calculate_total_combinations(
     words: &[String],
     config: &CombinatorialConfig,
-) -> Result<CombinatorialAnalysis, String> {
+) -> Result<CombinatorialAnalysis> {
     if words.is_empty() {
-        return Err("No words provided for combinatorial analysis".to_string());
+        return Err(Error::config("No words provided for combinatorial analysis"));
+    }
+    if config.min_len > config.max_len {
+        return Err(Error::config(format!(
+            "Minimum length {} is greater than maximum length {}",
+            config.min_len, config.max_len
+        )));
     }
 
     // Remove duplicate words
     let unique_words: Vec<String> = words.iter().cloned().collect::<std::collections::HashSet<_>>().into_iter().collect();
     let n = unique_words.len();
+    let transforms = &config.transforms;
 
     // 1. Calculate word permutations
     let word_permutations = calculate_word_permutations(n, config.max_words)?;
 
-    // 2. Calculate leet variants for each word
-    let leet_variants_per_word: Vec<u64> = unique_words
-        .iter()
-        .map(|word| calculate_leet_variants(word))
-        .collect();
-
-    let total_leet_variants: u64 = leet_variants_per_word.iter().product();
-
-    // 3. Case variations (always 3 per variant)
-    let _case_variants = 3;
-
-    // 4. Special character padding
-    let special_char_variants = if config.include_special_chars {
-        calculate_special_char_variants()
-    } else {
-        1 // No padding
+    // 2-4. Reference figures for the built-in transforms, 1 when not in the chain
+    let total_leet_variants = match transforms.find("leet") {
+        Some(leet) => unique_words.iter().fold(1u64, |total, word| total.saturating_mul(leet.count(word))),
+        None => 1,
     };
+    let case_variants = if transforms.find("case").is_some() { 3 } else { 1 };
+    let special_char_variants = transforms.find("pad").map_or(1, |pad| pad.count(""));
 
     // Calculate breakdown by word count first (this gives us the accurate count)
-    let by_word_count = calculate_breakdown_by_word_count(
-        &unique_words,
-        &leet_variants_per_word,
-        config.max_words,
-        config.include_special_chars,
-    )?;
+    let by_word_count = calculate_breakdown_by_word_count(&unique_words, config)?;
 
     // Calculate total combinations from breakdown (more accurate)
-    let total_combinations = by_word_count
+    let uncapped = by_word_count
         .iter()
-        .map(|b| b.combinations)
-        .sum::<u64>()
-        .min(1_000_000_000); // Cap at reasonable number
+        .fold(0u64, |total, b| total.saturating_add(b.combinations));
+    let total_combinations = uncapped.min(1_000_000_000); // Cap at reasonable number
 
-    // Estimate file size (average 15 characters per password + newline)
-    let avg_password_length = estimate_average_password_length(&unique_words, config.include_special_chars);
-    let estimated_file_size_bytes = total_combinations
-        .checked_mul(avg_password_length as u64 + 1) // +1 for newline
-        .unwrap_or(u64::MAX);
+    // Estimate file size from the exact average length (+1 for newline)
+    let total_length: f64 = by_word_count.iter().map(|b| b.combinations as f64 * b.average_length).sum();
+    let avg_password_length = if uncapped > 0 { total_length / uncapped as f64 } else { 0.0 };
+    let estimated_file_size_bytes = (total_combinations as f64 * (avg_password_length + 1.0)) as u64;
 
     Ok(CombinatorialAnalysis {
         total_combinations,
         estimated_file_size_bytes,
         breakdown: CombinationBreakdown {
+            transforms: transforms.names(),
             word_permutations,  // Still useful for reference
             leet_variants: total_leet_variants,  // Still useful for reference
-            case_variants: 3,  // Theoretical maximum
+            case_variants,  // Theoretical maximum
             special_char_variants,
             by_word_count,
         },
     })
 }
 
-fn calculate_word_permutations(n: usize, max_words: usize) -> Result<u64, String> {
+fn calculate_word_permutations(n: usize, max_words: usize) -> Result<u64> {
     let mut total = 0u64;
 
     for k in 1..=max_words.min(n) {
         // Calculate permutations: P(n, k) = n! / (n - k)!
         let permutations = permutation_count(n, k)?;
         total = total.checked_add(permutations)
-            .ok_or_else(|| format!("Overflow calculating permutations for {} words", k))?;
+            .ok_or_else(|| Error::Overflow(format!("Overflow calculating permutations for {} words", k)))?;
     }
 
     Ok(total)
 }
 
-fn permutation_count(n: usize, k: usize) -> Result<u64, String> {
+fn permutation_count(n: usize, k: usize) -> Result<u64> {
     if k > n {
         return Ok(0);
     }
@@ -116,220 +150,201 @@ fn permutation_count(n: usize, k: usize) -> Result<u64, String> {
     let mut result = 1u64;
     for i in 0..k {
         result = result.checked_mul((n - i) as u64)
-            .ok_or_else(|| format!("Overflow in permutation calculation: P({}, {})", n, k))?;
+            .ok_or_else(|| Error::Overflow(format!("Overflow in permutation calculation: P({}, {})", n, k)))?;
     }
 
     Ok(result)
 }
 
-fn calculate_leet_variants(word: &str) -> u64 {
-    let replacements = [
-        ('a', '4'),
-        ('e', '3'),
-        ('i', '1'),
-        ('l', '1'),
-        ('o', '0'),
-        ('s', '5'),
-    ];
-
-    let replaceable_count = word
-        .to_lowercase()
-        .chars()
-        .filter(|&ch| replacements.iter().any(|&(from, _)| from == ch))
-        .count();
-
-    // 2^K possible leet combinations
-    if replaceable_count >= 64 {
-        return u64::MAX; // Would overflow, return max
+fn convolve_lengths(a: &BTreeMap<usize, u64>, b: &BTreeMap<usize, u64>) -> BTreeMap<usize, u64> {
+    let mut result = BTreeMap::new();
+    for (&la, &ca) in a {
+        for (&lb, &cb) in b {
+            let entry = result.entry(la + lb).or_insert(0u64);
+            *entry = entry.saturating_add(ca.saturating_mul(cb));
+        }
     }
-
-    1u64 << replaceable_count // 2^replaceable_count
+    result
 }
 
-fn calculate_special_char_variants() -> u64 {
-    let special_chars = ['!', '@', '#', '$', '%'];
-    let n = special_chars.len();
-
-    // No padding: 1
-    let mut total = 1u64;
-
-    // Single prefix: n variants
-    total = total.checked_add(n as u64).unwrap_or(u64::MAX);
-
-    // Single suffix: n variants
-    total = total.checked_add(n as u64).unwrap_or(u64::MAX);
+/// Count each word-count tier from length distributions: variant lengths
+/// add up across the words of a combination, the candidate transforms map
+/// combination lengths to candidate lengths, and only candidates within the
+/// length bounds are counted. Every ordering of a word set has the same
+/// distribution, so each set is weighted by k!.
+fn calculate_breakdown_by_word_count(
+    words: &[String],
+    config: &CombinatorialConfig,
+) -> Result<Vec<WordCountBreakdown>> {
+    let mut breakdown = Vec::new();
+    let n = words.len();
 
-    // Multiple padding: all permutations of 2-5 special chars (both prefix and suffix)
-    for k in 2..=n {
-        let permutations = permutation_count(n, k).unwrap_or(u64::MAX);
-        let doubled = permutations.checked_mul(2).unwrap_or(u64::MAX); // ×2 for prefix/suffix
-        total = total.checked_add(doubled).unwrap_or(u64::MAX);
+    let word_lengths: Vec<BTreeMap<usize, u64>> = words
+        .iter()
+        .map(|word| {
+            let mut lengths = BTreeMap::new();
+            for variant in config.transforms.word_variants(word) {
+                *lengths.entry(variant.len()).or_insert(0) += 1;
+            }
+            lengths
+        })
+        .collect();
 
-        if total == u64::MAX {
-            break;
+    for k in 1..=config.max_words.min(n) {
+        let orderings = permutation_count(k, k)?;
+
+        let mut bases = BTreeMap::new();
+        for combination in (0..n).combinations(k) {
+            let lengths = combination[1..]
+                .iter()
+                .fold(word_lengths[combination[0]].clone(), |lengths, &idx| convolve_lengths(&lengths, &word_lengths[idx]));
+            // Combinations shorter than the minimum are skipped, not padded up to it
+            for (length, count) in lengths.into_iter().filter(|&(length, _)| length >= config.min_len) {
+                let entry = bases.entry(length).or_insert(0u64);
+                *entry = entry.saturating_add(count.saturating_mul(orderings));
+            }
         }
-    }
 
-    total
-}
-
-pub fn calculate_actual_word_variants(word: &str) -> u64 {
-    let lower = word.to_lowercase();
-
-    // Generate all possible l33t combinations for this word
-    let leet_variants = generate_all_leet_for_word_combinatorics(&lower);
+        let (combinations, total_length) = config
+            .transforms
+            .candidate_lengths(&bases)
+            .into_iter()
+            .filter(|(length, _)| (config.min_len..=config.max_len).contains(length))
+            .fold((0u64, 0f64), |(combinations, total_length), (length, count)| {
+                (combinations.saturating_add(count), total_length + length as f64 * count as f64)
+            });
 
-    // For each l33t variant, add different capitalizations
-    let mut variants = Vec::new();
-    for leet_word in leet_variants {
-        variants.push(leet_word.clone());                    // lowercase
-        variants.push(capitalize_word_combinatorics(&leet_word));         // Capitalized
-        variants.push(leet_word.to_uppercase());            // UPPERCASE
+        breakdown.push(WordCountBreakdown {
+            word_count: k,
+            combinations,
+            average_length: if combinations > 0 { total_length / combinations as f64 } else { 0.0 },
+        });
     }
 
-    // Remove duplicates (same as generator)
-    variants.sort();
-    variants.dedup();
-
-    variants.len() as u64
+    Ok(breakdown)
 }
 
-fn generate_all_leet_for_word_combinatorics(word: &str) -> Vec<String> {
-    let replacements = [
-        ('a', '4'),
-        ('e', '3'),
-        ('i', '1'),
-        ('l', '1'),
-        ('o', '0'),
-        ('s', '5'),
-    ];
+/// Predicted number of candidates that pass a password policy.
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyEstimate {
+    pub survivors: u64,
+    /// False when the policy has repeat or substring rules, which are not
+    /// modelled, making `survivors` an upper bound
+    pub exact: bool,
+}
 
-    let chars: Vec<char> = word.chars().collect();
-    let replaceable_positions: Vec<usize> = chars
-        .iter()
-        .enumerate()
-        .filter(|(_, &ch)| replacements.iter().any(|&(from, _)| from == ch))
-        .map(|(i, _)| i)
-        .collect();
+/// Lengths and class counts of a candidate fragment, each capped just past
+/// the value the length bounds or the policy care about so distributions
+/// stay small: [bytes, characters, upper, lower, digits, symbols]. The
+/// generator's bounds count bytes, the policy's count characters.
+type Profile = [usize; 6];
+
+fn profile_caps(config: &CombinatorialConfig, policy: &PasswordPolicy) -> Profile {
+    let class_floor = usize::from(policy.min_classes > 0);
+    [
+        if config.max_len < usize::MAX { config.max_len + 1 } else { config.min_len },
+        policy.max_length.map(|max| max + 1).or(policy.min_length).unwrap_or(0),
+        policy.min_upper.max(policy.min_letters).max(class_floor),
+        policy.min_lower.max(policy.min_letters).max(class_floor),
+        policy.min_digits.max(class_floor),
+        policy.min_symbols.max(class_floor),
+    ]
+}
 
-    let mut variants = Vec::new();
-    let max_combinations = 1 << replaceable_positions.len();
+fn profile_of(fragment: &str, caps: &Profile) -> Profile {
+    let counts = ClassCounts::of(fragment);
+    let raw = [fragment.len(), fragment.chars().count(), counts.upper, counts.lower, counts.digits, counts.symbols];
+    std::array::from_fn(|i| raw[i].min(caps[i]))
+}
 
-    for combination in 0..max_combinations {
-        let mut result_chars = chars.clone();
-        for (bit_pos, &char_pos) in replaceable_positions.iter().enumerate() {
-            if (combination >> bit_pos) & 1 == 1 {
-                if let Some(&(_, replacement)) = replacements.iter().find(|&&(from, _)| from == result_chars[char_pos]) {
-                    result_chars[char_pos] = replacement;
-                }
-            }
+fn convolve(a: &HashMap<Profile, u64>, b: &HashMap<Profile, u64>, caps: &Profile) -> HashMap<Profile, u64> {
+    let mut result = HashMap::new();
+    for (pa, &ca) in a {
+        for (pb, &cb) in b {
+            let combined: Profile = std::array::from_fn(|i| (pa[i] + pb[i]).min(caps[i]));
+            let entry = result.entry(combined).or_insert(0u64);
+            *entry = entry.saturating_add(ca.saturating_mul(cb));
         }
-        variants.push(result_chars.iter().collect::<String>());
     }
-
-    variants
+    result
 }
 
-fn capitalize_word_combinatorics(word: &str) -> String {
-    if word.is_empty() {
-        return String::new();
+fn profile_passes(profile: &Profile, config: &CombinatorialConfig, policy: &PasswordPolicy) -> bool {
+    if !(config.min_len..=config.max_len).contains(&profile[0]) {
+        return false;
     }
-
-    let mut chars = word.chars();
-    if let Some(first) = chars.next() {
-        let uppercase_first = first.to_uppercase().collect::<String>();
-        if uppercase_first.len() == 1 && uppercase_first.starts_with(first) {
-            // No change needed, use Cow to avoid allocation
-            word.to_string()
-        } else {
-            // Capitalization needed
-            uppercase_first + &chars.collect::<String>()
-        }
-    } else {
-        String::new()
+    let length = profile[1];
+    if policy.min_length.is_some_and(|min| length < min) || policy.max_length.is_some_and(|max| length > max) {
+        return false;
     }
+    policy.allows_counts(&ClassCounts {
+        upper: profile[2],
+        lower: profile[3],
+        digits: profile[4],
+        symbols: profile[5],
+    })
 }
 
-
-fn calculate_breakdown_by_word_count(
+/// Predict how many candidates survive `policy`.
+///
+/// Lengths and class counts add up across the words and padding that make
+/// up a candidate, so the distribution of each is convolved per word set
+/// rather than enumerating candidates. Every ordering of a word set has the
+/// same distribution, so each combination is weighted by k!. The
+/// generator's length bounds apply on top of the policy's.
+pub fn estimate_policy_survivors(
     words: &[String],
-    _leet_variants_per_word: &[u64],
-    max_words: usize,
-    include_special_chars: bool,
-) -> Result<Vec<WordCountBreakdown>, String> {
-    let mut breakdown = Vec::new();
-    let n = words.len();
-
-    for k in 1..=max_words.min(n) {
-        let _permutations = permutation_count(n, k)?;
-
-        // Calculate actual leet variants and their case variations for k-word combinations
-        let total_combinations = if k == 1 {
-            // For single words, sum up variants for each word and multiply by special variants
-            let mut total_single_word_variants = 0u64;
-            for word_idx in 0..words.len() {
-                let word = &words[word_idx];
-                let actual_variants = calculate_actual_word_variants(word);
-                total_single_word_variants += actual_variants;
-            }
-
-            let special_variants = if include_special_chars { calculate_special_char_variants() } else { 1 };
-
-            total_single_word_variants
-                .checked_mul(special_variants)
-                .unwrap_or(u64::MAX)
-        } else {
-            // For multi-word combinations, calculate for all permutations
-            // Each permutation consists of k distinct words from the available n words
-            let mut total_combinations = 0u64;
-
-            // For each permutation of k distinct words
-            for indices in (0..words.len()).permutations(k) {
-                // Calculate cartesian product for this specific combination of words
-                let mut cartesian_product = 1u64;
-                for &idx in &indices {
-                    let word = &words[idx];
-                    let actual_variants = calculate_actual_word_variants(word);
-                    cartesian_product = cartesian_product.checked_mul(actual_variants).unwrap_or(u64::MAX);
-                }
+    config: &CombinatorialConfig,
+    policy: &PasswordPolicy,
+) -> PolicyEstimate {
+    let unique_words: Vec<String> = words.iter().cloned().collect::<std::collections::HashSet<_>>().into_iter().collect();
+    let n = unique_words.len();
+    let caps = profile_caps(config, policy);
 
-                total_combinations = total_combinations.checked_add(cartesian_product).unwrap_or(u64::MAX);
-                if total_combinations == u64::MAX {
-                    break;
-                }
+    let word_distributions: Vec<HashMap<Profile, u64>> = unique_words
+        .iter()
+        .map(|word| {
+            let mut distribution = HashMap::new();
+            for variant in config.transforms.word_variants(word) {
+                *distribution.entry(profile_of(&variant, &caps)).or_insert(0) += 1;
             }
+            distribution
+        })
+        .collect();
 
-            let special_variants = if include_special_chars { calculate_special_char_variants() } else { 1 };
-            total_combinations
-                .checked_mul(special_variants)
-                .unwrap_or(u64::MAX)
-        };
-
-        // Estimate average length for k-word combinations
-        let avg_word_length = words.iter().take(k).map(|w| w.len()).sum::<usize>() as f64 / k as f64;
-        let avg_length = avg_word_length * k as f64;
-
-        breakdown.push(WordCountBreakdown {
-            word_count: k,
-            combinations: total_combinations,
-            average_length: avg_length,
-        });
+    // Candidate transforms are taken to add the fragments they make from an
+    // empty combination, which is exactly what padding does
+    let mut padding = HashMap::new();
+    config.transforms.apply_candidate("", &mut |fragment| {
+        *padding.entry(profile_of(fragment, &caps)).or_insert(0u64) += 1;
+    });
+
+    let mut survivors = 0u64;
+    for k in 1..=config.max_words.min(n) {
+        let orderings = permutation_count(k, k).unwrap_or(u64::MAX);
+
+        for combination in (0..n).combinations(k) {
+            let mut bases = combination[1..]
+                .iter()
+                .fold(word_distributions[combination[0]].clone(), |bases, &idx| convolve(&bases, &word_distributions[idx], &caps));
+            // Combinations shorter than the minimum are skipped, not padded up to it
+            bases.retain(|profile, _| profile[0] >= config.min_len);
+            let distribution = convolve(&padding, &bases, &caps);
+
+            let passing: u64 = distribution
+                .iter()
+                .filter(|(profile, _)| profile_passes(profile, config, policy))
+                .map(|(_, &count)| count)
+                .fold(0u64, |acc, count| acc.saturating_add(count));
+
+            survivors = survivors.saturating_add(passing.saturating_mul(orderings));
+        }
     }
 
-    Ok(breakdown)
-}
-
-fn estimate_average_password_length(words: &[String], include_special_chars: bool) -> usize {
-    if words.is_empty() {
-        return 0;
+    PolicyEstimate {
+        survivors,
+        exact: policy.is_additive(),
     }
-
-    let avg_word_length = words.iter().map(|w| w.len()).sum::<usize>() / words.len();
-
-    // Estimate based on typical usage patterns
-    let multiplier = if include_special_chars { 1.5 } else { 1.2 };
-
-    (avg_word_length as f64 * multiplier) as usize
 }
 
 pub fn format_file_size(bytes: u64) -> String {
@@ -354,6 +369,31 @@ pub fn format_file_size(bytes: u64) -> String {
     }
 }
 
+pub fn format_duration(seconds: f64) -> String {
+    if !seconds.is_finite() {
+        return "forever".to_string();
+    }
+
+    const UNITS: &[(f64, &str)] = &[
+        (365.25 * 86400.0, "years"),
+        (86400.0, "days"),
+        (3600.0, "h"),
+        (60.0, "m"),
+    ];
+
+    for &(unit, name) in UNITS {
+        if seconds >= unit {
+            let value = seconds / unit;
+            return if name.len() > 1 {
+                format!("{:.1} {}", value, name)
+            } else {
+                format!("{:.1}{}", value, name)
+            };
+        }
+    }
+    format!("{:.1}s", seconds)
+}
+
 pub fn format_combination_count(count: u64) -> String {
     if count == u64::MAX {
         return "too many to count".to_string();
@@ -397,14 +437,15 @@ mod tests {
             .collect()
     }
 
-    fn verify_calculation_is_reasonable(words: &[String], max_words: usize, include_special_chars: bool) -> Result<(), String> {
+    fn verify_calculation_is_reasonable(words: &[String], max_words: usize, include_special_chars: bool) -> std::result::Result<(), String> {
         let config = CombinatorialConfig {
             max_words,
-            include_special_chars,
+            transforms: TransformChain::standard(include_special_chars),
+            ..Default::default()
         };
 
         // Calculate expected count
-        let analysis = calculate_total_combinations(words, &config)?;
+        let analysis = calculate_total_combinations(words, &config).map_err(|e| e.to_string())?;
         let expected_count = analysis.total_combinations;
 
         // Basic sanity checks
@@ -421,10 +462,10 @@ mod tests {
         // Check that special chars increase the count when enabled
         if include_special_chars {
             let config_no_special = CombinatorialConfig {
-                include_special_chars: false,
+                transforms: TransformChain::standard(false),
                 ..config
             };
-            let analysis_no_special = calculate_total_combinations(words, &config_no_special)?;
+            let analysis_no_special = calculate_total_combinations(words, &config_no_special).map_err(|e| e.to_string())?;
             if expected_count <= analysis_no_special.total_combinations {
                 return Err("Special chars should increase count".to_string());
             }
@@ -436,7 +477,7 @@ mod tests {
                 max_words: 1,
                 ..config
             };
-            let analysis_single = calculate_total_combinations(words, &config_single_word)?;
+            let analysis_single = calculate_total_combinations(words, &config_single_word).map_err(|e| e.to_string())?;
             if expected_count < analysis_single.total_combinations {
                 return Err("More max_words should not decrease total combinations".to_string());
             }
@@ -445,40 +486,6 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    fn test_calculate_leet_variants() {
-        // Word with no replaceable characters
-        assert_eq!(calculate_leet_variants("xyz"), 1);
-
-        // Word with one replaceable character
-        assert_eq!(calculate_leet_variants("a"), 2);
-        assert_eq!(calculate_leet_variants("e"), 2);
-        assert_eq!(calculate_leet_variants("i"), 2);
-
-        // Word with multiple replaceable characters
-        assert_eq!(calculate_leet_variants("admin"), 4); // a and i -> 2^2
-        assert_eq!(calculate_leet_variants("password"), 16); // a, s, s, o -> 2^4
-        assert_eq!(calculate_leet_variants("hello"), 16); // e, l, l, o -> 2^4
-        assert_eq!(calculate_leet_variants("aeiou"), 16); // a, e, i, o -> 2^4
-
-        // Case insensitive
-        assert_eq!(calculate_leet_variants("ADMIN"), 4);
-        assert_eq!(calculate_leet_variants("Admin"), 4);
-        assert_eq!(calculate_leet_variants("PASSWORD"), 16);
-    }
-
-    #[test]
-    fn test_special_char_variants() {
-        let variants = calculate_special_char_variants();
-
-        // Should include:
-        // - No padding: 1
-        // - Single prefix: 5
-        // - Single suffix: 5
-        // - Multiple padding: permutations of 2-5 chars
-        assert!(variants > 16); // At least the basic ones
-    }
-
     #[test]
     fn test_word_permutations() {
         // 1 word
@@ -504,6 +511,9 @@ mod tests {
         assert_eq!(permutation_count(5, 0).unwrap(), 1); // 1 way to choose nothing
         assert_eq!(permutation_count(0, 1).unwrap(), 0); // Can't choose 1 from 0
         assert_eq!(permutation_count(1, 0).unwrap(), 1); // 1 way to choose nothing
+
+        // 30! does not fit in 64 bits
+        assert!(matches!(permutation_count(30, 30), Err(Error::Overflow(_))));
     }
 
     #[test]
@@ -525,12 +535,23 @@ mod tests {
         assert_eq!(format_file_size(1_073_741_824), "1.0 GB");
     }
 
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(42.0), "42.0s");
+        assert_eq!(format_duration(90.0), "1.5m");
+        assert_eq!(format_duration(5400.0), "1.5h");
+        assert_eq!(format_duration(3.0 * 86400.0), "3.0 days");
+        assert_eq!(format_duration(2.0 * 365.25 * 86400.0), "2.0 years");
+        assert_eq!(format_duration(f64::INFINITY), "forever");
+    }
+
     #[test]
     fn test_full_combinatorial_analysis() {
         let words = vec!["admin".to_string(), "pass".to_string()];
         let config = CombinatorialConfig {
             max_words: 2,
-            include_special_chars: false,
+            transforms: TransformChain::standard(false),
+            ..Default::default()
         };
 
         let analysis = calculate_total_combinations(&words, &config).unwrap();
@@ -554,19 +575,88 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_counts_match_generator() {
+        use crate::candidates::PasswordGenerator;
+
+        let words = vec!["admin".to_string(), "pass".to_string(), "x".to_string()];
+        for (transforms, min_len, max_len) in [("leet,case,pad", 6, 10), ("case,pad", 1, 5), ("leet", 0, usize::MAX)] {
+            let config = CombinatorialConfig {
+                max_words: 2,
+                transforms: TransformChain::parse(transforms).unwrap(),
+                min_len,
+                max_len,
+            };
+            let analysis = calculate_total_combinations(&words, &config).unwrap();
+
+            let generator = PasswordGenerator::builder(words.clone())
+                .max_words(2)
+                .min_length(min_len)
+                .max_length(max_len)
+                .transforms(config.transforms.clone())
+                .build();
+            let (count, bytes) = generator.fold((0u64, 0u64), |(count, bytes), c| (count + 1, bytes + c.len() as u64 + 1));
+
+            assert_eq!(analysis.total_combinations, count, "{}", transforms);
+            assert!(analysis.estimated_file_size_bytes.abs_diff(bytes) <= 1, "{}", transforms);
+            assert!(analysis.keyspace_bytes().abs_diff(bytes) <= 1, "{}", transforms);
+        }
+    }
+
+    #[test]
+    fn test_keyspace_saturates_on_overflow() {
+        // Ten leet-able characters: 1024 variants a word, 1024^9 * 9! for all nine
+        let words: Vec<String> = (0..9).map(|i| "aeiolsaeio"[i..].to_string() + &"aeiolsaeio"[..i]).collect();
+        let config = CombinatorialConfig {
+            max_words: 9,
+            transforms: TransformChain::parse("leet").unwrap(),
+            ..Default::default()
+        };
+        let analysis = calculate_total_combinations(&words, &config).unwrap();
+
+        assert_eq!(analysis.breakdown.leet_variants, u64::MAX);
+        assert_eq!(analysis.breakdown.by_word_count[0].combinations, 9 * 1024);
+        assert_eq!(analysis.breakdown.by_word_count[8].combinations, u64::MAX);
+        assert_eq!(format_combination_count(analysis.breakdown.by_word_count[8].combinations), "too many to count");
+    }
+
+    #[test]
+    fn test_keyspace_bytes_exceed_capped_estimate() {
+        use crate::output::{check_disk_space, Compression};
+        use crate::test_support::TempPath;
+
+        // About 2.6e13 candidates of up to 40 characters, near a petabyte uncapped
+        let words: Vec<String> = ["aeiolsaeio", "eiolsaeioa", "iolsaeioae", "olsaeioaei"].map(String::from).to_vec();
+        let config = CombinatorialConfig {
+            transforms: TransformChain::parse("leet").unwrap(),
+            ..Default::default()
+        };
+        let analysis = calculate_total_combinations(&words, &config).unwrap();
+
+        assert_eq!(analysis.total_combinations, 1_000_000_000);
+        assert!(analysis.estimated_file_size_bytes < 50_000_000_000);
+        assert!(analysis.keyspace_bytes() > 1_000_000_000_000_000);
+
+        // The preflight only sees a shortfall when given the whole keyspace
+        let dir = TempPath::dir("keyspace_bytes");
+        let out = dir.join("out.txt");
+        assert!(check_disk_space(&out, false, Compression::None, analysis.keyspace_bytes()).unwrap().is_some());
+    }
+
     #[test]
     fn test_with_special_characters() {
         let words = vec!["admin".to_string()];
         let config = CombinatorialConfig {
             max_words: 1,
-            include_special_chars: true,
+            transforms: TransformChain::standard(true),
+            ..Default::default()
         };
 
         let analysis = calculate_total_combinations(&words, &config).unwrap();
 
         // Should have more combinations with special chars
         let config_no_special = CombinatorialConfig {
-            include_special_chars: false,
+            transforms: TransformChain::standard(false),
             ..config
         };
 
@@ -580,7 +670,8 @@ mod tests {
         let words = vec!["admin".to_string(), "admin".to_string(), "pass".to_string()];
         let config = CombinatorialConfig {
             max_words: 2,
-            include_special_chars: false,
+            transforms: TransformChain::standard(false),
+            ..Default::default()
         };
 
         let analysis = calculate_total_combinations(&words, &config).unwrap();
@@ -647,6 +738,81 @@ mod tests {
         verify_calculation_is_reasonable(&words, 3, true).unwrap();
     }
 
+    #[test]
+    fn test_policy_survivors_match_filtered_enumeration() {
+        use crate::policy::PolicyPreset;
+
+        let words = vec!["admin".to_string(), "pass".to_string()];
+        let config = CombinatorialConfig {
+            max_words: 2,
+            transforms: TransformChain::standard(true),
+            ..Default::default()
+        };
+
+        // An empty policy keeps everything
+        let everything = estimate_policy_survivors(&words, &config, &PasswordPolicy::default());
+        let analysis = calculate_total_combinations(&words, &config).unwrap();
+        assert_eq!(everything.survivors, analysis.total_combinations);
+
+        // Enumerate candidates the way the generator does and filter them
+        let policy = PasswordPolicy::preset(PolicyPreset::Ad);
+        let variants: Vec<Vec<String>> = words.iter().map(|w| config.transforms.word_variants(w)).collect();
+        let paddings: Vec<String> = {
+            let chars = ['!', '@', '#', '$', '%'];
+            let mut paddings = vec![String::new()];
+            for k in 1..=chars.len() {
+                for perm in chars.iter().permutations(k) {
+                    let fragment: String = perm.into_iter().collect();
+                    paddings.push(fragment.clone());
+                    paddings.push(fragment);
+                }
+            }
+            paddings
+        };
+        let mut bases: Vec<String> = variants.concat();
+        for a in &variants[0] {
+            for b in &variants[1] {
+                bases.push(format!("{}{}", a, b));
+                bases.push(format!("{}{}", b, a));
+            }
+        }
+        let expected = bases
+            .iter()
+            .flat_map(|base| paddings.iter().map(move |pad| format!("{}{}", pad, base)))
+            .filter(|candidate| policy.allows(candidate))
+            .count() as u64;
+
+        let estimate = estimate_policy_survivors(&words, &config, &policy);
+        assert!(estimate.exact);
+        assert_eq!(estimate.survivors, expected);
+
+        // Short combinations are skipped, the bounds count bytes and the policy characters
+        use crate::candidates::PasswordGenerator;
+        let words = vec!["admin".to_string(), "pass".to_string(), "café".to_string()];
+        let policy = PasswordPolicy {
+            min_upper: 1,
+            max_length: Some(9),
+            ..Default::default()
+        };
+        for (min_len, max_len) in [(4, 20), (6, 10)] {
+            let config = CombinatorialConfig {
+                max_words: 2,
+                transforms: TransformChain::parse("case,pad").unwrap(),
+                min_len,
+                max_len,
+            };
+            let expected = PasswordGenerator::builder(words.clone())
+                .max_words(2)
+                .min_length(min_len)
+                .max_length(max_len)
+                .transforms(config.transforms.clone())
+                .build()
+                .filter(|candidate| policy.allows(candidate))
+                .count() as u64;
+            assert_eq!(estimate_policy_survivors(&words, &config, &policy).survivors, expected, "{}..={}", min_len, max_len);
+        }
+    }
+
     // Stress test with known problematic patterns
     #[test]
     fn test_problematic_patterns() {
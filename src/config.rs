@@ -0,0 +1,405 @@
+use crate::args::Args;
+use crate::error::{Error, Result};
+use clap::error::ErrorKind;
+use clap::builder::Resettable;
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Command, CommandFactory, FromArgMatches};
+use std::ffi::OsString;
+use toml::{Table, Value};
+
+/// Built-in job presets for `--preset`, as embedded TOML.
+pub const PRESETS: [(&str, &str); 5] = [
+    ("quick", include_str!("../presets/quick.toml")),
+    ("thorough", include_str!("../presets/thorough.toml")),
+    ("ad-complexity", include_str!("../presets/ad-complexity.toml")),
+    ("pin", include_str!("../presets/pin.toml")),
+    ("router-default", include_str!("../presets/router-default.toml")),
+];
+
+/// Names of the built-in presets, in `PRESETS` order.
+pub const PRESET_NAMES: [&str; PRESETS.len()] = {
+    let mut names = [""; PRESETS.len()];
+    let mut i = 0;
+    while i < PRESETS.len() {
+        names[i] = PRESETS[i].0;
+        i += 1;
+    }
+    names
+};
+
+/// Options that select or save configuration rather than configure the job.
+const CONFIG_OPTIONS: [&str; 3] = ["config", "preset", "save_config"];
+
+/// Parse the command line, filling in every option it leaves unset from the
+/// `--preset` and then the `--config` file. Keys are option names in
+/// snake_case (`min_length`, `match`), plus `words` for the positional
+/// words. These options may also lead a subcommand (`--config job.toml
+/// stats list.txt`). Exits on `--help`, `--version` and argument errors,
+/// like `Args::parse`.
+pub fn parse_args(argv: impl IntoIterator<Item = impl Into<OsString>>) -> Result<(Args, ArgMatches)> {
+    let argv = hoist_config_options(argv.into_iter().map(Into::into).collect());
+    // A job file may supply required arguments, so those are checked on the merged line
+    let (matches, missing) = match Args::command().try_get_matches_from(&argv) {
+        Ok(matches) => (matches, None),
+        Err(e) if e.kind() == ErrorKind::MissingRequiredArgument => {
+            (Args::command().ignore_errors(true).try_get_matches_from(&argv).unwrap_or_else(|e| e.exit()), Some(e))
+        }
+        Err(e) => e.exit(),
+    };
+
+    let (command, sub_matches) = job_command(&matches);
+    let mut sources = Vec::new();
+    if let Some(name) = sub_matches.get_one::<String>("preset") {
+        sources.push((format!("preset '{}'", name), preset(name)?.to_string()));
+    }
+    if let Some(path) = sub_matches.get_one::<String>("config") {
+        sources.push((path.clone(), std::fs::read_to_string(path).map_err(|e| Error::input(path, e))?));
+    }
+    if sources.is_empty() {
+        if let Some(e) = missing {
+            e.exit();
+        }
+        return Ok((from_matches(&matches), matches));
+    }
+
+    let mut table = Table::new();
+    for (source, content) in &sources {
+        let file = parse_table(source, content)?;
+        for (key, value) in file {
+            check_key(&command, source, content, &key, &value)?;
+            table.insert(key, value);
+        }
+    }
+
+    // Options from files go before any `--`, their words after everything
+    let split = argv.iter().position(|arg| arg == "--").unwrap_or(argv.len());
+    let (mut merged, rest) = (argv[..split].to_vec(), argv[split..].to_vec());
+    let mut words = Vec::new();
+    for (key, value) in &table {
+        let arg = find_arg(&command, key).expect("checked above");
+        if sub_matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let values: Vec<String> = match value {
+            Value::Array(items) => items.iter().map(scalar).collect(),
+            other => vec![scalar(other)],
+        };
+        // Saved files list defaults too; passing them would trip `requires`
+        let defaults: Vec<String> = arg.get_default_values().iter().map(|v| v.to_string_lossy().into_owned()).collect();
+        if !defaults.is_empty() && values == defaults {
+            continue;
+        }
+        match (arg.get_long(), arg.get_action()) {
+            (None, _) => words.extend(values),
+            (Some(long), ArgAction::SetTrue) => {
+                if value.as_bool() == Some(true) {
+                    merged.push(format!("--{}", long).into());
+                }
+            }
+            (Some(long), _) => merged.extend(values.iter().map(|v| OsString::from(format!("--{}={}", long, v)))),
+        }
+    }
+    merged.extend(rest);
+    if !words.is_empty() {
+        if split == argv.len() {
+            merged.push("--".into());
+        }
+        merged.extend(words.into_iter().map(OsString::from));
+    }
+
+    let matches = Args::command().try_get_matches_from(&merged).unwrap_or_else(|e| e.exit());
+    Ok((from_matches(&matches), matches))
+}
+
+/// The effective configuration of a parsed command line as a TOML job file:
+/// every option with a value, including defaults, except unset flags.
+pub fn effective_config(matches: &ArgMatches) -> String {
+    let (command, sub_matches) = job_command(matches);
+    let mut table = Table::new();
+
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if CONFIG_OPTIONS.contains(&id) || matches!(arg.get_action(), ArgAction::Help | ArgAction::Version) {
+            continue;
+        }
+        let Some(raw) = sub_matches.get_raw(id) else { continue };
+        let values: Vec<String> = raw.map(|value| value.to_string_lossy().into_owned()).collect();
+        let value = match arg.get_action() {
+            ArgAction::SetTrue if values.iter().all(|v| v == "false") => continue,
+            ArgAction::SetTrue => Value::Boolean(true),
+            ArgAction::Append => Value::Array(values.iter().map(|v| typed(v)).collect()),
+            _ if arg.get_long().is_none() => Value::Array(values.iter().map(|v| typed(v)).collect()),
+            _ => typed(&values[0]),
+        };
+        table.insert(key_of(arg), value);
+    }
+
+    toml::to_string(&table).expect("a flat table of scalars and arrays always serializes")
+}
+
+/// Embedded TOML of the preset called `name`.
+pub fn preset(name: &str) -> Result<&'static str> {
+    PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map(|(_, content)| *content)
+        .ok_or_else(|| Error::config(format!("unknown preset '{}' (expected {})", name, PRESET_NAMES.join(", "))))
+}
+
+/// Move `--config`, `--preset` and `--save-config` given before a
+/// subcommand to just after its name. Any top-level option turns a
+/// following subcommand name into a word, so `--config job.toml stats
+/// list.txt` would otherwise generate with "stats" as a word.
+fn hoist_config_options(argv: Vec<OsString>) -> Vec<OsString> {
+    let command = Args::command();
+    let longs: Vec<&str> = command
+        .get_arguments()
+        .filter(|arg| CONFIG_OPTIONS.contains(&arg.get_id().as_str()))
+        .filter_map(|arg| arg.get_long())
+        .collect();
+
+    let mut end = 1;
+    while let Some(arg) = argv.get(end).and_then(|arg| arg.to_str()) {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, _)) => (flag, true),
+            None => (arg, false),
+        };
+        if !flag.strip_prefix("--").is_some_and(|long| longs.contains(&long)) {
+            break;
+        }
+        end += if inline { 1 } else { 2 };
+    }
+
+    let subcommand = argv.get(end).and_then(|arg| arg.to_str()).and_then(|name| command.find_subcommand(name));
+    if end == 1 || subcommand.is_none() {
+        return argv;
+    }
+    let mut hoisted = vec![argv[0].clone(), argv[end].clone()];
+    hoisted.extend_from_slice(&argv[1..end]);
+    hoisted.extend_from_slice(&argv[end + 1..]);
+    hoisted
+}
+
+fn from_matches(matches: &ArgMatches) -> Args {
+    Args::from_arg_matches(matches).unwrap_or_else(|e| e.exit())
+}
+
+/// The command whose options the job file sets, and its matches: the
+/// subcommand when one is given, otherwise the top level (`generate`).
+fn job_command(matches: &ArgMatches) -> (Command, &ArgMatches) {
+    let command = Args::command();
+    let (name, sub_matches) = matches.subcommand().unwrap_or(("generate", matches));
+    (command.find_subcommand(name).expect("parsed subcommand exists").clone(), sub_matches)
+}
+
+fn key_of(arg: &clap::Arg) -> String {
+    match arg.get_long() {
+        Some(long) => long.replace('-', "_"),
+        None => arg.get_id().as_str().to_string(),
+    }
+}
+
+fn find_arg<'a>(command: &'a Command, key: &str) -> Option<&'a clap::Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| !CONFIG_OPTIONS.contains(&arg.get_id().as_str()))
+        .filter(|arg| !matches!(arg.get_action(), ArgAction::Help | ArgAction::Version))
+        .find(|arg| key_of(arg) == key)
+}
+
+fn parse_table(source: &str, content: &str) -> Result<Table> {
+    content.parse::<Table>().map_err(|e| {
+        let line = e.span().map(|span| content[..span.start].matches('\n').count() + 1);
+        Error::parse(source, line, e.message())
+    })
+}
+
+/// Reject keys the command does not have, values of the wrong shape and
+/// values the option's own parser refuses, naming the file and line.
+fn check_key(command: &Command, source: &str, content: &str, key: &str, value: &Value) -> Result<()> {
+    let line = content.lines().position(|line| line.trim_start().starts_with(key)).map(|index| index + 1);
+    let Some(arg) = find_arg(command, key) else {
+        return Err(Error::parse(source, line, format!("unknown option '{}' for {}", key, command.get_name())));
+    };
+
+    let multiple = arg.get_long().is_none() || matches!(arg.get_action(), ArgAction::Append);
+    let problem = match value {
+        Value::Boolean(_) if !matches!(arg.get_action(), ArgAction::SetTrue) => Some("a value, not true/false"),
+        Value::Array(_) if !multiple => Some("a single value, not a list"),
+        Value::Array(items) if items.iter().any(|item| matches!(item, Value::Array(_) | Value::Table(_))) => {
+            Some("a list of plain values")
+        }
+        Value::Table(_) | Value::Datetime(_) => Some("a string, number or true/false"),
+        _ if matches!(arg.get_action(), ArgAction::SetTrue) && !value.is_bool() => Some("true or false"),
+        _ => None,
+    };
+    if let Some(expected) = problem {
+        return Err(Error::parse(source, line, format!("'{}' expects {}", key, expected)));
+    }
+    if value.is_bool() {
+        return Ok(());
+    }
+
+    let values = match value {
+        Value::Array(items) => items.iter().map(scalar).collect(),
+        other => vec![scalar(other)],
+    };
+    for value in values {
+        if let Err(e) = parse_value(arg, &value) {
+            return Err(Error::parse(source, line, clap_message(&e)));
+        }
+    }
+    Ok(())
+}
+
+/// Run one value through `arg`'s parser and possible values, on its own:
+/// requirements and conflicts with other options are checked once the
+/// whole command line is merged.
+fn parse_value(arg: &clap::Arg, value: &str) -> std::result::Result<(), clap::Error> {
+    let arg = arg.clone().required(false).requires(Resettable::Reset).conflicts_with(Resettable::Reset).global(false).num_args(1).action(ArgAction::Set);
+    let argv = match arg.get_long() {
+        Some(long) => vec![format!("--{}={}", long, value)],
+        None => vec!["--".to_string(), value.to_string()],
+    };
+    Command::new("p455w0rd").no_binary_name(true).arg(arg).try_get_matches_from(argv).map(|_| ())
+}
+
+/// A clap error without its `error:` prefix and usage hint, on one line.
+fn clap_message(e: &clap::Error) -> String {
+    let rendered = e.to_string();
+    let message: Vec<&str> = rendered.lines().take_while(|line| !line.is_empty()).map(str::trim).collect();
+    message.join(" ").trim_start_matches("error: ").to_string()
+}
+
+/// Command-line form of a TOML value.
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// TOML form of a command-line value: numbers stay numbers, unless that
+/// would change how they are written (`0312`).
+fn typed(value: &str) -> Value {
+    if let Some(n) = value.parse::<i64>().ok().filter(|n| n.to_string() == value) {
+        Value::Integer(n)
+    } else if let Some(x) = value.parse::<f64>().ok().filter(|x| x.to_string() == value) {
+        Value::Float(x)
+    } else {
+        Value::String(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Command as Subcommand;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn test_config_file_fills_unset_options() {
+        let temp = TempPath::file("job.toml", "max_words = 3\nmin_length = 6\nexclude = [\"a.txt\", \"b.txt\"]\nquiet = true\nwords = [\"admin\"]\n");
+        let path = temp.as_str();
+        let (args, _) = parse_args(["p455w0rd", "--config", path, "--max-words", "2"]).unwrap();
+
+        // Command-line flags win over the file
+        assert_eq!(args.generation.keyspace.get_max_words(), 2);
+        assert_eq!(args.generation.keyspace.min_length, 6);
+        assert_eq!(args.generation.exclude, ["a.txt", "b.txt"]);
+        assert!(args.generation.quiet);
+        assert_eq!(args.generation.keyspace.words.words, ["admin"]);
+    }
+
+    #[test]
+    fn test_config_file_errors() {
+        let temp = TempPath::file("bad.toml", "max_words = 3\nmax_wrods = 2\n");
+        let path = temp.as_str();
+        let err = parse_args(["p455w0rd", "--config", path, "admin"]).err().unwrap();
+        assert!(matches!(err, Error::Parse { line: Some(2), .. }), "{:?}", err);
+
+        std::fs::write(path, "max_words = [1, 2]\n").unwrap();
+        assert!(matches!(parse_args(["p455w0rd", "--config", path, "admin"]), Err(Error::Parse { .. })));
+
+        // Values go through the option's parser, and errors still name the file and line
+        std::fs::write(path, "quiet = true\nmin_length = \"abc\"\n").unwrap();
+        let err = parse_args(["p455w0rd", "--config", path, "admin"]).err().unwrap();
+        assert!(matches!(err, Error::Parse { line: Some(2), .. }), "{:?}", err);
+        assert!(err.to_string().contains("abc"), "{}", err);
+        std::fs::write(path, "dedup = \"sometimes\"\n").unwrap();
+        assert!(matches!(parse_args(["p455w0rd", "--config", path, "admin"]), Err(Error::Parse { .. })));
+
+        // Output options do not apply to analyze
+        std::fs::write(path, "output = \"out.txt\"\n").unwrap();
+        assert!(parse_args(["p455w0rd", "analyze", "--config", path, "admin"]).is_err());
+
+        assert!(matches!(parse_args(["p455w0rd", "--config", "/nonexistent.toml"]), Err(Error::Input { .. })));
+    }
+
+    #[test]
+    fn test_config_options_before_subcommand() {
+        let temp = TempPath::file("stats.toml", "top = 3\n");
+        let path = temp.as_str();
+        let (args, matches) = parse_args(["p455w0rd", "--config", path, "--save-config=saved.toml", "stats", "passwords.txt"]).unwrap();
+        let Some(Subcommand::Stats(stats)) = args.command else { panic!("expected stats") };
+        assert_eq!(stats.wordlist, "passwords.txt");
+        assert_eq!(stats.top, 3);
+        assert_eq!(args.config.save_config.as_deref(), Some("saved.toml"));
+        assert!(effective_config(&matches).contains("top = 3\n"));
+
+        // After other top-level options the name is still a word
+        let (args, _) = parse_args(["p455w0rd", "--quiet", "--preset", "quick", "analyze"]).unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.generation.keyspace.words.words, ["analyze"]);
+    }
+
+    #[test]
+    fn test_config_file_supplies_required_options() {
+        let temp = TempPath::file("train.toml", "input = \"leaks.txt\"\nkind = \"pcfg\"\n");
+        let path = temp.as_str();
+        let (args, _) = parse_args(["p455w0rd", "train", "--config", path]).unwrap();
+        let Some(Subcommand::Train(train)) = args.command else { panic!("expected train") };
+        assert_eq!(train.input, "leaks.txt");
+        assert_eq!(train.get_output(), "p455w0rd.pcfg");
+    }
+
+    #[test]
+    fn test_presets_apply_to_subcommands() {
+        let (args, matches) = parse_args(["p455w0rd", "--preset", "pin", "1985", "0312"]).unwrap();
+        assert!(effective_config(&matches).contains(r#"words = [1985, "0312"]"#));
+        assert!(args.generation.keyspace.get_transforms().names().is_empty());
+        assert_eq!(args.generation.match_patterns, ["^[0-9]+$"]);
+
+        // Before the subcommand name as well as after it
+        for argv in [["p455w0rd", "analyze", "--preset", "quick", "admin"], ["p455w0rd", "--preset", "quick", "analyze", "admin"]] {
+            let (args, _) = parse_args(argv).unwrap();
+            let Some(Subcommand::Analyze(analyze)) = args.command else { panic!("expected analyze") };
+            assert_eq!(analyze.keyspace.combinatorial_config().transforms.names(), ["case", "pad"]);
+            assert_eq!(analyze.keyspace.words.words, ["admin"]);
+        }
+
+        for (name, content) in PRESETS {
+            assert!(PRESET_NAMES.contains(&name));
+            parse_table(name, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_saved_config_round_trips() {
+        let (_, matches) = parse_args([
+            "p455w0rd", "--max-words", "2", "--dedup-fp-rate", "0.01", "--match", "^a", "--quiet", "-o", "out.txt", "admin", "pass",
+        ])
+        .unwrap();
+        let saved = effective_config(&matches);
+        assert!(saved.contains("max_words = 2\n"), "{}", saved);
+        assert!(saved.contains("dedup_fp_rate = 0.01\n"), "{}", saved);
+        assert!(!saved.contains("append"), "{}", saved);
+
+        let temp = TempPath::file("saved.toml", &saved);
+        let path = temp.as_str();
+        let (args, reloaded) = parse_args(["p455w0rd", "--config", path]).unwrap();
+        assert_eq!(effective_config(&reloaded), saved);
+        assert_eq!(args.generation.keyspace.words.words, ["admin", "pass"]);
+        assert_eq!(args.output.get_output(), "out.txt");
+    }
+}
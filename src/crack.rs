@@ -0,0 +1,236 @@
+use crate::hashing::{from_hex, to_hex, HashAlgorithm};
+use crate::error::{Error, Result};
+use crate::sink::CandidateSink;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Unsalted target hashes that generated candidates are checked against.
+///
+/// Recovered `hash:plaintext` pairs are appended to a hashcat-style potfile
+/// as soon as they are found, so an interrupted run keeps its results and a
+/// later run against the same potfile skips hashes that are already known.
+pub struct Cracker {
+    algorithm: HashAlgorithm,
+    remaining: HashSet<Vec<u8>>,
+    total: usize,
+    previously_cracked: usize,
+    invalid_lines: usize,
+    potfile: BufWriter<File>,
+    potfile_path: String,
+    found: Vec<(String, String)>,
+}
+
+impl Cracker {
+    /// Load target hashes from `hashes_path`, one per line. Bare hashes,
+    /// `user:hash` lines and pwdump lines (`user:rid:lm:nt:::`) are accepted;
+    /// the last field that looks like a digest of the right length is used.
+    pub fn load(hashes_path: &str, algorithm: HashAlgorithm, potfile_path: &str) -> Result<Self> {
+        let mut remaining = HashSet::new();
+        let mut invalid_lines = 0;
+
+        let reader = BufReader::new(File::open(hashes_path).map_err(|e| Error::input(hashes_path, e))?);
+        for line in reader.lines() {
+            let line = line.map_err(|e| Error::input(hashes_path, e))?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match parse_hash(line, algorithm) {
+                Some(hash) => {
+                    remaining.insert(hash);
+                }
+                None => invalid_lines += 1,
+            }
+        }
+        let total = remaining.len();
+
+        // Hashes recovered by an earlier run do not need to be found again
+        let mut previously_cracked = 0;
+        if Path::new(potfile_path).exists() {
+            let reader = BufReader::new(File::open(potfile_path).map_err(|e| Error::input(potfile_path, e))?);
+            for line in reader.lines() {
+                let line = line.map_err(|e| Error::input(potfile_path, e))?;
+                let cracked = line.split_once(':').and_then(|(hash, _)| from_hex(hash));
+                if cracked.is_some_and(|hash| remaining.remove(&hash)) {
+                    previously_cracked += 1;
+                }
+            }
+        }
+
+        let potfile = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(potfile_path)
+            .map_err(|e| Error::output(potfile_path, e))?;
+
+        Ok(Cracker {
+            algorithm,
+            remaining,
+            total,
+            previously_cracked,
+            invalid_lines,
+            potfile: BufWriter::new(potfile),
+            potfile_path: potfile_path.to_string(),
+            found: Vec::new(),
+        })
+    }
+
+    /// Hash every candidate in parallel and record any that match.
+    pub fn check_chunk(&mut self, candidates: &[String]) -> io::Result<()> {
+        if self.is_done() {
+            return Ok(());
+        }
+
+        let algorithm = self.algorithm;
+        let remaining = &self.remaining;
+        let matches: Vec<(Vec<u8>, &String)> = candidates
+            .par_iter()
+            .filter_map(|candidate| {
+                let digest = algorithm.digest(candidate);
+                remaining.contains(&digest).then_some((digest, candidate))
+            })
+            .collect();
+
+        for (digest, candidate) in matches {
+            self.record(digest, candidate)?;
+        }
+        Ok(())
+    }
+
+    pub fn check_candidate(&mut self, candidate: &str) -> io::Result<()> {
+        let digest = self.algorithm.digest(candidate);
+        if self.remaining.contains(&digest) {
+            self.record(digest, candidate)?;
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, digest: Vec<u8>, candidate: &str) -> io::Result<()> {
+        // The same chunk can hold two candidates with one hash
+        if !self.remaining.remove(&digest) {
+            return Ok(());
+        }
+        let hash = to_hex(&digest);
+        writeln!(self.potfile, "{}:{}", hash, candidate)?;
+        self.potfile.flush()?;
+        self.found.push((hash, candidate.to_string()));
+        Ok(())
+    }
+
+    /// True once every target hash has been recovered.
+    pub fn is_done(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    /// Distinct target hashes loaded.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Targets already present in the potfile before this run.
+    pub fn previously_cracked(&self) -> usize {
+        self.previously_cracked
+    }
+
+    /// Lines in the hash file that held no usable hash.
+    pub fn invalid_lines(&self) -> usize {
+        self.invalid_lines
+    }
+
+    /// `(hash, plaintext)` pairs recovered during this run, in order found.
+    pub fn found(&self) -> &[(String, String)] {
+        &self.found
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.potfile.flush()
+    }
+}
+
+/// Matches are written to the potfile, so its path names any write error.
+impl CandidateSink for Cracker {
+    fn write_chunk(&mut self, candidates: &[String]) -> Result<()> {
+        self.check_chunk(candidates).map_err(|e| Error::output(&self.potfile_path, e))
+    }
+
+    fn write_candidate(&mut self, candidate: &str) -> Result<()> {
+        self.check_candidate(candidate).map_err(|e| Error::output(&self.potfile_path, e))
+    }
+
+    fn is_done(&self) -> bool {
+        Cracker::is_done(self)
+    }
+
+    fn describe(&self) -> (&'static str, String) {
+        ("Hash Verification", format!("{} hashes", self.total))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.flush().map_err(|e| Error::output(&self.potfile_path, e))
+    }
+}
+
+fn parse_hash(line: &str, algorithm: HashAlgorithm) -> Option<Vec<u8>> {
+    line.rsplit(':')
+        .filter(|field| field.len() == algorithm.digest_len() * 2)
+        .find_map(from_hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn test_parse_hash_formats() {
+        let nt = HashAlgorithm::Ntlm.digest("password");
+        assert_eq!(parse_hash("8846f7eaee8fb117ad06bdd830b7586c", HashAlgorithm::Ntlm), Some(nt.clone()));
+        assert_eq!(parse_hash("jsmith:8846F7EAEE8FB117AD06BDD830B7586C", HashAlgorithm::Ntlm), Some(nt.clone()));
+        assert_eq!(
+            parse_hash(
+                "jsmith:1001:aad3b435b51404eeaad3b435b51404ee:8846f7eaee8fb117ad06bdd830b7586c:::",
+                HashAlgorithm::Ntlm
+            ),
+            Some(nt)
+        );
+        assert_eq!(parse_hash("not a hash", HashAlgorithm::Md5), None);
+        assert_eq!(parse_hash("5f4dcc3b5aa765d61d8327deb882cf99", HashAlgorithm::Sha1), None);
+    }
+
+    #[test]
+    fn test_cracker_records_matches_and_skips_potfile_entries() {
+        let dir = TempPath::dir("crack");
+        let hashes = dir.join("hashes.txt");
+        let potfile = dir.join("test.potfile");
+
+        let md5 = HashAlgorithm::Md5;
+        std::fs::write(
+            &hashes,
+            format!("{}\n{}\n{}\ngarbage\n\n", md5.hex_digest("admin"), md5.hex_digest("4dm1n!"), md5.hex_digest("secret")),
+        )
+        .unwrap();
+        std::fs::write(&potfile, format!("{}:secret\n", md5.hex_digest("secret"))).unwrap();
+
+        let mut cracker = Cracker::load(&hashes, md5, &potfile).unwrap();
+        assert_eq!(cracker.total(), 3);
+        assert_eq!(cracker.previously_cracked(), 1);
+        assert_eq!(cracker.invalid_lines(), 1);
+
+        cracker
+            .check_chunk(&["Admin".to_string(), "admin".to_string(), "admin".to_string()])
+            .unwrap();
+        assert!(!cracker.is_done());
+        cracker.check_candidate("4dm1n!").unwrap();
+        assert!(cracker.is_done());
+        cracker.flush().unwrap();
+
+        assert_eq!(cracker.found().len(), 2);
+        assert_eq!(cracker.found()[0].1, "admin");
+        let pot = std::fs::read_to_string(&potfile).unwrap();
+        assert_eq!(pot.lines().count(), 3);
+        assert!(pot.ends_with(&format!("{}:4dm1n!\n", md5.hex_digest("4dm1n!"))));
+    }
+}
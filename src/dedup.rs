@@ -0,0 +1,408 @@
+use crate::error::{Error, Result};
+use clap::ValueEnum;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Maximum number of spilled runs merged at once; more runs are merged in
+/// several passes so we never hold too many open file handles.
+const MAX_MERGE_FANIN: usize = 128;
+
+/// How duplicates are removed across the whole output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DedupMode {
+    /// Only the per-word variant dedup in `TransformChain::word_variants`
+    None,
+    /// External sort-merge of spilled chunks; output comes out sorted
+    Exact,
+    /// Bounded-memory Bloom filter; may drop a small fraction of unique candidates
+    Bloom,
+}
+
+#[derive(Debug, Clone)]
+pub struct DedupConfig {
+    pub mode: DedupMode,
+    /// Target false-positive rate for the Bloom filter
+    pub fp_rate: f64,
+    /// Upper bound on Bloom filter memory in bytes
+    pub max_memory: u64,
+    /// Number of candidates the Bloom filter is sized for
+    pub expected_items: u64,
+    /// Directory for sorted runs spilled by exact dedup
+    pub temp_dir: PathBuf,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig {
+            mode: DedupMode::None,
+            fp_rate: 0.001,
+            max_memory: 1024 * 1024 * 1024,
+            expected_items: 0,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// Classic Bloom filter using double hashing over two SipHash digests.
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// Size the filter for `expected_items` at `fp_rate`, shrinking it to
+    /// fit in `max_memory` bytes if needed (at the cost of a higher rate).
+    pub fn new(expected_items: u64, fp_rate: f64, max_memory: u64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let p = fp_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let optimal_bits = (-n * p.ln() / (ln2 * ln2)).ceil() as u64;
+        let num_bits = optimal_bits.min(max_memory.saturating_mul(8)).max(64);
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 32.0) as u32;
+
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    /// Insert `item`, returning true if it was (probably) already present.
+    pub fn check_and_insert(&mut self, item: &str) -> bool {
+        let (h1, h2) = Self::hashes(item);
+        let mut present = true;
+
+        for i in 0..self.num_hashes as u64 {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits;
+            let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+            if self.bits[word] & mask == 0 {
+                present = false;
+                self.bits[word] |= mask;
+            }
+        }
+
+        present
+    }
+
+    fn hashes(item: &str) -> (u64, u64) {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let h1 = hasher.finish();
+
+        let mut hasher = DefaultHasher::new();
+        (item, 0x9e37_79b9_7f4a_7c15u64).hash(&mut hasher);
+        // Odd step so every probe sequence visits distinct bits
+        let h2 = hasher.finish() | 1;
+
+        (h1, h2)
+    }
+}
+
+/// Removes duplicates across the entire run, not just within one word.
+///
+/// Bloom mode filters each candidate as it is produced. Exact mode sorts
+/// every chunk, spills it to disk as a run and merges the runs into the
+/// output at the end, so memory stays bounded by the chunk size.
+pub struct Deduplicator {
+    mode: DedupMode,
+    bloom: Option<BloomFilter>,
+    temp_dir: PathBuf,
+    runs: Vec<PathBuf>,
+    /// Lines in all runs together
+    run_lines: usize,
+    /// Distinct candidates the runs are known to hold: the longest run, as
+    /// each run is distinct on its own
+    distinct: usize,
+    next_run_id: usize,
+    duplicates: u64,
+}
+
+impl Deduplicator {
+    pub fn new(config: &DedupConfig) -> Self {
+        let bloom = match config.mode {
+            DedupMode::Bloom => Some(BloomFilter::new(config.expected_items, config.fp_rate, config.max_memory)),
+            _ => None,
+        };
+
+        Deduplicator {
+            mode: config.mode,
+            bloom,
+            temp_dir: config.temp_dir.clone(),
+            runs: Vec::new(),
+            run_lines: 0,
+            distinct: 0,
+            next_run_id: 0,
+            duplicates: 0,
+        }
+    }
+
+    pub fn mode(&self) -> DedupMode {
+        self.mode
+    }
+
+    /// Duplicates removed so far.
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// Distinct candidates spilled so far (exact mode). A lower bound, as
+    /// runs are only compared when they are merged.
+    pub fn distinct(&self) -> usize {
+        self.distinct
+    }
+
+    /// Returns false if the candidate should be dropped as a duplicate.
+    pub fn accept(&mut self, candidate: &str) -> bool {
+        let seen = match &mut self.bloom {
+            Some(bloom) => bloom.check_and_insert(candidate),
+            None => false,
+        };
+        if seen {
+            self.duplicates += 1;
+        }
+        !seen
+    }
+
+    /// Sort and dedup a chunk and spill it to disk as one run (exact mode).
+    ///
+    /// With a `limit` (0 = none), the runs are merged into one once they
+    /// could hold `limit` distinct candidates, so `distinct` reaches the
+    /// limit when they do. To keep the extra merging in proportion to the
+    /// spilled output, this happens at most once per doubling of the lines
+    /// held.
+    pub fn spill(&mut self, chunk: &mut [String], limit: usize) -> Result<()> {
+        chunk.sort_unstable();
+
+        let path = self.next_run_path();
+        let mut previous: Option<&String> = None;
+        let mut lines = 0;
+        let mut duplicates = 0;
+        let written = File::create(&path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            for candidate in chunk.iter() {
+                if previous == Some(candidate) {
+                    duplicates += 1;
+                    continue;
+                }
+                writeln!(writer, "{}", candidate)?;
+                previous = Some(candidate);
+                lines += 1;
+            }
+            writer.flush()
+        });
+        // Track the run before checking for errors so Drop removes it
+        self.runs.push(path);
+        written.map_err(|e| run_error(self.runs.last().unwrap(), e))?;
+        self.duplicates += duplicates;
+        self.run_lines += lines;
+        self.distinct = self.distinct.max(lines);
+
+        if limit > 0 && self.distinct < limit && self.run_lines >= limit.max(2 * self.distinct) {
+            self.reduce_runs(1)?;
+        }
+        Ok(())
+    }
+
+    /// Merge all spilled runs, passing each distinct candidate to `emit`
+    /// once and at most `limit` of them (0 = unlimited). Returns the number
+    /// of candidates emitted.
+    pub fn merge_into(&mut self, limit: usize, emit: impl FnMut(&str) -> Result<()>) -> Result<usize> {
+        // Reduce the number of runs until a single pass can merge them all
+        self.reduce_runs(MAX_MERGE_FANIN)?;
+
+        // Runs stay tracked until merged, so Drop removes them on failure
+        let (written, duplicates) = merge_runs(&self.runs, limit, emit)?;
+        self.runs.clear();
+        self.run_lines = 0;
+        self.duplicates += duplicates;
+        Ok(written)
+    }
+
+    /// Merge runs, at most `MAX_MERGE_FANIN` at a time, until no more than
+    /// `max_runs` are left.
+    fn reduce_runs(&mut self, max_runs: usize) -> Result<()> {
+        while self.runs.len() > max_runs {
+            let batch = self.runs.len().min(MAX_MERGE_FANIN);
+            let path = self.next_run_path();
+            let mut writer = BufWriter::new(File::create(&path).map_err(|e| run_error(&path, e))?);
+            self.runs.push(path.clone());
+            let write = |line: &str| writeln!(writer, "{}", line).map_err(|e| run_error(&path, e));
+            let (lines, duplicates) = merge_runs(&self.runs[..batch], 0, write)?;
+            writer.flush().map_err(|e| run_error(&path, e))?;
+            self.runs.drain(..batch);
+            self.run_lines -= duplicates as usize;
+            self.distinct = self.distinct.max(lines);
+            self.duplicates += duplicates;
+        }
+        Ok(())
+    }
+
+    fn next_run_path(&mut self) -> PathBuf {
+        let path = self.temp_dir.join(format!(
+            "p455w0rd-dedup-{}-{}.run",
+            std::process::id(),
+            self.next_run_id
+        ));
+        self.next_run_id += 1;
+        path
+    }
+}
+
+impl Drop for Deduplicator {
+    fn drop(&mut self) {
+        for run in &self.runs {
+            let _ = std::fs::remove_file(run);
+        }
+    }
+}
+
+/// K-way merge of sorted runs, emitting each distinct line once and deleting
+/// the runs afterwards. Returns (lines emitted, duplicates skipped).
+fn merge_runs(
+    runs: &[PathBuf],
+    limit: usize,
+    mut emit: impl FnMut(&str) -> Result<()>,
+) -> Result<(usize, u64)> {
+    let mut readers = Vec::with_capacity(runs.len());
+    for run in runs {
+        readers.push(BufReader::new(File::open(run).map_err(|e| run_error(run, e))?).lines());
+    }
+
+    let mut heap = BinaryHeap::new();
+    for (index, reader) in readers.iter_mut().enumerate() {
+        if let Some(line) = reader.next() {
+            heap.push(Reverse((line.map_err(|e| run_error(&runs[index], e))?, index)));
+        }
+    }
+
+    let mut written = 0;
+    let mut duplicates = 0;
+    let mut last: Option<String> = None;
+
+    while let Some(Reverse((line, index))) = heap.pop() {
+        if let Some(next) = readers[index].next() {
+            heap.push(Reverse((next.map_err(|e| run_error(&runs[index], e))?, index)));
+        }
+
+        if last.as_ref() == Some(&line) {
+            duplicates += 1;
+            continue;
+        }
+        if limit > 0 && written >= limit {
+            break;
+        }
+
+        emit(&line)?;
+        written += 1;
+        last = Some(line);
+    }
+
+    drop(readers);
+    for run in runs {
+        std::fs::remove_file(run).map_err(|e| run_error(run, e))?;
+    }
+
+    Ok((written, duplicates))
+}
+
+/// Spilled runs are temporary output, so their failures are output errors.
+fn run_error(run: &Path, source: io::Error) -> Error {
+    Error::output(run.display().to_string(), source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+    use crate::output::{Compression, OutputWriter};
+
+    #[test]
+    fn test_bloom_filter_detects_repeats() {
+        let mut bloom = BloomFilter::new(1000, 0.001, 1024 * 1024);
+        assert!(!bloom.check_and_insert("admin"));
+        assert!(!bloom.check_and_insert("4dmin"));
+        assert!(bloom.check_and_insert("admin"));
+    }
+
+    #[test]
+    fn test_bloom_filter_respects_memory_cap() {
+        let bloom = BloomFilter::new(1_000_000_000, 0.0001, 1024);
+        assert!(bloom.bits.len() * 8 <= 1024);
+    }
+
+    #[test]
+    fn test_exact_dedup_across_chunks() {
+        let dir = TempPath::dir("dedup");
+        let path = dir.join("out.txt");
+
+        let config = DedupConfig {
+            mode: DedupMode::Exact,
+            temp_dir: dir.path().to_path_buf(),
+            ..DedupConfig::default()
+        };
+        let mut dedup = Deduplicator::new(&config);
+
+        // "a"+"bc" and "ab"+"c" collide across permutations
+        let mut first = vec!["abc".to_string(), "xyz".to_string(), "abc".to_string()];
+        let mut second = vec!["abc".to_string(), "123".to_string()];
+        dedup.spill(&mut first, 0).unwrap();
+        dedup.spill(&mut second, 0).unwrap();
+
+        let mut output = OutputWriter::open(&path, false, Compression::None, None).unwrap();
+        let written = dedup.merge_into(0, |line| output.write_candidate(line).map_err(|e| output.error(e))).unwrap();
+        output.finish().unwrap();
+
+        assert_eq!(written, 3);
+        assert_eq!(dedup.duplicates(), 2);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "123\nabc\nxyz\n");
+    }
+
+    #[test]
+    fn test_limit_merges_runs_to_count_distinct() {
+        let dir = TempPath::dir("dedup_limit");
+        let config = DedupConfig {
+            mode: DedupMode::Exact,
+            temp_dir: dir.path().to_path_buf(),
+            ..DedupConfig::default()
+        };
+        let mut dedup = Deduplicator::new(&config);
+
+        dedup.spill(&mut ["abc".to_string(), "xyz".to_string()], 3).unwrap();
+        assert_eq!(dedup.distinct(), 2);
+        // Four lines could hold three distinct candidates, so the runs are merged
+        dedup.spill(&mut ["abc".to_string(), "xyz".to_string()], 3).unwrap();
+        assert_eq!((dedup.distinct(), dedup.duplicates()), (2, 2));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        // Merged again only once the lines held double
+        dedup.spill(&mut ["123".to_string()], 3).unwrap();
+        assert_eq!(dedup.distinct(), 2);
+        dedup.spill(&mut ["456".to_string()], 3).unwrap();
+        assert_eq!(dedup.distinct(), 4);
+    }
+
+    #[test]
+    fn test_failed_merge_leaves_no_runs() {
+        let dir = TempPath::dir("dedup_failed");
+        let config = DedupConfig {
+            mode: DedupMode::Exact,
+            temp_dir: dir.path().to_path_buf(),
+            ..DedupConfig::default()
+        };
+        let mut dedup = Deduplicator::new(&config);
+        dedup.spill(&mut ["abc".to_string()], 0).unwrap();
+        dedup.spill(&mut ["xyz".to_string()], 0).unwrap();
+
+        let full = |_: &str| Err(Error::output("out.txt", io::Error::other("disk full")));
+        assert!(dedup.merge_into(0, full).is_err());
+        drop(dedup);
+        let left: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+        assert!(left.is_empty(), "{:?}", left);
+    }
+}
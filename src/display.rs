@@ -1,76 +1,928 @@
-use std::time::Instant;
-
-pub fn update_status_display(
-    total_count: usize,
-    start_time: &Instant,
-    output_file: &str,
-    words: &[String],
-    current_length: usize,
-    is_first: bool,
-    estimated_total: usize,
-) {
-    let elapsed = start_time.elapsed();
-    let rate = if elapsed.as_secs() > 0 {
-        total_count as f64 / elapsed.as_secs() as f64
-    } else {
-        0.0
-    };
-
-    // Calculate progress and ETA
-    let (progress_pct, show_progress) = if estimated_total > 0 && total_count <= estimated_total {
-        ((total_count as f64 / estimated_total as f64 * 100.0).min(100.0), true)
-    } else if estimated_total > 0 && total_count > estimated_total {
-        // If we've exceeded estimate significantly, don't show percentage
-        let excess_factor = total_count as f64 / estimated_total as f64;
-        if excess_factor > 3.0 {
-            (0.0, false) // Don't show progress when estimate is clearly wrong
+use crate::combinatorics::{format_duration, format_file_size};
+use crate::error::{Error, Result};
+use crossterm::cursor::{Hide, MoveTo, MoveToColumn, MoveUp, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{execute, queue};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// How often the dashboard redraws.
+const DASHBOARD_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often a plain status line is logged when stderr is not a terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often a JSON progress event is emitted.
+const EVENT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the keyboard is polled for run controls.
+const CONTROL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Throughput samples kept for the graph, one per redraw.
+const RATE_HISTORY: usize = 120;
+
+const GRAPH_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Counters owned by the rest of the pipeline, passed in on each redraw.
+#[derive(Debug, Clone, Default)]
+pub struct RunCounters {
+    /// Candidates written (or tried, when cracking)
+    pub written: u64,
+    /// Uncompressed bytes written; None when candidates are not written out
+    pub bytes_written: Option<u64>,
+    pub duplicates: u64,
+    pub excluded: u64,
+    pub policy_rejected: u64,
+    pub regex_rejections: Vec<(String, u64)>,
+}
+
+/// What the user asked the generator to do, via the run controls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    /// Abandon the current word-count tier and move on to the next
+    SkipTier,
+    /// Stop generating; the output is still finalized
+    Quit,
+}
+
+/// Where JSON-lines status events go (`--status-json`, `--status-file`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusEvents {
+    /// Instead of the dashboard or log lines
+    Stderr,
+    /// Appended to a file, alongside the normal display
+    File(String),
+}
+
+/// One line of the JSON-lines status stream.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StatusEvent<'a> {
+    Progress {
+        elapsed_seconds: f64,
+        written: u64,
+        /// Uncompressed bytes; null when candidates are not written out
+        bytes_written: Option<u64>,
+        /// Word count of the current tier
+        k: usize,
+        /// 1-based index of the current permutation within the tier
+        permutation: u64,
+        permutations: u64,
+        rate: f64,
+        eta_seconds: Option<f64>,
+        /// Exact candidate count from the combinatorial analysis
+        total: u64,
+    },
+    Complete {
+        elapsed_seconds: f64,
+        written: u64,
+        bytes_written: Option<u64>,
+        duplicates: u64,
+        excluded: u64,
+        policy_rejected: u64,
+        regex_rejected: u64,
+        /// The run controls skipped a tier or quit
+        stopped_early: bool,
+        total: u64,
+    },
+    Error {
+        elapsed_seconds: f64,
+        message: &'a str,
+    },
+}
+
+/// Writer for the JSON-lines status stream. A failing status file is not
+/// worth failing the run over, so write errors are ignored.
+pub struct EventLog {
+    out: Box<dyn Write>,
+    to_stderr: bool,
+    last: Option<Instant>,
+}
+
+impl EventLog {
+    pub fn open(target: &StatusEvents) -> Result<Self> {
+        let (out, to_stderr): (Box<dyn Write>, bool) = match target {
+            StatusEvents::Stderr => (Box::new(io::stderr()), true),
+            StatusEvents::File(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| Error::output(path, e))?;
+                (Box::new(file), false)
+            }
+        };
+        Ok(EventLog { out, to_stderr, last: None })
+    }
+
+    fn is_due(&self) -> bool {
+        self.last.is_none_or(|last| last.elapsed() >= EVENT_INTERVAL)
+    }
+
+    pub fn emit(&mut self, event: &StatusEvent) {
+        self.last = Some(Instant::now());
+        if let Ok(line) = serde_json::to_string(event) {
+            let _ = writeln!(self.out, "{}", line);
+            let _ = self.out.flush();
+        }
+    }
+}
+
+/// How the status is shown besides the job itself.
+#[derive(Default)]
+pub struct DisplayOptions {
+    /// No dashboard or log lines
+    pub quiet: bool,
+    /// Allow keyboard controls when the terminal supports them
+    pub controls: bool,
+    pub events: Option<EventLog>,
+}
+
+/// Receives progress from the generator: which permutation it is on, the
+/// latest counters, and how the run ended. `StatusDisplay` is the CLI's
+/// dashboard, log-line and JSON-event observer; `SilentProgress` ignores
+/// everything. Every method has a do-nothing default.
+pub trait ProgressObserver {
+    /// The generator moved on to permutation `words` of tier `k`.
+    fn start_permutation(&mut self, _k: usize, _words: &[&String]) {}
+
+    /// The current permutation has been fully expanded.
+    fn finish_permutation(&mut self) {}
+
+    /// Called often while generating; `counters` is cheap to skip and
+    /// fairly cheap to call. May block, e.g. while paused.
+    fn tick(&mut self, _counters: &dyn Fn() -> RunCounters) {}
+
+    /// What the generator should do next. A `SkipTier` request stays
+    /// pending until acknowledged with `tier_skipped`.
+    fn control(&self) -> Control {
+        Control::Continue
+    }
+
+    /// The generator has left the tier it was asked to skip.
+    fn tier_skipped(&mut self) {}
+
+    /// Generation is over; the output is about to be finalized.
+    fn finish(&mut self, _counters: &RunCounters) {}
+
+    /// The output was finalized.
+    fn complete(&mut self, _counters: &RunCounters) {}
+
+    /// The run failed with `message`.
+    fn error(&mut self, _message: &str) {}
+}
+
+/// Progress observer that ignores everything, for library callers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SilentProgress;
+
+impl ProgressObserver for SilentProgress {}
+
+impl<P: ProgressObserver + ?Sized> ProgressObserver for &mut P {
+    fn start_permutation(&mut self, k: usize, words: &[&String]) {
+        (**self).start_permutation(k, words)
+    }
+
+    fn finish_permutation(&mut self) {
+        (**self).finish_permutation()
+    }
+
+    fn tick(&mut self, counters: &dyn Fn() -> RunCounters) {
+        (**self).tick(counters)
+    }
+
+    fn control(&self) -> Control {
+        (**self).control()
+    }
+
+    fn tier_skipped(&mut self) {
+        (**self).tier_skipped()
+    }
+
+    fn finish(&mut self, counters: &RunCounters) {
+        (**self).finish(counters)
+    }
+
+    fn complete(&mut self, counters: &RunCounters) {
+        (**self).complete(counters)
+    }
+
+    fn error(&mut self, message: &str) {
+        (**self).error(message)
+    }
+}
+
+/// What the tiers of a run are, for labelling progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TierKind {
+    /// Tier k combines k words, one permutation at a time
+    Words,
+    /// Tier k is probability level k - 1, most likely first, one `group`
+    /// (a candidate length, a PCFG structure) at a time
+    Levels { group: &'static str },
+}
+
+impl TierKind {
+    fn tier_name(self, k: usize) -> String {
+        match self {
+            TierKind::Words => format!("{} words", k),
+            TierKind::Levels { .. } => format!("level {}", k.saturating_sub(1)),
+        }
+    }
+
+    fn group_key(self) -> &'static str {
+        match self {
+            TierKind::Words => "Permutation",
+            TierKind::Levels { group } => group,
+        }
+    }
+
+    fn source_key(self) -> &'static str {
+        match self {
+            TierKind::Words => "Words",
+            TierKind::Levels { .. } => "Model",
+        }
+    }
+}
+
+/// Progress through one tier: a word count of the `for k in 1..=max_words`
+/// loop, or a probability level.
+#[derive(Debug, Clone)]
+struct Tier {
+    /// Permutations of k words, or candidate lengths of a level
+    permutations: u64,
+    /// Candidates the analysis expects from this tier, used to weight overall progress
+    expected: u64,
+    done: u64,
+}
+
+impl Tier {
+    fn fraction(&self) -> f64 {
+        if self.permutations == 0 {
+            1.0
+        } else {
+            (self.done as f64 / self.permutations as f64).min(1.0)
+        }
+    }
+}
+
+enum Mode {
+    /// Redrawn in place on an interactive stderr
+    Dashboard { lines: u16, size: (u16, u16) },
+    /// Periodic plain lines for logs and pipes
+    Log,
+    Quiet,
+}
+
+/// Live status of a generation run: a dashboard when stderr is a terminal,
+/// plain periodic log lines otherwise. Progress is tracked per word-count
+/// tier by permutations completed, which is exact, and weighted by the
+/// analysis' candidate counts for the overall percentage and ETA.
+///
+/// When both stdin and stderr are terminals the dashboard also takes
+/// hashcat-style keyboard controls: [s]tatus, [p]ause, [r]esume, [b]ypass
+/// the current tier and [q]uit (or Ctrl-C). The terminal is in raw mode
+/// meanwhile, so Ctrl-C arrives as a key instead of killing the process.
+pub struct StatusDisplay {
+    mode: Mode,
+    controls: bool,
+    control: Control,
+    stopped_early: bool,
+    state: &'static str,
+    mode_name: &'static str,
+    target: String,
+    kind: TierKind,
+    /// What candidates are made from, e.g. "12 words"
+    source: String,
+    tiers: Vec<Tier>,
+    tier: usize,
+    permutation: String,
+    start: Instant,
+    paused_for: Duration,
+    last_draw: Option<Instant>,
+    last_poll: Instant,
+    last_sample: (Instant, u64),
+    rates: VecDeque<f64>,
+    events: Option<EventLog>,
+}
+
+impl StatusDisplay {
+    /// `tier_expected[k - 1]` is the analysis' candidate count for k-word
+    /// combinations; missing entries weight tiers by permutation count.
+    pub fn new(
+        mode_name: &'static str,
+        target: String,
+        word_count: usize,
+        max_words: usize,
+        tier_expected: &[u64],
+        options: DisplayOptions,
+    ) -> Self {
+        let tiers = (1..=max_words.min(word_count))
+            .map(|k| {
+                let permutations = (0..k as u64).fold(1u64, |acc, i| acc.saturating_mul(word_count as u64 - i));
+                Tier {
+                    permutations,
+                    expected: tier_expected.get(k - 1).copied().unwrap_or(permutations),
+                    done: 0,
+                }
+            })
+            .collect();
+        Self::with_tiers(mode_name, target, TierKind::Words, format!("{} words", word_count), tiers, options)
+    }
+
+    /// Status for a run through probability levels `0..levels`, each made
+    /// of `groups` groups called `group` ("Length"); 0 groups means the
+    /// count is not known. How many candidates a level holds is not known
+    /// up front either, so there is no overall percentage, total or ETA.
+    pub fn for_levels(
+        mode_name: &'static str,
+        target: String,
+        source: String,
+        group: &'static str,
+        levels: usize,
+        groups: u64,
+        options: DisplayOptions,
+    ) -> Self {
+        let tiers = (0..levels)
+            .map(|_| Tier {
+                permutations: groups,
+                expected: 0,
+                done: 0,
+            })
+            .collect();
+        Self::with_tiers(mode_name, target, TierKind::Levels { group }, source, tiers, options)
+    }
+
+    fn with_tiers(
+        mode_name: &'static str,
+        target: String,
+        kind: TierKind,
+        source: String,
+        tiers: Vec<Tier>,
+        options: DisplayOptions,
+    ) -> Self {
+        let json_on_stderr = options.events.as_ref().is_some_and(|events| events.to_stderr);
+        let mode = if options.quiet || json_on_stderr {
+            Mode::Quiet
+        } else if io::stderr().is_terminal() {
+            let _ = execute!(io::stderr(), Hide);
+            Mode::Dashboard { lines: 0, size: terminal::size().unwrap_or((80, 24)) }
         } else {
-            // Small excess, show capped progress
-            (95.0, true)
-        }
-    } else {
-        (0.0, false)
-    };
-
-    let eta_secs = if rate > 0.0 && estimated_total > total_count {
-        ((estimated_total - total_count) as f64 / rate).min(86400.0) // Cap at 24 hours
-    } else if total_count > estimated_total {
-        // When exceeded estimate, show "Unknown" ETA
-        -1.0 // Special value for unknown
-    } else {
-        0.0
-    };
-
-    let eta_formatted = if eta_secs < 0.0 {
-        "Unknown".to_string()
-    } else if eta_secs > 3600.0 {
-        format!("{:.1}h", eta_secs / 3600.0)
-    } else if eta_secs > 60.0 {
-        format!("{:.1}m", eta_secs / 60.0)
-    } else {
-        format!("{:.0}s", eta_secs)
-    };
-
-    // Move cursor up to overwrite previous display (only if not first time)
-    if !is_first {
-        print!("\x1B[12A"); // Move cursor up 12 lines
-        print!("\x1B[0J"); // Clear from cursor to end of screen
-    }
-
-    println!("Session..........: p455w0rd");
-    println!("Status...........: Running");
-    println!("Mode.............: Password Generator");
-    println!("Target...........: {}", output_file);
-    println!("Time.Elapsed.....: {:.0}s", elapsed.as_secs_f64());
-    println!("Time.ETA.........: {}", eta_formatted);
-    println!("Words............: {} words", words.len());
-    println!("Current.Length...: {} character passwords", current_length);
-    println!("Speed............: {:.0} P/s", rate);
-    if show_progress {
-        println!("Progress.........: {}/{} ({:.2}%)", total_count, estimated_total, progress_pct);
-    } else {
-        println!("Progress.........: {} passwords (estimate exceeded)", total_count);
-    }
-    println!("Generated........: {} passwords", total_count);
-    println!();
-}
\ No newline at end of file
+            Mode::Log
+        };
+        let controls = options.controls
+            && matches!(mode, Mode::Dashboard { .. })
+            && io::stdin().is_terminal()
+            && terminal::enable_raw_mode().is_ok();
+
+        let now = Instant::now();
+        StatusDisplay {
+            mode,
+            controls,
+            control: Control::Continue,
+            stopped_early: false,
+            state: "Running",
+            mode_name,
+            target,
+            kind,
+            source,
+            tiers,
+            tier: 1,
+            permutation: String::new(),
+            start: now,
+            paused_for: Duration::ZERO,
+            last_draw: None,
+            last_poll: now,
+            last_sample: (now, 0),
+            rates: VecDeque::with_capacity(RATE_HISTORY),
+            events: options.events,
+        }
+    }
+
+    /// True when the user skipped a tier or quit, so the output is partial.
+    pub fn stopped_early(&self) -> bool {
+        self.stopped_early
+    }
+
+    fn emit_progress(&mut self, counters: &RunCounters) {
+        let tier = self.tiers.get(self.tier - 1);
+        let permutations = tier.map_or(0, |t| t.permutations);
+        let event = StatusEvent::Progress {
+            elapsed_seconds: self.elapsed().as_secs_f64(),
+            written: counters.written,
+            bytes_written: counters.bytes_written,
+            k: self.tier,
+            permutation: tier.map_or(0, |t| t.done + 1).min(permutations),
+            permutations,
+            rate: self.current_rate(counters.written),
+            eta_seconds: self.eta_seconds(),
+            total: self.total(),
+        };
+        if let Some(events) = &mut self.events {
+            events.emit(&event);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, counters: &dyn Fn() -> RunCounters) {
+        match control_key(key) {
+            Some('s') => self.last_draw = None,
+            Some('p') => self.pause(counters),
+            Some('b') => self.request(Control::SkipTier),
+            Some('q') => self.request(Control::Quit),
+            _ => {}
+        }
+    }
+
+    fn request(&mut self, control: Control) {
+        if self.control != Control::Quit {
+            self.control = control;
+            self.state = match control {
+                Control::SkipTier => "Skipping tier",
+                _ => "Quitting",
+            };
+        }
+        self.stopped_early = true;
+        self.last_draw = None;
+    }
+
+    fn pause(&mut self, counters: &dyn Fn() -> RunCounters) {
+        let paused_at = Instant::now();
+        self.state = "Paused";
+        self.update(&counters());
+
+        loop {
+            match event::read() {
+                Ok(Event::Key(key)) => match control_key(key) {
+                    Some('r') | Some('p') => break,
+                    Some('b') => {
+                        self.request(Control::SkipTier);
+                        break;
+                    }
+                    Some('q') => {
+                        self.request(Control::Quit);
+                        break;
+                    }
+                    Some('s') => self.update(&counters()),
+                    _ => {}
+                },
+                Ok(Event::Resize(..)) => self.update(&counters()),
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+
+        self.paused_for += paused_at.elapsed();
+        if self.state == "Paused" {
+            self.state = "Running";
+        }
+        // Keep the pause out of the next throughput sample
+        self.last_sample.0 = Instant::now();
+        self.last_draw = None;
+    }
+
+    /// Whether enough time has passed for the next redraw or log line.
+    pub fn is_due(&self) -> bool {
+        let interval = match self.mode {
+            Mode::Dashboard { .. } => DASHBOARD_INTERVAL,
+            Mode::Log => LOG_INTERVAL,
+            Mode::Quiet => return false,
+        };
+        self.last_draw.is_none_or(|last| last.elapsed() >= interval)
+    }
+
+    /// Redraw the dashboard, or log a status line, with the latest counters.
+    pub fn update(&mut self, counters: &RunCounters) {
+        let now = Instant::now();
+        let interval = now.duration_since(self.last_sample.0).as_secs_f64();
+        if interval > 0.0 && self.last_draw.is_some() {
+            if self.rates.len() == RATE_HISTORY {
+                self.rates.pop_front();
+            }
+            self.rates.push_back(counters.written.saturating_sub(self.last_sample.1) as f64 / interval);
+        }
+        self.last_sample = (now, counters.written);
+        self.last_draw = Some(now);
+
+        match self.mode {
+            Mode::Dashboard { .. } => {
+                // A terminal that cannot be drawn on is not worth failing the run over
+                if self.draw_dashboard(counters).is_err() {
+                    self.mode = Mode::Log;
+                }
+            }
+            Mode::Log => eprintln!("{}", self.log_line(counters)),
+            Mode::Quiet => {}
+        }
+    }
+
+    /// Restore the cursor and terminal mode. The last frame stays on
+    /// screen above the summary.
+    fn restore_terminal(&mut self) {
+        if self.controls {
+            let _ = terminal::disable_raw_mode();
+            self.controls = false;
+        }
+        if let Mode::Dashboard { .. } = self.mode {
+            let _ = execute!(io::stderr(), Show);
+            self.mode = Mode::Quiet;
+        }
+    }
+
+    /// Time spent generating, not counting pauses.
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed().saturating_sub(self.paused_for)
+    }
+
+    /// Weighted fraction of the whole job completed.
+    fn overall_fraction(&self) -> f64 {
+        let expected: f64 = self.tiers.iter().map(|t| t.expected as f64).sum();
+        if expected == 0.0 {
+            return 0.0;
+        }
+        self.tiers.iter().map(|t| t.expected as f64 * t.fraction()).sum::<f64>() / expected
+    }
+
+    /// Candidates the analysis expects from the whole job.
+    fn total(&self) -> u64 {
+        self.tiers.iter().fold(0u64, |sum, tier| sum.saturating_add(tier.expected))
+    }
+
+    fn eta_seconds(&self) -> Option<f64> {
+        let fraction = self.overall_fraction();
+        if fraction <= 0.0 {
+            return None;
+        }
+        Some(self.elapsed().as_secs_f64() * (1.0 - fraction) / fraction)
+    }
+
+    fn eta(&self) -> String {
+        self.eta_seconds().map_or_else(|| "Unknown".to_string(), format_duration)
+    }
+
+    fn average_rate(&self, written: u64) -> f64 {
+        written as f64 / self.elapsed().as_secs_f64().max(1e-3)
+    }
+
+    fn current_rate(&self, written: u64) -> f64 {
+        self.rates.back().copied().unwrap_or_else(|| self.average_rate(written))
+    }
+
+    fn log_line(&self, counters: &RunCounters) -> String {
+        let mut line = format!(
+            "[{}] {} passwords ({:.0} P/s)",
+            format_duration(self.elapsed().as_secs_f64()),
+            counters.written,
+            self.current_rate(counters.written)
+        );
+        if let Some(bytes) = counters.bytes_written {
+            line.push_str(&format!(", {}", format_file_size(bytes)));
+        }
+        line.push_str(&format!(", {}", self.kind.tier_name(self.tier)));
+        if let Some(tier) = self.tiers.get(self.tier - 1).filter(|tier| tier.permutations > 0) {
+            line.push_str(&format!(" {:.1}%", tier.fraction() * 100.0));
+        }
+        // Levels have no known size to take an overall fraction of
+        if self.kind == TierKind::Words {
+            line.push_str(&format!(", overall {:.1}%, ETA {}", self.overall_fraction() * 100.0, self.eta()));
+        }
+        let rejected = rejected_total(counters);
+        if rejected > 0 {
+            line.push_str(&format!(", {} rejected", rejected));
+        }
+        line
+    }
+
+    fn dashboard_lines(&self, counters: &RunCounters, width: usize) -> Vec<String> {
+        let bar_width = width.saturating_sub(38).clamp(10, 50);
+        let permutations = self.tiers.get(self.tier - 1).map_or(0, |t| t.permutations);
+        let done = self.tiers.get(self.tier - 1).map_or(0, |t| t.done);
+
+        let mut lines = vec![
+            "Session..........: p455w0rd".to_string(),
+            format!("Status...........: {}", self.state),
+            format!("Mode.............: {}", self.mode_name),
+            format!("Target...........: {}", self.target),
+            format!("Time.Elapsed.....: {}", format_duration(self.elapsed().as_secs_f64())),
+            format!("Time.ETA.........: {}", self.eta()),
+            format!("{:.<17}: {}", self.kind.source_key(), self.source),
+            if permutations > 0 {
+                format!(
+                    "{:.<17}: {}/{} of {}: {}",
+                    self.kind.group_key(),
+                    (done + 1).min(permutations),
+                    permutations,
+                    self.kind.tier_name(self.tier),
+                    self.permutation
+                )
+            } else {
+                format!("{:.<17}: {} in {}", self.kind.group_key(), self.permutation, self.kind.tier_name(self.tier))
+            },
+            format!("Speed............: {:.0} P/s (average {:.0} P/s)", self.current_rate(counters.written), self.average_rate(counters.written)),
+            format!("Speed.History....: {}", graph(&self.rates, width.saturating_sub(19))),
+            match counters.bytes_written {
+                Some(bytes) => format!("Written..........: {} passwords, {}", counters.written, format_file_size(bytes)),
+                None => format!("Tried............: {} passwords", counters.written),
+            },
+            format!(
+                "Rejected.........: {} duplicates, {} excluded, {} by policy",
+                counters.duplicates, counters.excluded, counters.policy_rejected
+            ),
+        ];
+        for (label, rejected) in &counters.regex_rejections {
+            lines.push(format!("Rejected.........: {} by {}", rejected, label));
+        }
+        if self.controls {
+            lines.push("Controls.........: [s]tatus [p]ause [r]esume [b]ypass tier [q]uit".to_string());
+        }
+        // Levels are too many to list and their sizes are unknown
+        if self.kind != TierKind::Words {
+            return lines;
+        }
+        lines.push(format!("Progress.........: {} {:.2}%", bar(self.overall_fraction(), bar_width), self.overall_fraction() * 100.0));
+        for (i, tier) in self.tiers.iter().enumerate() {
+            lines.push(format!(
+                "{:.<17}: {} {:.2}% ({}/{})",
+                format!("Tier.{}.Words", i + 1),
+                bar(tier.fraction(), bar_width),
+                tier.fraction() * 100.0,
+                tier.done,
+                tier.permutations
+            ));
+        }
+        lines
+    }
+
+    fn draw_dashboard(&mut self, counters: &RunCounters) -> io::Result<()> {
+        let Mode::Dashboard { lines: drawn, size: old_size } = self.mode else {
+            return Ok(());
+        };
+        let size = terminal::size().unwrap_or(old_size);
+        // Lines are cut to the terminal width so none wrap and the frame
+        // height stays exact; after a resize the terminal may have reflowed
+        // the old frame, so start over from a clean screen instead.
+        let width = size.0.max(20) as usize - 1;
+        let mut lines = self.dashboard_lines(counters, width);
+        lines.truncate(size.1.saturating_sub(1).max(1) as usize);
+
+        let mut stderr = io::stderr().lock();
+        if size != old_size {
+            queue!(stderr, Clear(ClearType::All), MoveTo(0, 0))?;
+        } else if drawn > 0 {
+            queue!(stderr, MoveUp(drawn), MoveToColumn(0))?;
+        }
+        queue!(stderr, Clear(ClearType::FromCursorDown))?;
+        for line in &lines {
+            queue!(stderr, Print(line.chars().take(width).collect::<String>()), Print("\r\n"))?;
+        }
+        stderr.flush()?;
+
+        self.mode = Mode::Dashboard { lines: lines.len() as u16, size };
+        Ok(())
+    }
+}
+
+impl ProgressObserver for StatusDisplay {
+    /// Record that the generator moved on to permutation `words` of tier `k`.
+    fn start_permutation(&mut self, k: usize, words: &[&String]) {
+        self.tier = k;
+        self.permutation = words.iter().map(|w| w.as_str()).collect::<Vec<_>>().join(" + ");
+    }
+
+    /// Record that the current permutation has been fully expanded.
+    fn finish_permutation(&mut self) {
+        if let Some(tier) = self.tiers.get_mut(self.tier - 1) {
+            tier.done += 1;
+        }
+    }
+
+    /// The pending control request, if any. Skip requests stay pending
+    /// until the generator acknowledges them with `tier_skipped`.
+    fn control(&self) -> Control {
+        self.control
+    }
+
+    /// Acknowledge a skip request once the generator has left the tier.
+    fn tier_skipped(&mut self) {
+        if self.control == Control::SkipTier {
+            self.control = Control::Continue;
+            self.state = "Running";
+        }
+    }
+
+    /// Handle pending keys, then redraw if due. `counters` is only called
+    /// when something is drawn. Pausing blocks here until resumed.
+    fn tick(&mut self, counters: &dyn Fn() -> RunCounters) {
+        if self.controls && self.last_poll.elapsed() >= CONTROL_INTERVAL {
+            self.last_poll = Instant::now();
+            while let Ok(true) = event::poll(Duration::ZERO) {
+                match event::read() {
+                    Ok(Event::Key(key)) => self.handle_key(key, counters),
+                    Ok(Event::Resize(..)) => self.last_draw = None,
+                    _ => {}
+                }
+            }
+        }
+        let display_due = self.is_due();
+        let events_due = self.events.as_ref().is_some_and(EventLog::is_due);
+        if display_due || events_due {
+            let counters = counters();
+            if display_due {
+                self.update(&counters);
+            }
+            if events_due {
+                self.emit_progress(&counters);
+            }
+        }
+    }
+
+    /// Draw the final state and restore the terminal.
+    fn finish(&mut self, counters: &RunCounters) {
+        self.update(counters);
+        self.restore_terminal();
+    }
+
+    /// Emit the final event for a run that finished, or was stopped, cleanly.
+    fn complete(&mut self, counters: &RunCounters) {
+        let event = StatusEvent::Complete {
+            elapsed_seconds: self.elapsed().as_secs_f64(),
+            written: counters.written,
+            bytes_written: counters.bytes_written,
+            duplicates: counters.duplicates,
+            excluded: counters.excluded,
+            policy_rejected: counters.policy_rejected,
+            regex_rejected: counters.regex_rejections.iter().map(|(_, rejected)| rejected).sum(),
+            stopped_early: self.stopped_early,
+            total: self.total(),
+        };
+        if let Some(events) = &mut self.events {
+            events.emit(&event);
+        }
+    }
+
+    /// Emit the final event for a run that failed.
+    fn error(&mut self, message: &str) {
+        let event = StatusEvent::Error {
+            elapsed_seconds: self.elapsed().as_secs_f64(),
+            message,
+        };
+        if let Some(events) = &mut self.events {
+            events.emit(&event);
+        }
+    }
+}
+
+impl Drop for StatusDisplay {
+    fn drop(&mut self) {
+        self.restore_terminal();
+    }
+}
+
+/// Lowercase control key for a key press; Ctrl-C counts as `q`.
+fn control_key(key: KeyEvent) -> Option<char> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some('q'),
+        KeyCode::Char(c) => Some(c.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+fn rejected_total(counters: &RunCounters) -> u64 {
+    counters.duplicates
+        + counters.excluded
+        + counters.policy_rejected
+        + counters.regex_rejections.iter().map(|(_, rejected)| rejected).sum::<u64>()
+}
+
+fn bar(fraction: f64, width: usize) -> String {
+    let filled = (fraction.clamp(0.0, 1.0) * width as f64).round() as usize;
+    format!("[{}{}]", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+/// Sparkline of the most recent samples that fit in `width`, scaled to the peak.
+fn graph(samples: &VecDeque<f64>, width: usize) -> String {
+    let shown: Vec<f64> = samples.iter().skip(samples.len().saturating_sub(width)).copied().collect();
+    let peak = shown.iter().copied().fold(0.0, f64::max);
+    if peak <= 0.0 {
+        return String::new();
+    }
+    shown
+        .iter()
+        .map(|rate| GRAPH_LEVELS[((rate / peak) * (GRAPH_LEVELS.len() - 1) as f64).round() as usize])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    fn quiet() -> DisplayOptions {
+        DisplayOptions {
+            quiet: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tier_progress_is_weighted_by_expected_candidates() {
+        let mut status = StatusDisplay::new("Password Generator", "-".to_string(), 3, 2, &[10, 90], quiet());
+        let words = ["a".to_string(), "b".to_string()];
+        let refs: Vec<&String> = words.iter().collect();
+
+        for _ in 0..3 {
+            status.start_permutation(1, &refs[..1]);
+            status.finish_permutation();
+        }
+        assert_eq!(status.tiers[0].permutations, 3);
+        assert_eq!(status.tiers[1].permutations, 6);
+        assert!((status.overall_fraction() - 0.1).abs() < 1e-9);
+
+        status.start_permutation(2, &refs);
+        assert_eq!(status.permutation, "a + b");
+        for _ in 0..3 {
+            status.finish_permutation();
+        }
+        assert!((status.overall_fraction() - 0.55).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_levels_have_no_overall_progress() {
+        let mut status = StatusDisplay::for_levels("Password Generator", "-".to_string(), "order-3 Markov model".to_string(), "Length", 40, 17, quiet());
+        let length = "8 characters".to_string();
+        status.start_permutation(3, &[&length]);
+        status.finish_permutation();
+
+        let line = status.log_line(&RunCounters::default());
+        assert!(line.contains(", level 2 5.9%"), "{}", line);
+        assert!(!line.contains("overall"), "{}", line);
+        assert_eq!(status.total(), 0);
+        assert!(status.dashboard_lines(&RunCounters::default(), 80).iter().any(|l| l == "Length...........: 2/17 of level 2: 8 characters"));
+    }
+
+    #[test]
+    fn test_skip_requests_wait_for_the_generator() {
+        let mut status = StatusDisplay::new("Password Generator", "-".to_string(), 3, 2, &[], quiet());
+        status.request(Control::SkipTier);
+        assert_eq!(status.control(), Control::SkipTier);
+        status.tier_skipped();
+        assert_eq!(status.control(), Control::Continue);
+
+        status.request(Control::Quit);
+        status.request(Control::SkipTier);
+        status.tier_skipped();
+        assert_eq!(status.control(), Control::Quit);
+        assert!(status.stopped_early());
+
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(control_key(ctrl_c), Some('q'));
+        assert_eq!(control_key(KeyEvent::new(KeyCode::Char('P'), KeyModifiers::SHIFT)), Some('p'));
+    }
+
+    #[test]
+    fn test_status_events_are_json_lines() {
+        let temp = TempPath::new("events.jsonl");
+        let path = temp.as_str().to_string();
+
+        let options = DisplayOptions {
+            events: Some(EventLog::open(&StatusEvents::File(path.clone())).unwrap()),
+            ..quiet()
+        };
+        let mut status = StatusDisplay::new("Password Generator", "-".to_string(), 2, 2, &[4, 6], options);
+        let counters = RunCounters {
+            written: 7,
+            bytes_written: Some(42),
+            ..Default::default()
+        };
+        status.tick(&|| counters.clone());
+        status.complete(&counters);
+        status.error("disk full");
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["event"], "progress");
+        assert_eq!(lines[0]["total"], 10);
+        assert_eq!(lines[0]["k"], 1);
+        assert_eq!(lines[0]["bytes_written"], 42);
+        assert_eq!(lines[1]["event"], "complete");
+        assert_eq!(lines[1]["written"], 7);
+        assert_eq!(lines[2]["message"], "disk full");
+    }
+
+    #[test]
+    fn test_bar_and_graph() {
+        assert_eq!(bar(0.5, 4), "[██░░]");
+        assert_eq!(bar(2.0, 2), "[██]");
+        let samples: VecDeque<f64> = vec![0.0, 50.0, 100.0].into();
+        assert_eq!(graph(&samples, 2), "▅█");
+        assert_eq!(graph(&VecDeque::new(), 10), "");
+    }
+}
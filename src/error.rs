@@ -0,0 +1,117 @@
+use std::fmt;
+use std::io;
+
+/// Exit code for invalid settings: contradictory or missing options, an
+/// empty word list, a bad pattern. Clap uses the same code for bad arguments.
+pub const EXIT_CONFIG: u8 = 2;
+/// Exit code for an input file that could not be read.
+pub const EXIT_INPUT: u8 = 3;
+/// Exit code for an input file that was read but could not be parsed.
+pub const EXIT_PARSE: u8 = 4;
+/// Exit code for a job too large to count in 64 bits.
+pub const EXIT_OVERFLOW: u8 = 5;
+/// Exit code for output that could not be written.
+pub const EXIT_OUTPUT: u8 = 6;
+
+/// Everything that can go wrong in a run, grouped by what the caller can do
+/// about it. Each variant maps to its own process exit code.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading a word list, exclusion list, hash, rule or policy file failed
+    Input { path: String, source: io::Error },
+    /// An input file held something that could not be parsed; `line` is
+    /// 1-based, when known
+    Parse { path: String, line: Option<usize>, message: String },
+    /// A permutation or candidate count does not fit in 64 bits
+    Overflow(String),
+    /// The settings contradict each other or cannot produce anything
+    Config(String),
+    /// Writing candidates, a potfile, a report or temporary files failed
+    Output { path: String, source: io::Error },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn input(path: impl Into<String>, source: io::Error) -> Self {
+        Error::Input { path: path.into(), source }
+    }
+
+    pub fn parse(path: impl Into<String>, line: Option<usize>, message: impl Into<String>) -> Self {
+        Error::Parse { path: path.into(), line, message: message.into() }
+    }
+
+    pub fn config(message: impl Into<String>) -> Self {
+        Error::Config(message.into())
+    }
+
+    pub fn output(path: impl Into<String>, source: io::Error) -> Self {
+        Error::Output { path: path.into(), source }
+    }
+
+    /// Process exit code for this kind of failure.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Config(_) => EXIT_CONFIG,
+            Error::Input { .. } => EXIT_INPUT,
+            Error::Parse { .. } => EXIT_PARSE,
+            Error::Overflow(_) => EXIT_OVERFLOW,
+            Error::Output { .. } => EXIT_OUTPUT,
+        }
+    }
+
+    /// True when the output was a pipe whose reader exited, which is how a
+    /// consumer like `head` or hashcat signals it has had enough.
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self, Error::Output { source, .. } if source.kind() == io::ErrorKind::BrokenPipe)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Input { path, source } => write!(f, "Failed to read {}: {}", path, source),
+            Error::Parse { path, line: Some(line), message } => write!(f, "{} line {}: {}", path, line, message),
+            Error::Parse { path, line: None, message } => write!(f, "{}: {}", path, message),
+            Error::Overflow(message) | Error::Config(message) => f.write_str(message),
+            Error::Output { path, source } => write!(f, "Failed to write {}: {}", path, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Input { source, .. } | Error::Output { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            Error::config("bad"),
+            Error::input("words.txt", io::Error::from(io::ErrorKind::NotFound)),
+            Error::parse("rules.txt", Some(3), "bad rule"),
+            Error::Overflow("too many".to_string()),
+            Error::output("out.txt", io::Error::from(io::ErrorKind::BrokenPipe)),
+        ];
+        let mut codes: Vec<u8> = errors.iter().map(Error::exit_code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes, [EXIT_CONFIG, EXIT_INPUT, EXIT_PARSE, EXIT_OVERFLOW, EXIT_OUTPUT]);
+        assert!(errors[4].is_broken_pipe());
+        assert!(!errors[1].is_broken_pipe());
+    }
+
+    #[test]
+    fn test_parse_error_names_file_and_line() {
+        assert_eq!(Error::parse("rules.txt", Some(3), "bad rule").to_string(), "rules.txt line 3: bad rule");
+        assert_eq!(Error::parse("policy.toml", None, "empty").to_string(), "policy.toml: empty");
+    }
+}
@@ -0,0 +1,93 @@
+use crate::error::{Error, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufRead, BufReader};
+
+/// Passwords that have already been tried, loaded from one or more wordlists.
+///
+/// Entries are stored as sorted 64-bit hashes rather than strings, which
+/// keeps a rockyou-sized list around 100 MB. With 64-bit hashes a false
+/// match between two different passwords is vanishingly unlikely.
+#[derive(Debug, Default)]
+pub struct ExclusionList {
+    hashes: Vec<u64>,
+    excluded: u64,
+}
+
+impl ExclusionList {
+    /// Load every line of every file. Lines are compared as raw bytes, so
+    /// lists with stray non-UTF-8 entries (rockyou has plenty) load fine.
+    pub fn load(paths: &[String]) -> Result<Self> {
+        let mut hashes = Vec::new();
+
+        for path in paths {
+            let mut reader = BufReader::new(File::open(path).map_err(|e| Error::input(path, e))?);
+            let mut line = Vec::new();
+            while reader.read_until(b'\n', &mut line).map_err(|e| Error::input(path, e))? > 0 {
+                while matches!(line.last(), Some(b'\n') | Some(b'\r')) {
+                    line.pop();
+                }
+                if !line.is_empty() {
+                    hashes.push(hash_bytes(&line));
+                }
+                line.clear();
+            }
+        }
+
+        hashes.sort_unstable();
+        hashes.dedup();
+        hashes.shrink_to_fit();
+
+        Ok(ExclusionList { hashes, excluded: 0 })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn contains(&self, candidate: &str) -> bool {
+        !self.is_empty() && self.hashes.binary_search(&hash_bytes(candidate.as_bytes())).is_ok()
+    }
+
+    /// Returns false if the candidate is on an exclusion list and should be dropped.
+    pub fn accept(&mut self, candidate: &str) -> bool {
+        if self.contains(candidate) {
+            self.excluded += 1;
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Candidates dropped so far.
+    pub fn excluded(&self) -> u64 {
+        self.excluded
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn test_exclusion_list_drops_known_passwords() {
+        let path = TempPath::file("exclude.txt", b"password\r\n4dmin\n\n\xff\xfebroken\n");
+
+        let mut list = ExclusionList::load(&[path.as_str().to_string()]).unwrap();
+        assert_eq!(list.hashes.len(), 3);
+
+        assert!(!list.accept("password"));
+        assert!(!list.accept("4dmin"));
+        assert!(list.accept("admin"));
+        assert!(list.accept("Password"));
+        assert_eq!(list.excluded(), 2);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::error::{Error, Result};
+use crate::exclude::ExclusionList;
+use crate::policy::PasswordPolicy;
+use regex::Regex;
+
+/// Whether a regex filter keeps or drops the candidates it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegexRule {
+    /// Drop candidates that do not match (`--match`)
+    Match,
+    /// Drop candidates that match (`--reject`)
+    Reject,
+}
+
+#[derive(Debug)]
+pub struct RegexFilter {
+    rule: RegexRule,
+    regex: Regex,
+    rejected: u64,
+}
+
+impl RegexFilter {
+    pub fn new(rule: RegexRule, pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).map_err(|e| Error::config(format!("Invalid --match/--reject pattern: {}", e)))?;
+        Ok(RegexFilter { rule, regex, rejected: 0 })
+    }
+
+    fn accept(&mut self, candidate: &str) -> bool {
+        let keep = self.regex.is_match(candidate) == (self.rule == RegexRule::Match);
+        if !keep {
+            self.rejected += 1;
+        }
+        keep
+    }
+
+    /// Label used in the status display and summary, e.g. `--reject ^\d+$`.
+    pub fn label(&self) -> String {
+        match self.rule {
+            RegexRule::Match => format!("--match {}", self.regex.as_str()),
+            RegexRule::Reject => format!("--reject {}", self.regex.as_str()),
+        }
+    }
+}
+
+/// Compile `--match` and `--reject` patterns, in that order.
+pub fn compile_regex_filters(matches: &[String], rejects: &[String]) -> Result<Vec<RegexFilter>> {
+    matches
+        .iter()
+        .map(|pattern| RegexFilter::new(RegexRule::Match, pattern))
+        .chain(rejects.iter().map(|pattern| RegexFilter::new(RegexRule::Reject, pattern)))
+        .collect()
+}
+
+/// Checks applied to every candidate before it is buffered for output.
+#[derive(Debug, Default)]
+pub struct CandidateFilter {
+    exclusions: ExclusionList,
+    policy: Option<PasswordPolicy>,
+    policy_rejected: u64,
+    regexes: Vec<RegexFilter>,
+}
+
+impl CandidateFilter {
+    pub fn new(exclusions: ExclusionList, policy: Option<PasswordPolicy>, regexes: Vec<RegexFilter>) -> Self {
+        CandidateFilter {
+            exclusions,
+            policy,
+            policy_rejected: 0,
+            regexes,
+        }
+    }
+
+    /// Returns false if the candidate should be dropped. Cheap checks run
+    /// first, and each rejection is counted against the check that made it.
+    pub fn accept(&mut self, candidate: &str) -> bool {
+        if let Some(policy) = &self.policy {
+            if !policy.allows(candidate) {
+                self.policy_rejected += 1;
+                return false;
+            }
+        }
+
+        if !self.exclusions.accept(candidate) {
+            return false;
+        }
+
+        self.regexes.iter_mut().all(|filter| filter.accept(candidate))
+    }
+
+    /// Candidates dropped because they appear in an exclusion list.
+    pub fn excluded(&self) -> u64 {
+        self.exclusions.excluded()
+    }
+
+    /// Candidates dropped for not meeting the password policy.
+    pub fn policy_rejected(&self) -> u64 {
+        self.policy_rejected
+    }
+
+    /// Rejections per regex filter, in the order the filters were given.
+    pub fn regex_rejections(&self) -> Vec<(String, u64)> {
+        self.regexes.iter().map(|filter| (filter.label(), filter.rejected)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_filters_count_rejections_per_filter() {
+        let regexes = compile_regex_filters(
+            &[r"^[A-Z]".to_string()],
+            &[r"\d{3}".to_string(), r"!$".to_string()],
+        )
+        .unwrap();
+        let mut filter = CandidateFilter::new(ExclusionList::default(), None, regexes);
+
+        assert!(filter.accept("Admin12"));
+        assert!(!filter.accept("admin12")); // no leading capital
+        assert!(!filter.accept("Admin123")); // three digits
+        assert!(!filter.accept("Admin!")); // trailing bang
+        assert!(!filter.accept("Pass999"));
+
+        assert_eq!(
+            filter.regex_rejections(),
+            vec![
+                ("--match ^[A-Z]".to_string(), 1),
+                (r"--reject \d{3}".to_string(), 2),
+                ("--reject !$".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_regex_is_an_error() {
+        assert!(compile_regex_filters(&["(unclosed".to_string()], &[]).is_err());
+    }
+}
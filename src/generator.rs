@@ -1,13 +1,45 @@
-use crate::display::update_status_display;
-use crate::words::create_word_variants;
-use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
+use crate::candidates::{CandidateSource, PasswordGenerator};
+use crate::crack::Cracker;
+use crate::dedup::{DedupConfig, DedupMode, Deduplicator};
+use crate::combinatorics::{calculate_total_combinations, CombinatorialConfig};
+use crate::display::{Control, DisplayOptions, EventLog, ProgressObserver, RunCounters, StatusDisplay, StatusEvent, StatusEvents};
+use crate::error::{Error, Result};
+use crate::exclude::ExclusionList;
+use crate::filter::{compile_regex_filters, CandidateFilter};
+use crate::hashing::HashOutput;
+use crate::markov::{level_count, MarkovGenerator, MarkovModel};
+use crate::pcfg::{PcfgGenerator, PcfgModel};
+use crate::output::{check_disk_space, display_name, is_stdout, Compression, OutputWriter, SplitLimit};
+use crate::policy::PasswordPolicy;
+use crate::prince::PrinceGenerator;
+use crate::transform::TransformChain;
+use crate::sink::CandidateSink;
+use crate::wpa::WpaVerifier;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use itertools::Itertools;
-use rayon::prelude::*;
+
+/// Where candidates come from.
+#[derive(Debug, Clone, Default)]
+pub enum GenerationMode {
+    /// Orderings of the input words and their transform variants
+    #[default]
+    Combinations,
+    /// A trained character Markov model, most probable first, up to a level
+    /// (None: until the keyspace is exhausted). Needs no words.
+    Markov { model: Arc<MarkovModel>, max_level: Option<u32> },
+    /// A trained PCFG's structures with letter segments filled from the
+    /// input words' variants, most probable first
+    Pcfg { model: Arc<PcfgModel> },
+    /// PRINCE chains of the input words' variants, any word any number of
+    /// times, smallest keyspace first; `max_words` caps the chain length
+    Prince,
+}
 
 #[derive(Debug, Clone)]
 pub struct GeneratorConfig {
+    pub mode: GenerationMode,
     pub min_len: usize,
     pub max_len: usize,
     pub limit: usize,
@@ -16,242 +48,664 @@ pub struct GeneratorConfig {
     pub quiet: bool,
     pub append: bool,
     pub max_words: usize,
-    pub no_special_chars: bool,
+    /// Word and candidate transforms, in the order they run
+    pub transforms: TransformChain,
+    pub compression: Compression,
+    pub split: Option<SplitLimit>,
+    pub dedup: DedupConfig,
+    /// Wordlists whose entries are never written (already-tried lists)
+    pub exclude: Vec<String>,
+    /// Character-class requirements every written password must meet
+    pub policy: Option<PasswordPolicy>,
+    /// Regexes a password must match to be written (`--match`)
+    pub match_patterns: Vec<String>,
+    /// Regexes that drop a password when they match (`--reject`)
+    pub reject_patterns: Vec<String>,
+    /// Write hashes (optionally with plaintext) instead of plaintext
+    pub hash: Option<HashOutput>,
+    /// Estimated uncompressed output size, checked against free disk space
+    /// before anything is written; None skips the check
+    pub expected_bytes: Option<u64>,
+    /// Only warn, instead of refusing to start, when the output will not fit
+    pub force: bool,
+    /// Where to emit JSON-lines progress events, if anywhere
+    pub status_events: Option<StatusEvents>,
 }
 
-pub fn generate_combinations_streaming(
-    words: &[String],
-    config: &GeneratorConfig,
-) -> Result<usize, Box<dyn std::error::Error>> {
-    // Remove duplicates
-    let unique_words: Vec<String> = words.iter().cloned().collect::<std::collections::HashSet<_>>().into_iter().collect();
-    let n = unique_words.len();
-
-    // Use atomic file operations with temporary file for safety
-    let (file, temp_path) = if config.append {
-        (OpenOptions::new().create(true).append(true).open(&config.output_file)?, None)
-    } else {
-        let temp_path = format!("{}.tmp.{}", config.output_file, std::process::id());
-        let file = File::create(&temp_path)?;
-        (file, Some(temp_path))
-    };
-    let mut writer = BufWriter::new(file);
-    let mut total_count = 0;
-    let mut chunk_buffer = Vec::with_capacity(config.chunk_size);
-
-    let start_time = Instant::now();
-    let mut last_update = Instant::now();
-    let mut first_display = true;
-
-    // Special characters for padding
-    let special_chars = ['!', '@', '#', '$', '%'];
-
-    // Generate all permutations for each word count from 1 to max_words
-    for k in 1..=config.max_words.min(n) {
-        // Get all permutations of k distinct words
-        for word_indices in (0..n).permutations(k) {
-            // Get the actual words for this permutation
-            let perm_words: Vec<&String> = word_indices.iter().map(|&i| &unique_words[i]).collect();
-
-            // Generate all combinations for this word permutation
-            generate_word_combinations(
-                &perm_words,
-                &special_chars,
-                config,
-                &mut chunk_buffer,
-                &mut total_count,
-                &mut writer,
-                &start_time,
-                &mut last_update,
-                &mut first_display,
-                &unique_words,
-                k,
-            )?;
-
-            // Check limit
-            if config.limit > 0 && total_count >= config.limit {
-                break;
-            }
-        }
-
-        if config.limit > 0 && total_count >= config.limit {
-            break;
+/// The CLI's defaults: 4-20 characters, every word-count tier, the standard
+/// transforms, output to `passwords.txt` and no filters.
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            mode: GenerationMode::Combinations,
+            min_len: 4,
+            max_len: 20,
+            limit: 0,
+            output_file: "passwords.txt".to_string(),
+            chunk_size: 100_000,
+            quiet: false,
+            append: false,
+            max_words: usize::MAX,
+            transforms: TransformChain::default(),
+            compression: Compression::None,
+            split: None,
+            dedup: DedupConfig::default(),
+            exclude: Vec::new(),
+            policy: None,
+            match_patterns: Vec::new(),
+            reject_patterns: Vec::new(),
+            hash: None,
+            expected_bytes: None,
+            force: false,
+            status_events: None,
         }
     }
+}
 
-    // Write remaining combinations
-    if !chunk_buffer.is_empty() {
-        write_chunk(&mut writer, &chunk_buffer)?;
-        total_count += chunk_buffer.len();
+/// Outcome of a generation run.
+#[derive(Debug, Clone, Default)]
+pub struct GenerationSummary {
+    /// Passwords written to the output
+    pub count: usize,
+    /// Candidates dropped by global deduplication
+    pub duplicates_removed: u64,
+    /// Candidates dropped because they appear in an exclusion list
+    pub excluded: u64,
+    /// Candidates dropped for not meeting the password policy
+    pub policy_rejected: u64,
+    /// Candidates dropped by each `--match`/`--reject` filter
+    pub regex_rejections: Vec<(String, u64)>,
+    /// Time spent generating, excluding setup such as loading exclusion lists
+    pub elapsed: Duration,
+    /// The user skipped a tier or quit from the run controls; the output is
+    /// finalized but does not cover the whole job
+    pub stopped_early: bool,
+}
+
+impl GenerationSummary {
+    /// Candidates produced before filtering: written plus every kind of drop.
+    pub fn generated(&self) -> u64 {
+        let regex_rejected: u64 = self.regex_rejections.iter().map(|(_, rejected)| rejected).sum();
+        self.count as u64 + self.duplicates_removed + self.excluded + self.policy_rejected + regex_rejected
     }
+}
+
+/// Candidates written by `calibrate`: enough to warm up buffers and the
+/// encoder, small enough to take about a second on typical settings.
+const CALIBRATION_SAMPLE: usize = 200_000;
 
-    writer.flush()?;
-    drop(writer);
+/// Throughput of the real pipeline measured on this machine.
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    /// Candidates produced, including those dropped by filters
+    pub generated: u64,
+    /// Bytes that reached the disk, after hashing and compression
+    pub bytes_written: u64,
+    pub elapsed: Duration,
+}
+
+impl Calibration {
+    pub fn candidates_per_second(&self) -> f64 {
+        self.generated as f64 / self.elapsed.as_secs_f64().max(1e-6)
+    }
 
-    // Atomic rename
-    if let Some(temp_path) = temp_path {
-        std::fs::rename(&temp_path, &config.output_file)?;
+    pub fn bytes_per_second(&self) -> f64 {
+        self.bytes_written as f64 / self.elapsed.as_secs_f64().max(1e-6)
     }
 
-    Ok(total_count)
+    /// Projected (seconds, bytes on disk) for a job of `total` candidates.
+    pub fn project(&self, total: u64) -> (f64, u64) {
+        let seconds = total as f64 / self.candidates_per_second();
+        let bytes = total as f64 * self.bytes_written as f64 / self.generated.max(1) as f64;
+        (seconds, bytes as u64)
+    }
 }
 
-fn generate_word_combinations(
-    words: &[&String],
-    special_chars: &[char],
-    config: &GeneratorConfig,
-    chunk_buffer: &mut Vec<String>,
-    total_count: &mut usize,
-    writer: &mut BufWriter<File>,
-    start_time: &Instant,
-    last_update: &mut Instant,
-    first_display: &mut bool,
-    all_words: &[String],
-    current_word_count: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Generate all leet + case variants for each word
-    let word_variants: Vec<Vec<String>> = words
-        .par_iter()
-        .map(|word| create_word_variants(word))
-        .collect();
-
-    // Generate cartesian product of all word variants
-    let base_combinations = generate_cartesian_product(&word_variants);
-
-    // Apply length filtering and special character padding
-    for base_combo in base_combinations {
-        // Check length constraints
-        if base_combo.len() < config.min_len || base_combo.len() > config.max_len {
-            continue;
+/// Run the first candidates of the job through the full pipeline (filters,
+/// dedup, hashing, compression) into a scratch file beside the output, and
+/// time it. The scratch file is removed afterwards. The sample favours the
+/// short single-word candidates generated first, and leaves out exact
+/// dedup's sort-merge, so treat the projection as a guide rather than a
+/// promise.
+pub fn calibrate(words: &[String], config: &GeneratorConfig) -> Result<Calibration> {
+    let scratch_dir = if is_stdout(&config.output_file) {
+        std::env::temp_dir()
+    } else {
+        match Path::new(&config.output_file).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
         }
+    };
+    let scratch = scratch_dir.join(format!(".p455w0rd-calibration-{}", std::process::id()));
+
+    let mut sample_config = config.clone();
+    sample_config.output_file = scratch.to_string_lossy().into_owned();
+    sample_config.limit = match config.limit {
+        0 => CALIBRATION_SAMPLE,
+        limit => limit.min(CALIBRATION_SAMPLE),
+    };
+    sample_config.append = false;
+    sample_config.expected_bytes = None;
+    sample_config.status_events = None;
+    sample_config.split = None;
+    sample_config.quiet = true;
+    sample_config.dedup.expected_items = sample_config.limit as u64;
+    // Exact dedup holds everything back until its runs are merged, so the
+    // sample could run far past its size before writing anything
+    if sample_config.dedup.mode == DedupMode::Exact {
+        sample_config.dedup.mode = DedupMode::None;
+    }
 
-        // Add the base combination (no special chars)
-        add_to_buffer(base_combo.clone(), chunk_buffer, total_count, writer, config, start_time, last_update, first_display, all_words, current_word_count)?;
+    let result = generate_combinations_streaming(words, &sample_config);
+    let bytes_written = std::fs::metadata(&scratch).map(|meta| meta.len()).unwrap_or(0);
+    let _ = std::fs::remove_file(&scratch);
+    let summary = result?;
 
-        // Add special character variations if enabled
-        if !config.no_special_chars {
-            add_special_char_variations(&base_combo, special_chars, config, chunk_buffer, total_count, writer, start_time, last_update, first_display, all_words, current_word_count)?;
+    Ok(Calibration {
+        generated: summary.generated(),
+        bytes_written,
+        elapsed: summary.elapsed,
+    })
+}
+
+pub fn generate_combinations_streaming(
+    words: &[String],
+    config: &GeneratorConfig,
+) -> Result<GenerationSummary> {
+    let writer = match open_output(config) {
+        Ok(writer) => writer,
+        Err(e) => {
+            report_setup_error(config, &e.to_string());
+            return Err(e);
         }
+    };
+    run_generation(words, config, writer)
+}
 
-        if config.limit > 0 && *total_count >= config.limit {
-            break;
+fn open_output(config: &GeneratorConfig) -> Result<OutputWriter> {
+    if let Some(raw_bytes) = config.expected_bytes {
+        match check_disk_space(&config.output_file, config.append, config.compression, raw_bytes) {
+            Ok(None) => {}
+            Ok(Some(problem)) if config.force => eprintln!("⚠️  Warning: {}", problem),
+            Ok(Some(problem)) => {
+                let message = format!("{}. Free up space, use --compress, or pass --force to start anyway", problem);
+                return Err(Error::output(display_name(&config.output_file), io::Error::new(io::ErrorKind::StorageFull, message)));
+            }
+            Err(e) => eprintln!("⚠️  Warning: could not check free disk space: {}", e),
         }
     }
 
-    Ok(())
+    // Atomic temp-file rename for files, locked buffered handle for stdout
+    let writer = OutputWriter::open(&config.output_file, config.append, config.compression, config.split)
+        .map_err(|e| Error::output(display_name(&config.output_file), e))?;
+    Ok(writer.with_hash(config.hash))
 }
 
-fn generate_cartesian_product(word_variants: &[Vec<String>]) -> Vec<String> {
-    if word_variants.is_empty() {
-        return vec![];
+/// Emit the final error event for a run that failed before it started.
+fn report_setup_error(config: &GeneratorConfig, message: &str) {
+    if let Some(mut events) = config.status_events.as_ref().and_then(|target| EventLog::open(target).ok()) {
+        events.emit(&StatusEvent::Error {
+            elapsed_seconds: 0.0,
+            message,
+        });
     }
+}
 
-    let mut result = vec![String::new()];
-    for variants in word_variants {
-        let mut new_result = Vec::new();
-        for base in result {
-            for variant in variants {
-                new_result.push(format!("{}{}", base, variant));
-            }
+/// Run the generation pipeline, hashing each candidate and checking it
+/// against `cracker` instead of writing it. Stops early once every target
+/// hash is recovered. The summary count is the number of candidates tried.
+pub fn crack_combinations(
+    words: &[String],
+    config: &GeneratorConfig,
+    cracker: &mut Cracker,
+) -> Result<GenerationSummary> {
+    run_generation(words, config, cracker)
+}
+
+/// Run the generation pipeline, checking each candidate as a WPA passphrase
+/// against the captured handshakes. Stops once every handshake is unlocked.
+/// The summary count is the number of candidates tried.
+pub fn verify_wpa_combinations(
+    words: &[String],
+    config: &GeneratorConfig,
+    verifier: &mut WpaVerifier,
+) -> Result<GenerationSummary> {
+    run_generation(words, config, verifier)
+}
+
+/// Run the pipeline into `sink` behind the CLI's status display.
+fn run_generation<S: CandidateSink>(
+    words: &[String],
+    config: &GeneratorConfig,
+    sink: S,
+) -> Result<GenerationSummary> {
+    let (mode_name, target) = sink.describe();
+    let options = DisplayOptions {
+        quiet: config.quiet,
+        // Raw-mode key handling would garble candidates printed to the same terminal
+        controls: !sink.writes_to_terminal(),
+        events: config.status_events.as_ref().map(EventLog::open).transpose()?,
+    };
+    let mut status = match &config.mode {
+        GenerationMode::Combinations => {
+            let word_count = words.iter().collect::<std::collections::HashSet<_>>().len();
+            let analysis_config = CombinatorialConfig {
+                max_words: config.max_words,
+                transforms: config.transforms.clone(),
+                min_len: config.min_len,
+                max_len: config.max_len,
+            };
+            let tier_expected: Vec<u64> = calculate_total_combinations(words, &analysis_config)
+                .map(|analysis| analysis.breakdown.by_word_count.iter().map(|tier| tier.combinations).collect())
+                .unwrap_or_default();
+            StatusDisplay::new(mode_name, target, word_count, config.max_words, &tier_expected, options)
+        }
+        GenerationMode::Markov { model, max_level } => {
+            let source = format!("order-{} Markov model, {} characters", model.order, model.alphabet().len());
+            let lengths = (config.min_len.max(1)..=config.max_len).count() as u64;
+            StatusDisplay::for_levels(mode_name, target, source, "Length", level_count(config.max_len, *max_level), lengths, options)
         }
-        result = new_result;
+        GenerationMode::Pcfg { model } => {
+            let source = format!("PCFG of {} structures, {} words", model.structures.len(), words.len());
+            StatusDisplay::for_levels(mode_name, target, source, "Structure", 0, 0, options)
+        }
+        GenerationMode::Prince => {
+            let source = format!("PRINCE chains of {} words", words.len());
+            StatusDisplay::for_levels(mode_name, target, source, "Chain", 0, 0, options)
+        }
+    };
+
+    generate_into(words, config, sink, &mut status)
+}
+
+/// Run the generation pipeline (filters, dedup, limit) into `sink`,
+/// reporting progress to `progress`. The output settings of `config`
+/// (file, compression, split, hash, disk check, status events) are not
+/// used; the sink decides what happens to each candidate. The sink is
+/// finished before this returns, and `progress` is told about a failure.
+pub fn generate_into<S: CandidateSink, P: ProgressObserver + ?Sized>(
+    words: &[String],
+    config: &GeneratorConfig,
+    sink: S,
+    progress: &mut P,
+) -> Result<GenerationSummary> {
+    let result = run_pipeline(words, config, sink, progress);
+    if let Err(e) = &result {
+        progress.error(&e.to_string());
     }
     result
 }
 
-fn add_special_char_variations(
-    base_combo: &str,
-    special_chars: &[char],
+fn run_pipeline<S: CandidateSink, P: ProgressObserver + ?Sized>(
+    words: &[String],
     config: &GeneratorConfig,
-    chunk_buffer: &mut Vec<String>,
-    total_count: &mut usize,
-    writer: &mut BufWriter<File>,
-    start_time: &Instant,
-    last_update: &mut Instant,
-    first_display: &mut bool,
-    all_words: &[String],
-    current_word_count: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let n = special_chars.len();
-
-    // Single prefix
-    for &special in special_chars {
-        let padded = format!("{}{}", special, base_combo);
-        if padded.len() >= config.min_len && padded.len() <= config.max_len {
-            add_to_buffer(padded, chunk_buffer, total_count, writer, config, start_time, last_update, first_display, all_words, current_word_count)?;
+    mut sink: S,
+    progress: &mut P,
+) -> Result<GenerationSummary> {
+    let filter = CandidateFilter::new(
+        ExclusionList::load(&config.exclude)?,
+        config.policy.clone(),
+        compile_regex_filters(&config.match_patterns, &config.reject_patterns)?,
+    );
+    let mut candidates: Box<dyn CandidateSource> = match &config.mode {
+        GenerationMode::Combinations => Box::new(
+            PasswordGenerator::builder(words.to_vec())
+                .min_length(config.min_len)
+                .max_length(config.max_len)
+                .max_words(config.max_words)
+                .transforms(config.transforms.clone())
+                .filter(filter)
+                .build(),
+        ),
+        GenerationMode::Markov { model, max_level } => {
+            Box::new(MarkovGenerator::new(model, config.min_len, config.max_len, *max_level, filter)?)
         }
-    }
+        GenerationMode::Pcfg { model } => {
+            Box::new(PcfgGenerator::new(model, words, &config.transforms, config.min_len, config.max_len, filter))
+        }
+        GenerationMode::Prince => {
+            let max_elements = if config.max_words == usize::MAX { 0 } else { config.max_words };
+            Box::new(PrinceGenerator::new(words, &config.transforms, config.min_len, config.max_len, max_elements, filter)?)
+        }
+    };
+    let mut dedup = Deduplicator::new(&config.dedup);
+    let mut total_count = 0;
+    let mut chunk_buffer = Vec::with_capacity(config.chunk_size);
+    let mut stopped_early = false;
+    let start_time = Instant::now();
 
-    // Single suffix
-    for &special in special_chars {
-        let padded = format!("{}{}", base_combo, special);
-        if padded.len() >= config.min_len && padded.len() <= config.max_len {
-            add_to_buffer(padded, chunk_buffer, total_count, writer, config, start_time, last_update, first_display, all_words, current_word_count)?;
+    while let Some(k) = candidates.next_group() {
+        progress.start_permutation(k, &candidates.group());
+
+        while let Some(candidate) = candidates.next_in_group() {
+            let candidate = candidate.to_string();
+            add_to_buffer(candidate, &mut chunk_buffer, &mut total_count, &mut sink, &mut dedup, candidates.candidate_filter(), config, progress)?;
+            // Buffered candidates count towards the limit, or it would only be checked per chunk
+            if is_finished(config, total_count + chunk_buffer.len(), &sink, &dedup) || progress.control() != Control::Continue {
+                break;
+            }
         }
-    }
+        progress.finish_permutation();
+        progress.tick(&|| run_counters(total_count, &sink, &dedup, candidates.candidate_filter()));
 
-    // Multiple special chars (2 to n)
-    for k in 2..=n {
-        for special_combo in special_chars.iter().combinations(k) {
-            for perm_special in special_combo.iter().permutations(k) {
-                // Prefix
-                let mut padded = String::new();
-                for &&special in &perm_special {
-                    padded.push(*special);
-                }
-                padded.push_str(base_combo);
-                if padded.len() >= config.min_len && padded.len() <= config.max_len {
-                    add_to_buffer(padded, chunk_buffer, total_count, writer, config, start_time, last_update, first_display, all_words, current_word_count)?;
-                }
-
-                // Suffix
-                let mut padded = base_combo.to_string();
-                for &&special in &perm_special {
-                    padded.push(*special);
-                }
-                if padded.len() >= config.min_len && padded.len() <= config.max_len {
-                    add_to_buffer(padded, chunk_buffer, total_count, writer, config, start_time, last_update, first_display, all_words, current_word_count)?;
-                }
+        // Check limit and run controls
+        if is_finished(config, total_count + chunk_buffer.len(), &sink, &dedup) {
+            break;
+        }
+        match progress.control() {
+            Control::Continue => {}
+            Control::SkipTier => {
+                candidates.skip_tier();
+                progress.tier_skipped();
+                stopped_early = true;
+            }
+            Control::Quit => {
+                stopped_early = true;
+                break;
             }
         }
     }
 
-    Ok(())
+    // Write remaining combinations
+    if !chunk_buffer.is_empty() {
+        write_chunk(&mut sink, &mut dedup, &mut chunk_buffer, config.limit)?;
+        total_count += chunk_buffer.len();
+    }
+
+    // Exact dedup holds everything back in sorted runs until the end
+    if dedup.mode() == DedupMode::Exact {
+        total_count = dedup.merge_into(config.limit, |line| sink.write_candidate(line))?;
+    }
+
+    let filter = candidates.candidate_filter();
+    let counters = run_counters(total_count, &sink, &dedup, filter);
+    progress.finish(&counters);
+    sink.finish()?;
+    progress.complete(&counters);
+
+    Ok(GenerationSummary {
+        stopped_early,
+        count: total_count,
+        duplicates_removed: dedup.duplicates(),
+        excluded: filter.excluded(),
+        policy_rejected: filter.policy_rejected(),
+        regex_rejections: filter.regex_rejections(),
+        elapsed: start_time.elapsed(),
+    })
 }
 
+/// Buffer an accepted candidate, writing the buffer out once it is full.
+#[allow(clippy::too_many_arguments)]
 fn add_to_buffer(
     password: String,
     chunk_buffer: &mut Vec<String>,
     total_count: &mut usize,
-    writer: &mut BufWriter<File>,
+    sink: &mut impl CandidateSink,
+    dedup: &mut Deduplicator,
+    filter: &CandidateFilter,
     config: &GeneratorConfig,
-    start_time: &Instant,
-    last_update: &mut Instant,
-    first_display: &mut bool,
-    all_words: &[String],
-    current_word_count: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
+    progress: &mut (impl ProgressObserver + ?Sized),
+) -> Result<()> {
+    // The generator's filter has already run, so rejected passwords never occupy the dedup filter
+    if !dedup.accept(&password) {
+        return Ok(());
+    }
+
     chunk_buffer.push(password);
 
     if chunk_buffer.len() >= config.chunk_size {
-        write_chunk(writer, chunk_buffer)?;
+        write_chunk(sink, dedup, chunk_buffer, config.limit)?;
         *total_count += chunk_buffer.len();
         chunk_buffer.clear();
 
-        // Update status display
-        if !config.quiet && (*first_display || last_update.elapsed() >= Duration::from_secs(2)) {
-            update_status_display(*total_count, start_time, &config.output_file, all_words, current_word_count, *first_display, 0);
-            *last_update = Instant::now();
-            *first_display = false;
-        }
+        progress.tick(&|| run_counters(*total_count, sink, dedup, filter));
     }
 
     Ok(())
 }
 
-fn write_chunk(writer: &mut BufWriter<File>, combinations: &[String]) -> Result<(), Box<dyn std::error::Error>> {
-    for combination in combinations {
-        writeln!(writer, "{}", combination)?;
+fn write_chunk(
+    sink: &mut impl CandidateSink,
+    dedup: &mut Deduplicator,
+    combinations: &mut [String],
+    limit: usize,
+) -> Result<()> {
+    if dedup.mode() == DedupMode::Exact {
+        dedup.spill(combinations, limit)?;
+        return Ok(());
     }
+
+    sink.write_chunk(combinations)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+fn run_counters(total_count: usize, sink: &impl CandidateSink, dedup: &Deduplicator, filter: &CandidateFilter) -> RunCounters {
+    RunCounters {
+        written: total_count as u64,
+        bytes_written: sink.bytes_written(),
+        duplicates: dedup.duplicates(),
+        excluded: filter.excluded(),
+        policy_rejected: filter.policy_rejected(),
+        regex_rejections: filter.regex_rejections(),
+    }
+}
+
+/// The limit counts written passwords, after deduplication. Exact dedup
+/// holds candidates back in runs, so there it is reached once the runs are
+/// known to hold that many distinct candidates; `merge_into` then writes
+/// exactly the limit.
+fn is_finished(config: &GeneratorConfig, total_count: usize, sink: &impl CandidateSink, dedup: &Deduplicator) -> bool {
+    let written = if dedup.mode() == DedupMode::Exact { dedup.distinct() } else { total_count };
+    (config.limit > 0 && written >= config.limit) || sink.is_done()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+    use crate::display::SilentProgress;
+
+    fn small_config() -> GeneratorConfig {
+        GeneratorConfig {
+            max_words: 2,
+            transforms: TransformChain::parse("case").unwrap(),
+            chunk_size: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_generate_into_memory() {
+        let words = vec!["admin".to_string(), "pass".to_string()];
+        let config = small_config();
+
+        let mut collected = Vec::new();
+        let summary = generate_into(&words, &config, &mut collected, &mut SilentProgress).unwrap();
+
+        let expected: Vec<String> = PasswordGenerator::builder(words.clone())
+            .max_words(2)
+            .transforms(config.transforms.clone())
+            .build()
+            .collect();
+        assert_eq!(collected, expected);
+        assert_eq!(summary.count, expected.len());
+        assert!(!summary.stopped_early);
+    }
+
+    #[test]
+    fn test_limit_is_exact_within_a_chunk() {
+        let config = GeneratorConfig {
+            limit: 7,
+            chunk_size: 100,
+            ..small_config()
+        };
+        let mut collected = Vec::new();
+        let summary = generate_into(&["admin".to_string(), "pass".to_string()], &config, &mut collected, &mut SilentProgress).unwrap();
+        assert_eq!(collected.len(), 7);
+        assert_eq!(summary.count, 7);
+    }
+
+    #[test]
+    fn test_generate_into_from_markov_model() {
+        let mut model = MarkovModel::new(2).unwrap();
+        for password in ["abab", "abab", "baba"] {
+            model.add(password);
+        }
+        let config = GeneratorConfig {
+            mode: GenerationMode::Markov { model: Arc::new(model.clone()), max_level: None },
+            limit: 5,
+            ..small_config()
+        };
+
+        let mut collected = Vec::new();
+        let summary = generate_into(&[], &config, &mut collected, &mut SilentProgress).unwrap();
+        let expected: Vec<String> = MarkovGenerator::new(&model, 4, 20, None, CandidateFilter::default()).unwrap().take(5).collect();
+        assert_eq!(collected, expected);
+        assert_eq!(collected[0], "abab");
+        assert_eq!(summary.count, 5);
+    }
+
+    #[test]
+    fn test_limit_counts_distinct_candidates_under_exact_dedup() {
+        let words = vec!["ab".to_string(), "Ab".to_string()];
+        let config = GeneratorConfig {
+            min_len: 1,
+            limit: 10,
+            dedup: DedupConfig { mode: DedupMode::Exact, ..Default::default() },
+            ..small_config()
+        };
+
+        let mut collected = Vec::new();
+        let summary = generate_into(&words, &config, &mut collected, &mut SilentProgress).unwrap();
+        // Both words have the same case forms, so most raw candidates are duplicates
+        assert_eq!(collected.len(), 10);
+        assert_eq!(summary.count, 10);
+        assert_eq!(collected.iter().collect::<std::collections::HashSet<_>>().len(), 10);
+    }
+
+    #[test]
+    fn test_exact_dedup_stops_generating_at_limit() {
+        let words: Vec<String> = ('a'..='l').map(|c| c.to_string()).collect();
+        let config = GeneratorConfig {
+            min_len: 1,
+            limit: 4,
+            dedup: DedupConfig { mode: DedupMode::Exact, ..Default::default() },
+            ..small_config()
+        };
+
+        let mut progress = CountPermutations::default();
+        let mut collected = Vec::new();
+        generate_into(&words, &config, &mut collected, &mut progress).unwrap();
+        assert_eq!(collected, ["A", "B", "C", "a"]);
+        // Two chunks of three hold the limit, long before the 144 permutations run out
+        assert_eq!(progress.0, 3);
+    }
+
+    #[test]
+    fn test_calibration_sample_stops_under_exact_dedup() {
+        let dir = TempPath::dir("calibration");
+        let words: Vec<String> = ('a'..='z').map(|c| c.to_string()).collect();
+        let config = GeneratorConfig {
+            output_file: dir.join("out.txt"),
+            min_len: 1,
+            max_words: 3,
+            limit: 10,
+            // One chunk holds the whole job, so exact dedup would only spill at the end
+            chunk_size: 100_000,
+            dedup: DedupConfig { mode: DedupMode::Exact, ..Default::default() },
+            // Three-word candidates are rejected, and counted, only if the sample gets that far
+            reject_patterns: vec!["^[a-zA-Z]{3}$".to_string()],
+            quiet: true,
+            ..small_config()
+        };
+
+        let calibration = calibrate(&words, &config).unwrap();
+        assert_eq!(calibration.generated, 10);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_failed_setup_leaves_no_temp_file() {
+        let dir = TempPath::dir("setup");
+        let output = dir.join("out.txt");
+        for (exclude, reject) in [(vec!["/nonexistent/exclude.txt".to_string()], vec![]), (vec![], vec!["(".to_string()])] {
+            let config = GeneratorConfig {
+                output_file: output.clone(),
+                quiet: true,
+                exclude,
+                reject_patterns: reject,
+                ..small_config()
+            };
+            assert!(generate_combinations_streaming(&["admin".to_string()], &config).is_err());
+            let left: Vec<_> = std::fs::read_dir(dir.path()).unwrap().collect();
+            assert!(left.is_empty(), "{:?}", left);
+        }
+    }
+
+    #[test]
+    fn test_generate_into_prince_chains() {
+        let words = vec!["ab".to_string(), "cd".to_string()];
+        let config = GeneratorConfig { mode: GenerationMode::Prince, ..small_config() };
+
+        let mut collected = Vec::new();
+        let summary = generate_into(&words, &config, &mut collected, &mut SilentProgress).unwrap();
+        let expected: Vec<String> = PrinceGenerator::new(&words, &config.transforms, 4, 20, 2, CandidateFilter::default()).unwrap().collect();
+        assert_eq!(collected, expected);
+        // 6 case variants, two per chain; words repeat, unlike combinations
+        assert_eq!(summary.count, 36);
+        assert!(collected.contains(&"abab".to_string()));
+    }
+
+    /// Counts the permutations started.
+    #[derive(Default)]
+    struct CountPermutations(usize);
+
+    impl ProgressObserver for CountPermutations {
+        fn start_permutation(&mut self, _k: usize, _words: &[&String]) {
+            self.0 += 1;
+        }
+    }
+
+    /// Records permutations and quits once the first one is done.
+    #[derive(Default)]
+    struct QuitAfterFirst {
+        permutations: Vec<String>,
+        done: usize,
+        finished: Option<u64>,
+    }
+
+    impl ProgressObserver for QuitAfterFirst {
+        fn start_permutation(&mut self, _k: usize, words: &[&String]) {
+            self.permutations.push(words.iter().map(|w| w.as_str()).collect());
+        }
+
+        fn finish_permutation(&mut self) {
+            self.done += 1;
+        }
+
+        fn control(&self) -> Control {
+            match self.done {
+                0 => Control::Continue,
+                _ => Control::Quit,
+            }
+        }
+
+        fn complete(&mut self, counters: &RunCounters) {
+            self.finished = Some(counters.written);
+        }
+    }
+
+    #[test]
+    fn test_generate_into_custom_observer() {
+        let words = vec!["admin".to_string(), "pass".to_string()];
+        let mut progress = QuitAfterFirst::default();
+        let mut collected = Vec::new();
+
+        let summary = generate_into(&words, &small_config(), &mut collected, &mut progress).unwrap();
+
+        assert_eq!(progress.permutations, ["admin"]);
+        assert!(summary.stopped_early);
+        assert_eq!(progress.finished, Some(summary.count as u64));
+        assert_eq!(collected, ["ADMIN", "Admin", "admin"]);
+    }
+}
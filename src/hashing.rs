@@ -0,0 +1,137 @@
+use clap::ValueEnum;
+use md4::Md4;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// Unsalted hash algorithms supported for output and verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    /// MD4 over the UTF-16LE encoding of the password, as used by Windows
+    Ntlm,
+    Md4,
+}
+
+impl HashAlgorithm {
+    pub fn digest(&self, candidate: &str) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Md5 => Md5::digest(candidate.as_bytes()).to_vec(),
+            HashAlgorithm::Sha1 => Sha1::digest(candidate.as_bytes()).to_vec(),
+            HashAlgorithm::Sha256 => Sha256::digest(candidate.as_bytes()).to_vec(),
+            HashAlgorithm::Md4 => Md4::digest(candidate.as_bytes()).to_vec(),
+            HashAlgorithm::Ntlm => {
+                let utf16le: Vec<u8> = candidate.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+                Md4::digest(&utf16le).to_vec()
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "MD5",
+            HashAlgorithm::Sha1 => "SHA-1",
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Ntlm => "NTLM",
+            HashAlgorithm::Md4 => "MD4",
+        }
+    }
+
+    /// Lowercase hex digest, the form hashcat and john expect.
+    pub fn hex_digest(&self, candidate: &str) -> String {
+        to_hex(&self.digest(candidate))
+    }
+
+    /// Length of the raw digest in bytes.
+    pub fn digest_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Md5 | HashAlgorithm::Ntlm | HashAlgorithm::Md4 => 16,
+            HashAlgorithm::Sha1 => 20,
+            HashAlgorithm::Sha256 => 32,
+        }
+    }
+}
+
+/// How each candidate is written when hashing output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashOutput {
+    pub algorithm: HashAlgorithm,
+    /// Write only the hash instead of `hash:plaintext`
+    pub hash_only: bool,
+}
+
+impl HashOutput {
+    pub fn format(&self, candidate: &str) -> String {
+        let hash = self.algorithm.hex_digest(candidate);
+        if self.hash_only {
+            hash
+        } else {
+            format!("{}:{}", hash, candidate)
+        }
+    }
+
+    /// Output size for `count` candidates whose plaintext output would take
+    /// `plaintext_bytes` (newlines included).
+    pub fn estimate_size(&self, plaintext_bytes: u64, count: u64) -> u64 {
+        let hash_line = self.algorithm.digest_len() as u64 * 2 + 1;
+        if self.hash_only {
+            count.saturating_mul(hash_line)
+        } else {
+            plaintext_bytes.saturating_add(count.saturating_mul(hash_line))
+        }
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a hex string of either case. None if it is not valid hex.
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(HashAlgorithm::Md5.hex_digest("password"), "5f4dcc3b5aa765d61d8327deb882cf99");
+        assert_eq!(HashAlgorithm::Sha1.hex_digest("password"), "5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8");
+        assert_eq!(
+            HashAlgorithm::Sha256.hex_digest("password"),
+            "5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8"
+        );
+        assert_eq!(HashAlgorithm::Ntlm.hex_digest("password"), "8846f7eaee8fb117ad06bdd830b7586c");
+        assert_eq!(HashAlgorithm::Md4.hex_digest(""), "31d6cfe0d16ae931b73c59d7e0c089c0");
+    }
+
+    #[test]
+    fn test_hash_output_format() {
+        let pair = HashOutput { algorithm: HashAlgorithm::Ntlm, hash_only: false };
+        assert_eq!(pair.format("password"), "8846f7eaee8fb117ad06bdd830b7586c:password");
+
+        let hash_only = HashOutput { algorithm: HashAlgorithm::Md5, hash_only: true };
+        assert_eq!(hash_only.format("password"), "5f4dcc3b5aa765d61d8327deb882cf99");
+        assert_eq!(hash_only.estimate_size(900, 100), 3300);
+        assert_eq!(pair.estimate_size(900, 100), 4200);
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let digest = HashAlgorithm::Md5.digest("password");
+        assert_eq!(from_hex(&to_hex(&digest)).unwrap(), digest);
+        assert_eq!(from_hex("8846F7EA").unwrap(), vec![0x88, 0x46, 0xf7, 0xea]);
+        assert!(from_hex("abc").is_none());
+        assert!(from_hex("zz").is_none());
+    }
+}
@@ -1,7 +1,39 @@
 pub mod args;
+pub mod candidates;
 pub mod combinatorics;
+pub mod config;
+pub mod crack;
+pub mod dedup;
 pub mod display;
+pub mod error;
+pub mod exclude;
+pub mod filter;
 pub mod generator;
+pub mod hashing;
+pub mod markov;
+pub mod output;
+pub mod pcfg;
+pub mod policy;
+pub mod prince;
+pub mod profile;
+pub mod report;
+pub mod rules;
+pub mod sink;
+pub mod stats;
+#[cfg(test)]
+mod test_support;
+pub mod transform;
 pub mod words;
+pub mod wpa;
 
-pub use combinatorics::{calculate_total_combinations, CombinatorialConfig};
\ No newline at end of file
+pub use candidates::{CandidateSource, PasswordGenerator, PasswordGeneratorBuilder};
+pub use combinatorics::{calculate_total_combinations, CombinatorialConfig};
+pub use display::{ProgressObserver, SilentProgress};
+pub use error::Error;
+pub use filter::CandidateFilter;
+pub use generator::{generate_into, GenerationMode, GenerationSummary, GeneratorConfig};
+pub use markov::{MarkovGenerator, MarkovModel};
+pub use pcfg::{PcfgGenerator, PcfgModel};
+pub use prince::PrinceGenerator;
+pub use sink::CandidateSink;
+pub use transform::{Transform, TransformChain};
\ No newline at end of file
@@ -1,100 +1,674 @@
-mod args;
-mod words;
-mod generator;
-mod display;
-mod combinatorics;
-
-use clap::Parser;
-use args::Args;
+use p455w0rd::{args, combinatorics, config, crack, dedup, error, generator, markov, output, pcfg, policy, prince, report, rules, stats, words, wpa};
+use args::{AnalyzeArgs, Command, CrackArgs, GenerationArgs, ModelArgs, OutputArgs, ProfileArgs, RulesArgs, StatsArgs, ModelKind, TrainArgs, WordArgs, Wpa2Args};
 use words::get_words;
-use generator::{generate_combinations_streaming, GeneratorConfig};
-use combinatorics::{calculate_total_combinations, CombinatorialConfig, format_file_size, format_combination_count};
+use generator::{calibrate, crack_combinations, generate_combinations_streaming, verify_wpa_combinations, GenerationMode, GenerationSummary, GeneratorConfig};
+use combinatorics::{calculate_total_combinations, estimate_policy_survivors, CombinatorialAnalysis, CombinatorialConfig, format_duration, format_file_size, format_combination_count};
+use crack::Cracker;
+use dedup::{DedupConfig, DedupMode};
+use markov::MarkovModel;
+use pcfg::{PcfgGenerator, PcfgModel};
+use prince::PrinceGenerator;
+use error::{Error, Result};
+use output::{display_name, manifest_path, Compression, OutputWriter};
+use policy::PasswordPolicy;
+use report::{AnalysisReport, SizeEstimate};
+use rules::{apply_rules, load_rules};
+use stats::{wordlist_stats, WordlistStats};
+use std::io::Write;
+use std::process::ExitCode;
+use std::sync::Arc;
+use wpa::WpaVerifier;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+fn main() -> ExitCode {
+    // Each kind of failure has its own exit code, listed in the README
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
 
-    // Set length constraints for WPA2
-    let (min_len, max_len) = args.get_length_constraints();
+fn run() -> Result<()> {
+    let (args, matches) = config::parse_args(std::env::args_os())?;
 
-    // Get words from input
-    let words = get_words(&args)?;
+    if let Some(path) = &args.config.save_config {
+        std::fs::write(path, config::effective_config(&matches)).map_err(|e| Error::output(path, e))?;
+        eprintln!("Saved configuration to {}", path);
+        return Ok(());
+    }
 
-    if words.is_empty() {
-        eprintln!("No words provided. Use --input file or provide words as arguments.");
-        std::process::exit(1);
+    match &args.command {
+        Some(Command::Generate(generate)) => run_generate(&generate.generation, &generate.output, &generate.model),
+        Some(Command::Analyze(analyze)) => run_analyze(analyze),
+        Some(Command::Stats(stats)) => run_stats(stats),
+        Some(Command::Profile(profile)) => run_profile(profile),
+        Some(Command::Rules(rules)) => run_rules(rules),
+        Some(Command::Crack(crack)) => run_crack(crack),
+        Some(Command::Wpa2(wpa2)) => run_wpa2(wpa2),
+        Some(Command::Train(train)) => run_train(train),
+        // Running without a subcommand is the same as `generate`
+        None => run_generate(&args.generation, &args.output, &args.model),
+    }
+}
+
+fn run_generate(args: &GenerationArgs, output: &OutputArgs, model: &ModelArgs) -> Result<()> {
+    args.validate()?;
+    output.validate()?;
+    if let Some(path) = &model.markov {
+        return generate_markov(args, output, path, model.markov_max_level);
+    }
+
+    let words = load_words(&args.keyspace.words)?;
+    if let Some(path) = &model.pcfg {
+        return generate_pcfg(args, output, path, &words);
+    }
+    if model.prince {
+        return generate_prince(args, output, &words);
+    }
+    generate_words(args, output, &words)
+}
+
+fn run_profile(profile: &ProfileArgs) -> Result<()> {
+    let args = &profile.generation;
+    args.validate()?;
+    profile.output.validate()?;
+
+    let target = profile.profile();
+    let mut words = target.words()?;
+    words.extend(get_words(&args.keyspace.words)?);
+    words.sort();
+    words.dedup();
+
+    if target.is_empty() && words.is_empty() {
+        return Err(Error::config("No target details provided. Use --first-name, --pet, --date, --keyword, etc."));
+    }
+
+    eprintln!("Profile produced {} base words: {}", words.len(), words.join(", "));
+    generate_words(args, &profile.output, &words)
+}
+
+fn run_train(train: &TrainArgs) -> Result<()> {
+    let output = train.get_output();
+    let (passwords, skipped) = match train.kind {
+        ModelKind::Markov => {
+            let model = MarkovModel::train(&train.input, train.order)?;
+            if model.passwords > 0 {
+                model.save(output)?;
+                eprintln!("Trained an order-{} Markov model on {} passwords from {}", model.order, model.passwords, train.input);
+                eprintln!("{} n-grams over {} characters, saved to {}", model.ngrams.len(), model.alphabet().len(), output);
+            }
+            (model.passwords, model.skipped)
+        }
+        ModelKind::Pcfg => {
+            let model = PcfgModel::train(&train.input)?;
+            if model.passwords > 0 {
+                model.save(output)?;
+                eprintln!("Trained a PCFG on {} passwords from {}", model.passwords, train.input);
+                eprintln!(
+                    "{} base structures, {} digit runs, {} symbol runs, saved to {}",
+                    model.structures.len(),
+                    model.digits.values().map(|runs| runs.len()).sum::<usize>(),
+                    model.symbols.values().map(|runs| runs.len()).sum::<usize>(),
+                    output
+                );
+            }
+            (model.passwords, model.skipped)
+        }
+    };
+    if passwords == 0 {
+        return Err(Error::config(format!("No passwords to train on in {}", train.input)));
+    }
+    if skipped > 0 {
+        eprintln!("Skipped {} empty, non-UTF-8 or control-character lines", skipped);
     }
+    Ok(())
+}
 
-    println!("Processing {} words...", words.len());
+fn run_analyze(analyze: &AnalyzeArgs) -> Result<()> {
+    let words = load_words(&analyze.keyspace.words)?;
+    let analysis = calculate_total_combinations(&words, &analyze.keyspace.combinatorial_config())?;
 
-    // Calculate combinatorial analysis
-    let combinatorial_config = CombinatorialConfig {
-        max_words: args.get_max_words(),
-        include_special_chars: !args.no_special_chars,
+    let mut out = std::io::stdout().lock();
+    let result = if analyze.json {
+        writeln!(out, "{}", to_json(&analysis)?)
+    } else {
+        print_analysis(&mut out, &words, &analyze.keyspace.combinatorial_config(), &analysis, None, None)
     };
+    ignore_broken_pipe(result)
+}
+
+fn run_stats(stats: &StatsArgs) -> Result<()> {
+    let report = wordlist_stats(&stats.wordlist, stats.top)?;
+
+    let mut out = std::io::stdout().lock();
+    if stats.json {
+        return ignore_broken_pipe(writeln!(out, "{}", to_json(&report)?));
+    }
+    ignore_broken_pipe(print_stats(&mut out, &stats.wordlist, &report))
+}
 
-    let analysis = calculate_total_combinations(&words, &combinatorial_config)?;
+fn print_stats(out: &mut dyn Write, wordlist: &str, report: &WordlistStats) -> std::io::Result<()> {
+    let share = |count: u64| 100.0 * count as f64 / report.lines.max(1) as f64;
+    writeln!(out, "Wordlist: {}", wordlist)?;
+    writeln!(out, "  Lines: {} (unique: {}, {:.1}%)", report.lines, report.unique, share(report.unique))?;
+    if report.invalid_utf8 > 0 {
+        writeln!(out, "  Invalid UTF-8: {}", report.invalid_utf8)?;
+    }
+    writeln!(out, "  Length: min {}, max {}, average {:.1}", report.min_length, report.max_length, report.average_length)?;
 
-    // Display analysis
-    println!("\nCombinatorial Analysis:");
-    println!("  Total combinations: {} (exact: {})", format_combination_count(analysis.total_combinations), analysis.total_combinations);
-    println!("  Estimated file size: {}", format_file_size(analysis.estimated_file_size_bytes));
-    println!("  Word permutations: {}", format_combination_count(analysis.breakdown.word_permutations));
-    println!("  Leet variants: {}", format_combination_count(analysis.breakdown.leet_variants));
-    println!("  Case variations: {}", analysis.breakdown.case_variants);
-    println!("  Special char variants: {}", format_combination_count(analysis.breakdown.special_char_variants));
+    writeln!(out, "\nLengths:")?;
+    for (length, count) in &report.lengths {
+        writeln!(out, "  {:>3}: {} ({:.1}%)", length, count, share(*count))?;
+    }
+    writeln!(out, "\nCharacter sets:")?;
+    for (charset, count) in &report.charsets {
+        writeln!(out, "  {}: {} ({:.1}%)", charset, count, share(*count))?;
+    }
+    writeln!(out, "\nTop masks:")?;
+    for (mask, count) in &report.top_masks {
+        writeln!(out, "  {}: {} ({:.1}%)", mask, count, share(*count))?;
+    }
+    Ok(())
+}
+
+fn run_rules(rules: &RulesArgs) -> Result<()> {
+    let output = &rules.output;
+    output.validate()?;
+
+    let loaded = load_rules(&rules.rule_files)?;
+    eprintln!("Applying {} rules to {}", loaded.len(), rules.input);
+
+    let writer = OutputWriter::open(output.get_output(), output.append, output.get_compression(), output.get_split())
+        .map_err(|e| Error::output(display_name(output.get_output()), e))?
+        .with_hash(output.get_hash_output());
+    let summary = match apply_rules(&rules.input, &loaded, writer) {
+        Ok(summary) => summary,
+        Err(e) if e.is_broken_pipe() => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    eprintln!("Generated {} candidates from {} words to {}", summary.count, summary.words, display_name(output.get_output()));
+    if summary.invalid_utf8 > 0 {
+        eprintln!("Skipped {} lines that are not valid UTF-8", summary.invalid_utf8);
+    }
+    if output.get_split().is_some() {
+        eprintln!("Part manifest written to {}", manifest_path(output.get_output()));
+    }
+    Ok(())
+}
 
-    println!("\nBreakdown by word count:");
+/// Analyze, confirm and generate candidates from `words` into `output`.
+fn generate_words(args: &GenerationArgs, output: &OutputArgs, words: &[String]) -> Result<()> {
+    let words = words.to_vec();
+    let analysis = calculate_total_combinations(&words, &args.keyspace.combinatorial_config())?;
+
+    let compression = output.get_compression();
+    let hash_output = output.get_hash_output();
+    let output_bytes = match hash_output {
+        Some(hash) => hash.estimate_size(analysis.keyspace_bytes(), analysis.keyspace()),
+        None => analysis.keyspace_bytes(),
+    };
+
+    let policy = args.get_policy()?;
+    if args.dry_run {
+        let size = SizeEstimate {
+            raw_bytes: output_bytes,
+            compression: compression.name(),
+            compressed_bytes: compression.estimate_size(output_bytes),
+        };
+        return print_report(args, &words, &analysis, policy.as_ref(), Some(size));
+    }
+    print_analysis(&mut std::io::stderr(), &words, &args.keyspace.combinatorial_config(), &analysis, policy.as_ref(), Some((output_bytes, compression)))
+        .map_err(|e| Error::output("stderr", e))?;
+
+    // Create generator configuration
+    let config = GeneratorConfig {
+        output_file: output.get_output().to_string(),
+        append: output.append,
+        compression,
+        split: output.get_split(),
+        hash: hash_output,
+        expected_bytes: Some(limited_size(output_bytes, analysis.keyspace(), args.limit)),
+        ..generator_config(args, analysis.total_combinations, policy)
+    };
+
+    // Require confirmation unless --force is used, with a projection measured on this machine
+    if needs_confirmation(args, &analysis) {
+        // The whole job, not the capped total, unless --limit ends it sooner
+        let job = match args.limit {
+            0 => analysis.keyspace(),
+            limit => analysis.keyspace().min(limit as u64),
+        };
+        let mut warning = format!(
+            "This will generate {} passwords (estimated size: {})",
+            format_combination_count(job),
+            format_file_size(compression.estimate_size(output_bytes))
+        );
+        eprintln!("\nCalibrating on a sample of the job...");
+        match calibrate(&words, &config) {
+            Ok(calibration) => {
+                let (seconds, bytes) = calibration.project(job);
+                warning.push_str(&format!(
+                    "\n    Measured speed: {:.0} passwords/s, {}/s written",
+                    calibration.candidates_per_second(),
+                    format_file_size(calibration.bytes_per_second() as u64)
+                ));
+                warning.push_str(&format!(
+                    "\n    Projected: {} wall time, {} on disk",
+                    format_duration(seconds),
+                    format_file_size(bytes)
+                ));
+            }
+            Err(e) => eprintln!("Calibration failed, no runtime projection: {}", e),
+        }
+        if !confirm_large_job(args, &analysis, &warning)? {
+            return Ok(());
+        }
+    }
+
+    // Generate and write combinations incrementally
+    let summary = match generate_combinations_streaming(&words, &config) {
+        Ok(summary) => summary,
+        // The consumer on the other end of the pipe exited; that is not our failure
+        Err(e) if e.is_broken_pipe() => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    print_written(&config, &summary);
+    check_count(&summary, &analysis);
+
+    Ok(())
+}
+
+/// Enumerate candidates from a trained Markov model into `output`. There is
+/// no word list, so no combinatorial analysis, size estimate or prompt.
+fn generate_markov(args: &GenerationArgs, output: &OutputArgs, path: &str, max_level: Option<u32>) -> Result<()> {
+    if args.dry_run {
+        return Err(Error::config("--dry-run analyzes word combinations and is not available with --markov"));
+    }
+    if args.keyspace.words.input.is_some() || !args.keyspace.words.words.is_empty() {
+        eprintln!("⚠️  Warning: --markov generates without a word list; the input words are ignored");
+    }
+
+    let model = MarkovModel::load(path)?;
+    eprintln!("Loaded an order-{} Markov model trained on {} passwords", model.order, model.passwords);
+    if args.limit == 0 && max_level.is_none() {
+        eprintln!("No --limit or --markov-max-level: generating until stopped or every string up to {} characters is done", args.keyspace.get_length_constraints().1);
+    }
+
+    generate_from_model(args, output, GenerationMode::Markov { model: Arc::new(model), max_level }, &[], u64::MAX)
+}
+
+/// Generate Weir-style PCFG candidates into `output`: the model's
+/// structures, with letter runs filled from `words`.
+fn generate_pcfg(args: &GenerationArgs, output: &OutputArgs, path: &str, words: &[String]) -> Result<()> {
+    if args.dry_run {
+        return Err(Error::config("--dry-run analyzes word combinations and is not available with --pcfg"));
+    }
+
+    let model = PcfgModel::load(path)?;
+    let (min_len, max_len) = args.keyspace.get_length_constraints();
+    let grammar = PcfgGenerator::new(&model, words, &args.keyspace.get_transforms(), min_len, max_len, Default::default());
+    eprintln!("Loaded a PCFG of {} base structures trained on {} passwords", model.structures.len(), model.passwords);
+    if grammar.structures() == 0 {
+        return Err(Error::config("No base structure of the PCFG fits the length bounds with letter runs the input words can fill"));
+    }
+    eprintln!(
+        "{} structures can be filled from the words: up to {} candidates",
+        grammar.structures(),
+        format_combination_count(grammar.keyspace())
+    );
+
+    generate_from_model(args, output, GenerationMode::Pcfg { model: Arc::new(model) }, words, grammar.keyspace())
+}
+
+/// Generate PRINCE chains of the input words into `output`.
+fn generate_prince(args: &GenerationArgs, output: &OutputArgs, words: &[String]) -> Result<()> {
+    if args.dry_run {
+        return Err(Error::config("--dry-run analyzes word combinations and is not available with --prince"));
+    }
+
+    let (min_len, max_len) = args.keyspace.get_length_constraints();
+    let chains = PrinceGenerator::new(words, &args.keyspace.get_transforms(), min_len, max_len, args.keyspace.max_words, Default::default())?;
+    if chains.chains() == 0 {
+        return Err(Error::config(format!("No chain of the input words is {} to {} characters long", min_len, max_len)));
+    }
+    eprintln!(
+        "PRINCE: {} elements in {} length combinations: up to {} candidates",
+        chains.elements(),
+        chains.chains(),
+        format_combination_count(chains.keyspace())
+    );
+
+    generate_from_model(args, output, GenerationMode::Prince, words, chains.keyspace())
+}
+
+/// Stream a model-driven or PRINCE generation of up to `keyspace`
+/// candidates into `output`. These modes have no combinatorial analysis,
+/// size estimate or confirmation prompt.
+fn generate_from_model(args: &GenerationArgs, output: &OutputArgs, mode: GenerationMode, words: &[String], keyspace: u64) -> Result<()> {
+    let config = GeneratorConfig {
+        mode,
+        output_file: output.get_output().to_string(),
+        append: output.append,
+        compression: output.get_compression(),
+        split: output.get_split(),
+        hash: output.get_hash_output(),
+        ..generator_config(args, if args.limit > 0 { keyspace.min(args.limit as u64) } else { keyspace }, args.get_policy()?)
+    };
+
+    let summary = match generate_combinations_streaming(words, &config) {
+        Ok(summary) => summary,
+        Err(e) if e.is_broken_pipe() => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    print_written(&config, &summary);
+    if summary.stopped_early {
+        eprintln!("Stopped early from the run controls; the output is complete up to that point");
+    }
+    Ok(())
+}
+
+fn run_crack(crack: &CrackArgs) -> Result<()> {
+    let args = &crack.generation;
+    args.validate()?;
+
+    let mut cracker = Cracker::load(&crack.hashes, crack.hash_type, &crack.potfile)?;
+    eprintln!("Loaded {} {} hashes from {}", cracker.total(), crack.hash_type.name(), crack.hashes);
+    if cracker.invalid_lines() > 0 {
+        eprintln!("Skipped {} lines without a valid hash", cracker.invalid_lines());
+    }
+    if cracker.previously_cracked() > 0 {
+        eprintln!("{} hashes already recovered in {}", cracker.previously_cracked(), crack.potfile);
+    }
+    if cracker.is_done() {
+        eprintln!("Nothing left to crack.");
+        return Ok(());
+    }
+
+    let words = load_words(&args.keyspace.words)?;
+    let analysis = calculate_total_combinations(&words, &args.keyspace.combinatorial_config())?;
+    let policy = args.get_policy()?;
+    if args.dry_run {
+        return print_report(args, &words, &analysis, policy.as_ref(), None);
+    }
+    print_analysis(&mut std::io::stderr(), &words, &args.keyspace.combinatorial_config(), &analysis, policy.as_ref(), None)
+        .map_err(|e| Error::output("stderr", e))?;
+
+    if !confirm_large_job(args, &analysis, &format!(
+        "This will try {} candidates",
+        format_combination_count(analysis.total_combinations)
+    ))? {
+        return Ok(());
+    }
+
+    // The status display reports the potfile as the output
+    let config = GeneratorConfig {
+        output_file: crack.potfile.clone(),
+        ..generator_config(args, analysis.total_combinations, policy)
+    };
+
+    let summary = crack_combinations(&words, &config, &mut cracker)?;
+
+    for (hash, plaintext) in cracker.found() {
+        println!("{}:{}", hash, plaintext);
+    }
+
+    eprintln!("Tried {} candidates", summary.count);
+    print_filter_summary(&config, &summary);
+    eprintln!("Recovered {}/{} hashes ({} this run), potfile: {}",
+             cracker.previously_cracked() + cracker.found().len(),
+             cracker.total(),
+             cracker.found().len(),
+             crack.potfile);
+    if !cracker.is_done() {
+        check_count(&summary, &analysis);
+    }
+
+    Ok(())
+}
+
+fn run_wpa2(wpa2: &Wpa2Args) -> Result<()> {
+    let args = &wpa2.generation;
+    args.validate()?;
+
+    let mut verifier = WpaVerifier::load(&wpa2.handshake)?;
+    eprintln!("Loaded {} handshakes from {}", verifier.handshake_count(), wpa2.handshake);
+
+    let words = load_words(&args.keyspace.words)?;
+    let analysis = calculate_total_combinations(&words, &args.keyspace.combinatorial_config())?;
+    let policy = args.get_policy()?;
+    if args.dry_run {
+        return print_report(args, &words, &analysis, policy.as_ref(), None);
+    }
+    print_analysis(&mut std::io::stderr(), &words, &args.keyspace.combinatorial_config(), &analysis, policy.as_ref(), None)
+        .map_err(|e| Error::output("stderr", e))?;
+
+    if !confirm_large_job(args, &analysis, &format!(
+        "This will try {} passphrases",
+        format_combination_count(analysis.total_combinations)
+    ))? {
+        return Ok(());
+    }
+
+    // Only 8-63 character passphrases are valid for WPA-PSK
+    let (min_len, max_len) = args.keyspace.get_length_constraints();
+    let config = GeneratorConfig {
+        min_len: min_len.max(8),
+        max_len: max_len.min(63),
+        chunk_size: args.chunk_size.min(wpa::MAX_CHUNK_SIZE),
+        output_file: wpa2.handshake.clone(),
+        ..generator_config(args, analysis.total_combinations, policy)
+    };
+
+    let summary = verify_wpa_combinations(&words, &config, &mut verifier)?;
+
+    for (handshake, passphrase) in verifier.found() {
+        println!("{}", handshake.potfile_line(passphrase));
+        eprintln!("Passphrase found for {}: {}", String::from_utf8_lossy(handshake.essid()), passphrase);
+    }
+
+    eprintln!("Tried {} passphrases", summary.count);
+    print_filter_summary(&config, &summary);
+    if verifier.found().next().is_none() {
+        eprintln!("Passphrase not found");
+    }
+
+    Ok(())
+}
+
+fn load_words(args: &WordArgs) -> Result<Vec<String>> {
+    // Get words from input
+    let words = get_words(args)?;
+
+    if words.is_empty() {
+        return Err(Error::config("No words provided. Use --input file or provide words as arguments."));
+    }
+
+    eprintln!("Processing {} words...", words.len());
+    Ok(words)
+}
+
+/// Print the combinatorial analysis. `output_size` is the estimated raw
+/// output size and compression, when candidates are written to a file.
+fn print_analysis(
+    out: &mut dyn Write,
+    words: &[String],
+    config: &CombinatorialConfig,
+    analysis: &CombinatorialAnalysis,
+    policy: Option<&PasswordPolicy>,
+    output_size: Option<(u64, Compression)>,
+) -> std::io::Result<()> {
+    writeln!(out, "\nCombinatorial Analysis:")?;
+    writeln!(out, "  Total combinations: {} (exact: {})", format_combination_count(analysis.total_combinations), analysis.total_combinations)?;
+    match output_size {
+        Some((output_bytes, Compression::None)) => {
+            writeln!(out, "  Estimated file size: {}", format_file_size(output_bytes))?;
+        }
+        Some((output_bytes, compression)) => {
+            writeln!(out, "  Estimated file size: {} raw, ~{} with {}",
+                     format_file_size(output_bytes),
+                     format_file_size(compression.estimate_size(output_bytes)),
+                     compression.name())?;
+        }
+        None => {}
+    }
+    let transforms = &analysis.breakdown.transforms;
+    writeln!(out, "  Transforms: {}", if transforms.is_empty() { "none".to_string() } else { transforms.join(", ") })?;
+    writeln!(out, "  Word permutations: {}", format_combination_count(analysis.breakdown.word_permutations))?;
+    writeln!(out, "  Leet variants: {}", format_combination_count(analysis.breakdown.leet_variants))?;
+    writeln!(out, "  Case variations: {}", analysis.breakdown.case_variants)?;
+    writeln!(out, "  Special char variants: {}", format_combination_count(analysis.breakdown.special_char_variants))?;
+
+    if let Some(policy) = policy {
+        let estimate = estimate_policy_survivors(words, config, policy);
+        writeln!(out, "  Policy survivors: {}{} (exact: {})",
+                 if estimate.exact { "" } else { "at most " },
+                 format_combination_count(estimate.survivors),
+                 estimate.survivors)?;
+    }
+
+    writeln!(out, "\nBreakdown by word count:")?;
     for breakdown in &analysis.breakdown.by_word_count {
-        println!("  {} words: {} (exact: {}) (avg length: {:.1})",
+        writeln!(out, "  {} words: {} (exact: {}) (avg length: {:.1})",
                  breakdown.word_count,
                  format_combination_count(breakdown.combinations),
                  breakdown.combinations,
-                 breakdown.average_length);
+                 breakdown.average_length)?;
     }
+    Ok(())
+}
 
-    // Require confirmation unless --force is used
-    if !args.force && analysis.total_combinations > 1_000_000 {
-        println!("\n⚠️  Warning: This will generate {} passwords (estimated size: {})",
-                 format_combination_count(analysis.total_combinations),
-                 format_file_size(analysis.estimated_file_size_bytes));
+/// Print the `--dry-run` report to stdout.
+fn print_report(
+    args: &GenerationArgs,
+    words: &[String],
+    analysis: &CombinatorialAnalysis,
+    policy: Option<&PasswordPolicy>,
+    output: Option<SizeEstimate>,
+) -> Result<()> {
+    let policy = policy.map(|policy| estimate_policy_survivors(words, &args.keyspace.combinatorial_config(), policy));
+    let report = AnalysisReport::new(words, &args.keyspace.get_transforms(), analysis, output, policy).render(args.format)?;
+    ignore_broken_pipe(writeln!(std::io::stdout().lock(), "{}", report.trim_end()))
+}
 
-        print!("Do you want to continue? [y/N]: ");
-        use std::io::Write;
-        std::io::stdout().flush()?;
+/// A reader that stops early (`| head`) is not an error.
+fn ignore_broken_pipe(result: std::io::Result<()>) -> Result<()> {
+    match result {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        other => other.map_err(|e| Error::output("stdout", e)),
+    }
+}
 
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
+fn to_json(value: &impl serde::Serialize) -> Result<String> {
+    serde_json::to_string_pretty(value).map_err(|e| Error::output("stdout", e.into()))
+}
 
-        let input = input.trim().to_lowercase();
-        if input != "y" && input != "yes" {
-            println!("Operation cancelled.");
-            std::process::exit(0);
-        }
+fn needs_confirmation(args: &GenerationArgs, analysis: &CombinatorialAnalysis) -> bool {
+    !args.force && analysis.total_combinations > 1_000_000
+}
+
+/// Ask before starting a job of more than a million candidates, unless
+/// --force. False when the user declines, which is not an error.
+fn confirm_large_job(args: &GenerationArgs, analysis: &CombinatorialAnalysis, warning: &str) -> Result<bool> {
+    if !needs_confirmation(args, analysis) {
+        return Ok(true);
     }
 
-    // Create generator configuration
-    let config = GeneratorConfig {
+    eprintln!("\n⚠️  Warning: {}", warning);
+    eprint!("Do you want to continue? [y/N]: ");
+    std::io::stderr().flush().map_err(|e| Error::output("stderr", e))?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input).map_err(|e| Error::input("stdin", e))?;
+
+    let input = input.trim().to_lowercase();
+    if input != "y" && input != "yes" {
+        eprintln!("Operation cancelled.");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Generator settings shared by every mode, writing uncompressed plaintext
+/// to the default output until the caller overrides it.
+fn generator_config(args: &GenerationArgs, expected_items: u64, policy: Option<PasswordPolicy>) -> GeneratorConfig {
+    let (min_len, max_len) = args.keyspace.get_length_constraints();
+    GeneratorConfig {
+        mode: GenerationMode::Combinations,
         min_len,
         max_len,
         limit: args.limit,
-        output_file: args.output.clone(),
+        output_file: String::new(),
         chunk_size: args.chunk_size,
         quiet: args.quiet,
-        append: args.append,
-        max_words: args.get_max_words(),
-        no_special_chars: args.no_special_chars,
-    };
+        append: false,
+        max_words: args.keyspace.get_max_words(),
+        transforms: args.keyspace.get_transforms(),
+        compression: Compression::None,
+        split: None,
+        dedup: DedupConfig {
+            mode: args.dedup,
+            fp_rate: args.dedup_fp_rate,
+            max_memory: args.dedup_max_memory,
+            expected_items,
+            temp_dir: args.temp_dir.clone().map(Into::into).unwrap_or_else(std::env::temp_dir),
+        },
+        exclude: args.exclude.clone(),
+        policy,
+        match_patterns: args.match_patterns.clone(),
+        reject_patterns: args.reject_patterns.clone(),
+        hash: None,
+        expected_bytes: None,
+        force: args.force,
+        status_events: args.get_status_events(),
+    }
+}
 
-    // Generate and write combinations incrementally
-    let count = generate_combinations_streaming(&words, &config)?;
+/// Scale an output size estimate down to the share `--limit` lets through.
+fn limited_size(bytes: u64, total: u64, limit: usize) -> u64 {
+    if limit == 0 || limit as u64 >= total {
+        bytes
+    } else {
+        (bytes as f64 * limit as f64 / total as f64) as u64
+    }
+}
 
-    println!("Generated {} passwords to {}", count, args.output);
+fn print_written(config: &GeneratorConfig, summary: &GenerationSummary) {
+    eprintln!("Generated {} passwords to {}", summary.count, display_name(&config.output_file));
+    print_filter_summary(config, summary);
+    if config.split.is_some() {
+        eprintln!("Part manifest written to {}", manifest_path(&config.output_file));
+    }
+}
 
-    // Verify the count matches our calculation
-    if count != analysis.total_combinations as usize && analysis.total_combinations != u64::MAX {
-        println!("⚠️  Generated count ({}) differs from calculated count ({})",
-                 count, format_combination_count(analysis.total_combinations));
+fn print_filter_summary(config: &GeneratorConfig, summary: &GenerationSummary) {
+    if config.dedup.mode != DedupMode::None {
+        eprintln!("Removed {} duplicate passwords", summary.duplicates_removed);
+    }
+    if !config.exclude.is_empty() {
+        eprintln!("Excluded {} passwords found in exclusion lists", summary.excluded);
+    }
+    if config.policy.is_some() {
+        eprintln!("Rejected {} passwords not meeting the password policy", summary.policy_rejected);
     }
+    for (label, rejected) in &summary.regex_rejections {
+        eprintln!("Rejected {} passwords by {}", rejected, label);
+    }
+}
 
-    Ok(())
-}
\ No newline at end of file
+/// Verify the count matches our calculation, counting dropped candidates as generated.
+fn check_count(summary: &GenerationSummary, analysis: &CombinatorialAnalysis) {
+    if summary.stopped_early {
+        eprintln!("Stopped early from the run controls; the output is complete up to that point");
+        return;
+    }
+    let generated = summary.generated();
+    if generated != analysis.total_combinations && analysis.total_combinations != u64::MAX {
+        eprintln!("⚠️  Generated count ({}) differs from calculated count ({})",
+                 generated, format_combination_count(analysis.total_combinations));
+    }
+}
@@ -0,0 +1,446 @@
+use crate::candidates::CandidateSource;
+use crate::error::{Error, Result};
+use crate::filter::CandidateFilter;
+use crate::words::read_passwords;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Pads n-gram contexts before the first character of a password.
+pub const START: char = '\u{2}';
+/// Follows the last character of a password in its final n-gram.
+pub const END: char = '\u{3}';
+/// N-gram length used by `train` unless told otherwise.
+pub const DEFAULT_ORDER: usize = 3;
+/// Longest supported n-gram; contexts are packed 16 bits per character.
+pub const MAX_ORDER: usize = 5;
+/// Level of the least likely n-gram or length. Like OMEN, probabilities
+/// are bucketed into levels 0 (likely) to 10.
+pub const MAX_LEVEL: u32 = 10;
+/// Added to every n-gram and length count, so unseen ones stay possible
+/// at a high level.
+const SMOOTHING: f64 = 0.01;
+
+/// Character n-gram and length counts of a password list, from which
+/// `MarkovGenerator` enumerates candidates most probable first. `train`
+/// saves it as JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MarkovModel {
+    /// N-gram length: each character is predicted from the `order - 1`
+    /// characters before it
+    pub order: usize,
+    /// Passwords the model was trained on
+    pub passwords: u64,
+    /// Lines skipped for being empty, not UTF-8 or holding control characters
+    #[serde(default)]
+    pub skipped: u64,
+    /// Training passwords per length in characters
+    pub lengths: BTreeMap<usize, u64>,
+    /// Count of each n-gram. Contexts at the start of a password are padded
+    /// with `START`, and `END` follows its last character.
+    pub ngrams: BTreeMap<String, u64>,
+}
+
+impl MarkovModel {
+    /// An empty model of the given n-gram length.
+    pub fn new(order: usize) -> Result<Self> {
+        if !(2..=MAX_ORDER).contains(&order) {
+            return Err(Error::config(format!("--order must be between 2 and {}", MAX_ORDER)));
+        }
+        Ok(MarkovModel {
+            order,
+            ..Default::default()
+        })
+    }
+
+    /// Train on a plaintext password list, one password per line.
+    pub fn train(path: &str, order: usize) -> Result<Self> {
+        let mut model = MarkovModel::new(order)?;
+        model.skipped = read_passwords(path, |password| model.add(password))?;
+        Ok(model)
+    }
+
+    /// Count one password; false (and nothing counted) if it is empty or
+    /// holds control characters.
+    pub fn add(&mut self, password: &str) -> bool {
+        if password.is_empty() || password.chars().any(char::is_control) {
+            return false;
+        }
+        let padded: Vec<char> = std::iter::repeat_n(START, self.order - 1)
+            .chain(password.chars())
+            .chain(std::iter::once(END))
+            .collect();
+        for ngram in padded.windows(self.order) {
+            *self.ngrams.entry(ngram.iter().collect()).or_default() += 1;
+        }
+        *self.lengths.entry(padded.len() - self.order).or_default() += 1;
+        self.passwords += 1;
+        true
+    }
+
+    /// Load a model saved by `save`.
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::input(path, e))?;
+        let model: MarkovModel =
+            serde_json::from_str(&content).map_err(|e| Error::parse(path, Some(e.line()), e.to_string()))?;
+
+        if !(2..=MAX_ORDER).contains(&model.order) {
+            return Err(Error::parse(path, None, format!("order must be between 2 and {}", MAX_ORDER)));
+        }
+        if let Some(ngram) = model.ngrams.keys().find(|ngram| ngram.chars().count() != model.order) {
+            return Err(Error::parse(path, None, format!("n-gram {:?} does not have {} characters", ngram, model.order)));
+        }
+        Ok(model)
+    }
+
+    /// Save the model as JSON.
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut out = BufWriter::new(File::create(path).map_err(|e| Error::output(path, e))?);
+        serde_json::to_writer(&mut out, self).map_err(|e| Error::output(path, e.into()))?;
+        out.flush().map_err(|e| Error::output(path, e))
+    }
+
+    /// Distinct characters the model has seen, in order.
+    pub fn alphabet(&self) -> Vec<char> {
+        let chars: BTreeSet<char> = self.ngrams.keys().flat_map(|ngram| ngram.chars()).filter(|&c| c != START && c != END).collect();
+        chars.into_iter().collect()
+    }
+}
+
+/// Levels a generator for candidates of up to `max_len` characters goes
+/// through, from 0 to `max_level` or, if None, the highest level any
+/// candidate can have.
+pub fn level_count(max_len: usize, max_level: Option<u32>) -> usize {
+    // A candidate has a length, its characters and the end: nothing costs more
+    max_level.unwrap_or((max_len as u32 + 2) * MAX_LEVEL) as usize + 1
+}
+
+/// Level of a probability: its negative log2, capped at `MAX_LEVEL`.
+fn level(probability: f64) -> u32 {
+    (-probability.log2()).floor().clamp(0.0, MAX_LEVEL as f64) as u32
+}
+
+/// Next-character levels after one context.
+#[derive(Debug, Clone)]
+struct Context {
+    /// (level, character index), most likely first
+    next: Vec<(u32, u16)>,
+    /// Level of the password ending here
+    end: u32,
+}
+
+/// Enumerates candidates from a `MarkovModel` in descending probability,
+/// OMEN-style: a candidate's level is the sum of the levels of its length
+/// and of every n-gram in it, and level 0 is generated first, then 1, and
+/// so on up to `max_level`. Within a level, lengths go from `min_len` to
+/// `max_len` characters, and candidates must also fit those bounds in
+/// bytes and pass the filter.
+///
+/// Each level is a tier and each length a group, for the generation
+/// pipeline's progress reporting and run controls.
+#[derive(Debug)]
+pub struct MarkovGenerator {
+    /// Index 0 stands for `START` in contexts and `END` as the next character
+    alphabet: Vec<char>,
+    contexts: HashMap<u64, Context>,
+    /// Levels after a context the model never saw
+    unseen: Context,
+    context_len: usize,
+    /// (length in characters, level) for each length generated
+    lengths: Vec<(usize, u32)>,
+    min_len: usize,
+    max_len: usize,
+    max_level: u32,
+    filter: CandidateFilter,
+    /// Current level; None before the first group
+    level: Option<u32>,
+    length: usize,
+    label: String,
+    /// Character indices chosen so far at each depth of the search
+    symbols: Vec<u16>,
+    /// Next entry of the context's `next` list to try at each depth
+    choices: Vec<usize>,
+    /// Level spent before each depth, starting with the length's level
+    spent: Vec<u32>,
+    depth: usize,
+    searching: bool,
+    candidate: String,
+    accepted: usize,
+}
+
+impl MarkovGenerator {
+    /// Generate candidates of `min_len..=max_len` characters up to level
+    /// `max_level`; None runs until every string the model allows is done.
+    pub fn new(model: &MarkovModel, min_len: usize, max_len: usize, max_level: Option<u32>, filter: CandidateFilter) -> Result<Self> {
+        let alphabet: Vec<char> = std::iter::once(START).chain(model.alphabet()).collect();
+        if alphabet.len() > u16::MAX as usize {
+            return Err(Error::config("Markov model has too many distinct characters"));
+        }
+        let index: HashMap<char, u16> = alphabet.iter().enumerate().map(|(i, &c)| (c, i as u16)).collect();
+        let symbol = |c: char| if c == START || c == END { 0 } else { index[&c] };
+        let context_len = model.order - 1;
+
+        // Group n-gram counts by context
+        let mut counts: HashMap<u64, Vec<u64>> = HashMap::new();
+        for (ngram, &count) in &model.ngrams {
+            let chars: Vec<char> = ngram.chars().collect();
+            let key = chars[..context_len].iter().fold(0u64, |key, &c| key << 16 | symbol(c) as u64);
+            counts.entry(key).or_insert_with(|| vec![0; alphabet.len()])[symbol(chars[context_len]) as usize] += count;
+        }
+        let contexts = counts.into_iter().map(|(key, counts)| (key, Context::from_counts(&counts))).collect();
+        let unseen = Context::from_counts(&vec![0; alphabet.len()]);
+
+        let total = model.passwords as f64 + SMOOTHING;
+        let lengths = (min_len.max(1)..=max_len)
+            .map(|length| {
+                let count = model.lengths.get(&length).copied().unwrap_or(0);
+                (length, level((count as f64 + SMOOTHING) / total))
+            })
+            .collect();
+        let max_level = level_count(max_len, max_level) as u32 - 1;
+
+        Ok(MarkovGenerator {
+            alphabet,
+            contexts,
+            unseen,
+            context_len,
+            lengths,
+            min_len,
+            max_len,
+            max_level,
+            filter,
+            level: None,
+            length: 0,
+            label: String::new(),
+            symbols: Vec::new(),
+            choices: Vec::new(),
+            spent: Vec::new(),
+            depth: 0,
+            searching: false,
+            candidate: String::new(),
+            accepted: 0,
+        })
+    }
+
+    /// Candidates accepted so far.
+    pub fn accepted(&self) -> usize {
+        self.accepted
+    }
+
+    /// Next accepted candidate, moving through levels and lengths as needed.
+    pub fn next_candidate(&mut self) -> Option<&str> {
+        loop {
+            if self.search() {
+                return Some(&self.candidate);
+            }
+            self.next_group()?;
+        }
+    }
+
+    fn context(&self, depth: usize) -> &Context {
+        let key = (0..self.context_len).fold(0u64, |key, i| {
+            let symbol = (depth + i).checked_sub(self.context_len).map_or(0, |position| self.symbols[position]);
+            key << 16 | symbol as u64
+        });
+        self.contexts.get(&key).unwrap_or(&self.unseen)
+    }
+
+    /// Depth-first search for the next string of the current length whose
+    /// levels add up to exactly the current level, leaving it in
+    /// `self.candidate` if it passes the filter.
+    fn search(&mut self) -> bool {
+        let (Some(target), true) = (self.level, self.searching) else {
+            return false;
+        };
+        loop {
+            let depth = self.depth;
+            let next = self.context(depth).next.get(self.choices[depth]).copied();
+            match next {
+                // Too cheap: even the least likely rest could not reach the level
+                Some((level, _)) if self.spent[depth] + level + (self.length - depth) as u32 * MAX_LEVEL < target => {
+                    self.choices[depth] += 1;
+                }
+                Some((level, symbol)) if self.spent[depth] + level <= target => {
+                    self.choices[depth] += 1;
+                    self.symbols[depth] = symbol;
+                    self.spent[depth + 1] = self.spent[depth] + level;
+                    if depth + 1 < self.length {
+                        self.depth += 1;
+                        self.choices[self.depth] = 0;
+                    } else if self.spent[self.length] + self.context(self.length).end == target && self.accept() {
+                        return true;
+                    }
+                }
+                // Every remaining character here costs too much; backtrack
+                _ => {
+                    if depth == 0 {
+                        self.searching = false;
+                        return false;
+                    }
+                    self.depth -= 1;
+                }
+            }
+        }
+    }
+
+    fn accept(&mut self) -> bool {
+        self.candidate.clear();
+        self.candidate.extend(self.symbols.iter().map(|&symbol| self.alphabet[symbol as usize]));
+        if (self.min_len..=self.max_len).contains(&self.candidate.len()) && self.filter.accept(&self.candidate) {
+            self.accepted += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Context {
+    fn from_counts(counts: &[u64]) -> Self {
+        let total = counts.iter().sum::<u64>() as f64 + SMOOTHING * counts.len() as f64;
+        let level_of = |count: u64| level((count as f64 + SMOOTHING) / total);
+
+        let mut next: Vec<(u32, u16)> = (1..counts.len()).map(|symbol| (level_of(counts[symbol]), symbol as u16)).collect();
+        // Most frequent first within a level
+        next.sort_by_key(|&(level, symbol)| (level, std::cmp::Reverse(counts[symbol as usize]), symbol));
+        Context { next, end: level_of(counts[0]) }
+    }
+}
+
+impl CandidateSource for MarkovGenerator {
+    fn next_group(&mut self) -> Option<usize> {
+        let (level, index) = match self.level {
+            None => (0, 0),
+            Some(level) => match self.lengths.iter().position(|&(length, _)| length == self.length) {
+                Some(index) if index + 1 < self.lengths.len() => (level, index + 1),
+                _ => (level + 1, 0),
+            },
+        };
+        if level > self.max_level || self.lengths.is_empty() {
+            self.searching = false;
+            return None;
+        }
+
+        let (length, length_level) = self.lengths[index];
+        self.level = Some(level);
+        self.length = length;
+        self.label = format!("{} characters", length);
+        self.symbols = vec![0; length];
+        self.choices = vec![0; length];
+        self.spent = vec![0; length + 1];
+        self.spent[0] = length_level;
+        self.depth = 0;
+        self.searching = length_level <= level;
+        Some(level as usize + 1)
+    }
+
+    fn group(&self) -> Vec<&String> {
+        vec![&self.label]
+    }
+
+    fn next_in_group(&mut self) -> Option<&str> {
+        if self.search() {
+            Some(&self.candidate)
+        } else {
+            None
+        }
+    }
+
+    fn skip_tier(&mut self) {
+        if let Some(&(length, _)) = self.lengths.last() {
+            self.length = length;
+        }
+        self.searching = false;
+    }
+
+    fn candidate_filter(&self) -> &CandidateFilter {
+        &self.filter
+    }
+}
+
+impl Iterator for MarkovGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.next_candidate().map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    fn model(passwords: &[&str]) -> MarkovModel {
+        let mut model = MarkovModel::new(2).unwrap();
+        for password in passwords {
+            assert!(model.add(password));
+        }
+        model
+    }
+
+    #[test]
+    fn test_training_counts_ngrams_with_boundaries() {
+        let model = model(&["abc", "abd"]);
+        assert_eq!(model.passwords, 2);
+        assert_eq!(model.lengths[&3], 2);
+        assert_eq!(model.ngrams[&format!("{}a", START)], 2);
+        assert_eq!(model.ngrams["ab"], 2);
+        assert_eq!(model.ngrams[&format!("c{}", END)], 1);
+        assert_eq!(model.alphabet(), ['a', 'b', 'c', 'd']);
+
+        let mut model = MarkovModel::new(3).unwrap();
+        assert!(!model.add("tab\there"));
+        assert!(!model.add(""));
+        assert!(MarkovModel::new(1).is_err());
+    }
+
+    #[test]
+    fn test_generation_is_most_probable_first() {
+        let passwords = ["abab", "abab", "abab", "abba", "baba"];
+        let generator = MarkovGenerator::new(&model(&passwords), 4, 4, Some(12), CandidateFilter::default()).unwrap();
+        let candidates: Vec<String> = generator.collect();
+
+        assert_eq!(candidates[0], "abab");
+        let position = |candidate: &str| candidates.iter().position(|c| c == candidate).unwrap();
+        assert!(position("abab") < position("baba"));
+        assert!(position("baba") < position("bbbb"));
+        // Every string of the alphabet is reachable, each exactly once
+        let mut unique = candidates.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), candidates.len());
+    }
+
+    #[test]
+    fn test_groups_are_lengths_within_levels() {
+        let mut generator = MarkovGenerator::new(&model(&["aa", "aaa"]), 2, 3, Some(1), CandidateFilter::default()).unwrap();
+        assert_eq!(level_count(3, Some(1)), 2);
+        assert_eq!(level_count(3, None), 51);
+
+        let mut groups = Vec::new();
+        while let Some(tier) = generator.next_group() {
+            groups.push((tier, generator.group()[0].clone()));
+            if tier == 1 {
+                generator.skip_tier();
+            }
+        }
+        assert_eq!(groups, [(1, "2 characters".to_string()), (2, "2 characters".to_string()), (2, "3 characters".to_string())]);
+    }
+
+    #[test]
+    fn test_model_round_trips_and_rejects_bad_files() {
+        let temp = TempPath::new("markov.json");
+        let path = temp.as_str();
+        let trained = model(&["secret", "s3cret"]);
+        trained.save(path).unwrap();
+        let loaded = MarkovModel::load(path).unwrap();
+        assert_eq!(loaded.ngrams, trained.ngrams);
+
+        std::fs::write(path, r#"{"order": 2, "passwords": 1, "lengths": {}, "ngrams": {"abc": 1}}"#).unwrap();
+        assert!(matches!(MarkovModel::load(path), Err(Error::Parse { .. })));
+        std::fs::write(path, "{").unwrap();
+        assert!(matches!(MarkovModel::load(path), Err(Error::Parse { .. })));
+    }
+}
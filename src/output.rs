@@ -0,0 +1,601 @@
+use crate::combinatorics::format_file_size;
+use crate::hashing::{to_hex, HashOutput};
+use crate::error::{Error, Result};
+use crate::sink::CandidateSink;
+use clap::ValueEnum;
+use rayon::prelude::*;
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::Path;
+use xz2::write::XzEncoder;
+
+/// Output path that selects streaming to stdout instead of a file.
+pub const STDOUT_PATH: &str = "-";
+
+pub fn is_stdout(path: &str) -> bool {
+    path == STDOUT_PATH
+}
+
+/// Human-readable name for an output path, used in status lines and summaries.
+pub fn display_name(path: &str) -> &str {
+    if is_stdout(path) {
+        "stdout"
+    } else {
+        path
+    }
+}
+
+/// Compression applied to the candidate stream before it reaches the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    /// Pick a compression format from the output file extension.
+    pub fn from_path(path: &str) -> Self {
+        if path.ends_with(".gz") {
+            Compression::Gzip
+        } else if path.ends_with(".zst") {
+            Compression::Zstd
+        } else if path.ends_with(".xz") {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Xz => "xz",
+        }
+    }
+
+    /// Typical compression ratio for newline-separated wordlists at the
+    /// default level, used only for size estimates.
+    pub fn estimated_ratio(&self) -> f64 {
+        match self {
+            Compression::None => 1.0,
+            Compression::Gzip => 4.0,
+            Compression::Zstd => 6.0,
+            Compression::Xz => 8.0,
+        }
+    }
+
+    /// Estimated on-disk size for `raw_bytes` of uncompressed output.
+    pub fn estimate_size(&self, raw_bytes: u64) -> u64 {
+        (raw_bytes as f64 / self.estimated_ratio()) as u64
+    }
+}
+
+/// Encoder stack beneath the buffered writer. Compressed formats need an
+/// explicit `finish` to write their trailer, so they cannot hide behind
+/// `Box<dyn Write>`.
+enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    Xz(XzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    fn new(inner: W, compression: Compression) -> io::Result<Self> {
+        Ok(match compression {
+            Compression::None => Encoder::Plain(inner),
+            Compression::Gzip => Encoder::Gzip(GzEncoder::new(inner, flate2::Compression::default())),
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(inner, 0)?),
+            Compression::Xz => Encoder::Xz(XzEncoder::new(inner, 6)),
+        })
+    }
+
+    fn finish(self) -> io::Result<W> {
+        let mut inner = match self {
+            Encoder::Plain(inner) => inner,
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Zstd(encoder) => encoder.finish()?,
+            Encoder::Xz(encoder) => encoder.finish()?,
+        };
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(w) => w.write(buf),
+            Encoder::Gzip(w) => w.write(buf),
+            Encoder::Zstd(w) => w.write(buf),
+            Encoder::Xz(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(w) => w.flush(),
+            Encoder::Gzip(w) => w.flush(),
+            Encoder::Zstd(w) => w.flush(),
+            Encoder::Xz(w) => w.flush(),
+        }
+    }
+}
+
+/// Bottom of the writer stack. Counts the bytes that actually reach the
+/// destination and optionally checksums them for the split manifest.
+struct Sink {
+    inner: Box<dyn Write>,
+    hasher: Option<Sha256>,
+    bytes: u64,
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..written]);
+        }
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Upper bound for a single output part when splitting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitLimit {
+    /// Uncompressed candidate bytes, newlines included
+    Bytes(u64),
+    Lines(u64),
+}
+
+/// Line count and checksum of a finished output part, as listed in the manifest.
+#[derive(Debug, Clone)]
+pub struct PartSummary {
+    pub path: String,
+    pub lines: u64,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// Path of the `index`th part: `passwords.txt` becomes `passwords.000.txt`
+/// and `passwords.txt.gz` becomes `passwords.000.txt.gz`.
+pub fn part_path(path: &str, index: usize) -> String {
+    let name_start = path.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match path[name_start..].find('.') {
+        Some(dot) if dot > 0 => {
+            let (stem, ext) = path.split_at(name_start + dot);
+            format!("{}.{:03}{}", stem, index, ext)
+        }
+        _ => format!("{}.{:03}", path, index),
+    }
+}
+
+/// Path of the manifest written next to split output parts.
+pub fn manifest_path(path: &str) -> String {
+    format!("{}.manifest", path)
+}
+
+/// One file (or stdout) being written.
+struct Part {
+    writer: BufWriter<Encoder<Sink>>,
+    temp_path: Option<String>,
+    final_path: Option<String>,
+    lines: u64,
+    raw_bytes: u64,
+}
+
+impl Part {
+    fn open(path: &str, append: bool, compression: Compression, checksum: bool) -> io::Result<Self> {
+        let (inner, temp_path, final_path): (Box<dyn Write>, _, _) = if is_stdout(path) {
+            (Box::new(io::stdout().lock()), None, None)
+        } else if append {
+            // Appending a new gzip/zstd/xz frame still yields a valid multi-frame stream
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            (Box::new(file), None, None)
+        } else {
+            let temp_path = format!("{}.tmp.{}", path, std::process::id());
+            let file = File::create(&temp_path)?;
+            (Box::new(file), Some(temp_path), Some(path.to_string()))
+        };
+
+        let sink = Sink {
+            inner,
+            hasher: if checksum { Some(Sha256::new()) } else { None },
+            bytes: 0,
+        };
+
+        Ok(Part {
+            writer: BufWriter::new(Encoder::new(sink, compression)?),
+            temp_path,
+            final_path,
+            lines: 0,
+            raw_bytes: 0,
+        })
+    }
+
+    fn finish(self) -> io::Result<PartSummary> {
+        let encoder = self.writer.into_inner().map_err(|e| e.into_error())?;
+        let sink = encoder.finish()?;
+
+        if let (Some(temp_path), Some(final_path)) = (&self.temp_path, &self.final_path) {
+            std::fs::rename(temp_path, final_path)?;
+        }
+
+        let sha256 = sink
+            .hasher
+            .map(|hasher| to_hex(&hasher.finalize()))
+            .unwrap_or_default();
+
+        Ok(PartSummary {
+            path: self.final_path.unwrap_or_default(),
+            lines: self.lines,
+            bytes: sink.bytes,
+            sha256,
+        })
+    }
+}
+
+/// Destination for generated candidates.
+///
+/// Overwriting a file goes through a temporary file that is renamed into
+/// place by `finish`, so an interrupted run never leaves a truncated list
+/// behind, and a writer dropped without `finish` (a failed run) removes
+/// its temporary file. Appending writes to the file directly, and stdout
+/// is a locked, buffered handle so candidates can be piped straight into
+/// a cracker. Compression sits between the buffer and the destination.
+///
+/// With a split limit, output rolls over into numbered part files that are
+/// each finalized as soon as they are full, and a manifest with per-part
+/// line counts and SHA-256 checksums is written at the end.
+pub struct OutputWriter {
+    /// The part being written; None once the writer is finished
+    current: Option<Part>,
+    path: String,
+    compression: Compression,
+    split: Option<SplitLimit>,
+    parts: Vec<PartSummary>,
+    hash: Option<HashOutput>,
+    bytes_written: u64,
+}
+
+impl OutputWriter {
+    pub fn open(
+        path: &str,
+        append: bool,
+        compression: Compression,
+        split: Option<SplitLimit>,
+    ) -> io::Result<Self> {
+        let current = match split {
+            Some(_) => Part::open(&part_path(path, 0), false, compression, true)?,
+            None => Part::open(path, append, compression, false)?,
+        };
+
+        Ok(OutputWriter {
+            current: Some(current),
+            path: path.to_string(),
+            compression,
+            split,
+            parts: Vec::new(),
+            hash: None,
+            bytes_written: 0,
+        })
+    }
+
+    /// Write each candidate as its hash (or `hash:plaintext`) instead of plaintext.
+    pub fn with_hash(mut self, hash: Option<HashOutput>) -> Self {
+        self.hash = hash;
+        self
+    }
+
+    /// Write a chunk of candidates. When hashing, the digests are computed
+    /// in parallel before the lines are written in order.
+    pub fn write_chunk(&mut self, candidates: &[String]) -> io::Result<()> {
+        match self.hash {
+            Some(hash) => {
+                let lines: Vec<String> = candidates.par_iter().map(|c| hash.format(c)).collect();
+                for line in &lines {
+                    self.write_line(line)?;
+                }
+            }
+            None => {
+                for candidate in candidates {
+                    self.write_line(candidate)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write one candidate, hashing it first if hash output is enabled.
+    pub fn write_candidate(&mut self, candidate: &str) -> io::Result<()> {
+        match self.hash {
+            Some(hash) => self.write_line(&hash.format(candidate)),
+            None => self.write_line(candidate),
+        }
+    }
+
+    /// Write one line followed by a newline, rolling over to the next part
+    /// first if it would push the current one past the split limit.
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let line_len = line.len() as u64 + 1;
+        let current = self.current.as_ref().ok_or_else(finished_error)?;
+
+        if let Some(limit) = self.split {
+            let full = match limit {
+                SplitLimit::Bytes(max) => current.raw_bytes + line_len > max,
+                SplitLimit::Lines(max) => current.lines >= max,
+            };
+            if full && current.lines > 0 {
+                self.roll_over()?;
+            }
+        }
+
+        let current = self.current.as_mut().ok_or_else(finished_error)?;
+        current.writer.write_all(line.as_bytes())?;
+        current.writer.write_all(b"\n")?;
+        current.lines += 1;
+        current.raw_bytes += line_len;
+        self.bytes_written += line_len;
+        Ok(())
+    }
+
+    fn roll_over(&mut self) -> io::Result<()> {
+        let next_path = part_path(&self.path, self.parts.len() + 1);
+        let next = Part::open(&next_path, false, self.compression, true)?;
+        if let Some(finished) = self.current.replace(next) {
+            self.parts.push(finished.finish()?);
+        }
+        Ok(())
+    }
+
+    /// An output error naming the file being written.
+    pub fn error(&self, source: io::Error) -> Error {
+        Error::output(display_name(&self.path), source)
+    }
+
+    /// Uncompressed bytes written so far, across all parts.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Flush buffered candidates, finalize compression and move the
+    /// temporary file into place. Returns the finished parts when splitting.
+    /// Later writes, and a second finish, fail.
+    pub fn finish(&mut self) -> io::Result<Vec<PartSummary>> {
+        let last = self.current.take().ok_or_else(finished_error)?.finish()?;
+
+        if self.split.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.parts.push(last);
+        write_manifest(&manifest_path(&self.path), &self.parts)?;
+        Ok(std::mem::take(&mut self.parts))
+    }
+}
+
+impl Drop for OutputWriter {
+    fn drop(&mut self) {
+        if let Some(part) = self.current.take() {
+            let temp_path = part.temp_path.clone();
+            drop(part);
+            if let Some(temp_path) = temp_path {
+                let _ = std::fs::remove_file(temp_path);
+            }
+        }
+    }
+}
+
+impl CandidateSink for OutputWriter {
+    fn write_chunk(&mut self, candidates: &[String]) -> Result<()> {
+        OutputWriter::write_chunk(self, candidates).map_err(|e| self.error(e))
+    }
+
+    fn write_candidate(&mut self, candidate: &str) -> Result<()> {
+        OutputWriter::write_candidate(self, candidate).map_err(|e| self.error(e))
+    }
+
+    fn describe(&self) -> (&'static str, String) {
+        ("Password Generator", display_name(&self.path).to_string())
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        Some(self.bytes_written)
+    }
+
+    fn writes_to_terminal(&self) -> bool {
+        is_stdout(&self.path) && io::stdout().is_terminal()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        OutputWriter::finish(self).map(|_| ()).map_err(|e| self.error(e))
+    }
+}
+
+fn finished_error() -> io::Error {
+    io::Error::other("output is already finished")
+}
+
+fn write_manifest(path: &str, parts: &[PartSummary]) -> io::Result<()> {
+    let temp_path = format!("{}.tmp.{}", path, std::process::id());
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+
+    writeln!(writer, "# part\tlines\tbytes\tsha256")?;
+    for part in parts {
+        // Parts live next to the manifest, so list them by file name only
+        let name = Path::new(&part.path)
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        writeln!(writer, "{}\t{}\t{}\t{}", name, part.lines, part.bytes, part.sha256)?;
+    }
+
+    writer.flush()?;
+    drop(writer);
+    std::fs::rename(&temp_path, path)
+}
+
+/// Free-space preflight for file output. Returns a description of the
+/// shortfall when `raw_bytes` of output, after the estimated compression,
+/// will not fit on the target filesystem; stdout is never checked. Without
+/// `--append` the new file is written beside the old one and only renamed
+/// over it at the end, so an existing file's space does not count as free.
+pub fn check_disk_space(path: &str, append: bool, compression: Compression, raw_bytes: u64) -> io::Result<Option<String>> {
+    if is_stdout(path) {
+        return Ok(None);
+    }
+
+    let dir = match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let available = fs2::available_space(dir)?;
+    let needed = compression.estimate_size(raw_bytes);
+    if needed <= available {
+        return Ok(None);
+    }
+
+    let mut problem = format!(
+        "Not enough disk space for {}: about {} needed, {} free",
+        path,
+        format_file_size(needed),
+        format_file_size(available)
+    );
+    if !append {
+        if let Ok(existing) = std::fs::metadata(path) {
+            problem.push_str(&format!(
+                " (the existing {} file is only replaced once generation finishes)",
+                format_file_size(existing.len())
+            ));
+        }
+    }
+    Ok(Some(problem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn test_file_output_is_renamed_into_place() {
+        let temp = TempPath::new("output.txt");
+        let path = temp.as_str().to_string();
+
+        let mut writer = OutputWriter::open(&path, false, Compression::None, None).unwrap();
+        writer.write_candidate("admin").unwrap();
+        writer.write_candidate("4dmin").unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "admin\n4dmin\n");
+        assert!(!std::path::Path::new(&format!("{}.tmp.{}", path, std::process::id())).exists());
+    }
+
+    #[test]
+    fn test_unfinished_output_leaves_no_temp_file() {
+        let temp = TempPath::new("unfinished.txt");
+        let path = temp.as_str().to_string();
+
+        let mut writer = OutputWriter::open(&path, false, Compression::None, None).unwrap();
+        writer.write_candidate("admin").unwrap();
+        drop(writer);
+
+        assert!(!std::path::Path::new(&format!("{}.tmp.{}", path, std::process::id())).exists());
+        assert!(!std::path::Path::new(&path).exists());
+    }
+
+    #[test]
+    fn test_gzip_output_round_trips() {
+        use std::io::Read;
+
+        let temp = TempPath::new("output.txt.gz");
+        let path = temp.as_str().to_string();
+
+        let mut writer = OutputWriter::open(&path, false, Compression::from_path(&path), None).unwrap();
+        writer.write_candidate("p4ssw0rd!").unwrap();
+        writer.finish().unwrap();
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(File::open(&path).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "p4ssw0rd!\n");
+    }
+
+    #[test]
+    fn test_split_by_lines_writes_parts_and_manifest() {
+        let dir = TempPath::dir("split");
+        let path = dir.join("passwords.txt");
+
+        let mut writer = OutputWriter::open(&path, false, Compression::None, Some(SplitLimit::Lines(2))).unwrap();
+        for candidate in ["admin", "4dmin", "Admin", "ADMIN", "4DMIN"] {
+            writer.write_candidate(candidate).unwrap();
+        }
+        let parts = writer.finish().unwrap();
+
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts.iter().map(|p| p.lines).collect::<Vec<_>>(), vec![2, 2, 1]);
+        assert_eq!(std::fs::read_to_string(part_path(&path, 1)).unwrap(), "Admin\nADMIN\n");
+        // SHA-256 of "4DMIN\n"
+        assert_eq!(parts[2].sha256, "0283b34014272f9dbc27be183dde3c79e9da3015a5f2b7359f7c4d3478381a94");
+
+        let manifest = std::fs::read_to_string(manifest_path(&path)).unwrap();
+        assert_eq!(manifest.lines().count(), 4);
+        assert!(manifest.contains(&parts[0].sha256));
+    }
+
+    #[test]
+    fn test_part_path() {
+        assert_eq!(part_path("passwords.txt", 0), "passwords.000.txt");
+        assert_eq!(part_path("out/passwords.txt.gz", 12), "out/passwords.012.txt.gz");
+        assert_eq!(part_path("wordlist", 1), "wordlist.001");
+    }
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(Compression::from_path("out.txt"), Compression::None);
+        assert_eq!(Compression::from_path("out.txt.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("out.txt.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("out.txt.xz"), Compression::Xz);
+        assert_eq!(Compression::None.estimate_size(1000), 1000);
+        assert!(Compression::Zstd.estimate_size(1000) < 1000);
+    }
+
+    #[test]
+    fn test_stdout_path() {
+        assert!(is_stdout("-"));
+        assert!(!is_stdout("passwords.txt"));
+        assert_eq!(display_name("-"), "stdout");
+        assert_eq!(display_name("out.txt"), "out.txt");
+    }
+
+    #[test]
+    fn test_disk_space_check() {
+        let temp = TempPath::new("space.txt");
+        let path = temp.as_str();
+
+        assert_eq!(check_disk_space(path, false, Compression::None, 1024).unwrap(), None);
+        assert_eq!(check_disk_space("-", false, Compression::None, u64::MAX).unwrap(), None);
+
+        let problem = check_disk_space(path, false, Compression::Gzip, u64::MAX).unwrap().unwrap();
+        assert!(problem.starts_with("Not enough disk space"));
+        assert!(!problem.contains("existing"));
+
+        std::fs::write(path, "admin\n").unwrap();
+        let problem = check_disk_space(path, false, Compression::None, u64::MAX).unwrap().unwrap();
+        assert!(problem.contains("only replaced once generation finishes"));
+        let problem = check_disk_space(path, true, Compression::None, u64::MAX).unwrap().unwrap();
+        assert!(!problem.contains("existing"));
+    }
+}
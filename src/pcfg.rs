@@ -0,0 +1,563 @@
+use crate::candidates::CandidateSource;
+use crate::error::{Error, Result};
+use crate::filter::CandidateFilter;
+use crate::transform::TransformChain;
+use crate::words::read_passwords;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// What a run of characters in a base structure is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SegmentKind {
+    /// Letters (`L`), filled from the input words
+    Letters,
+    /// ASCII digits (`D`), filled from learned digit runs
+    Digits,
+    /// Everything else (`S`), filled from learned symbol runs
+    Symbols,
+}
+
+impl SegmentKind {
+    fn of(c: char) -> Self {
+        if c.is_alphabetic() {
+            SegmentKind::Letters
+        } else if c.is_ascii_digit() {
+            SegmentKind::Digits
+        } else {
+            SegmentKind::Symbols
+        }
+    }
+
+    fn tag(self) -> char {
+        match self {
+            SegmentKind::Letters => 'L',
+            SegmentKind::Digits => 'D',
+            SegmentKind::Symbols => 'S',
+        }
+    }
+}
+
+/// One run of a base structure: `D2` is two digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    /// Length in characters
+    pub len: usize,
+}
+
+/// Split a password into runs of letters, digits and symbols, with the
+/// text of each: `pass12!` is `L4` "pass", `D2` "12", `S1` "!".
+pub fn segments(password: &str) -> Vec<(Segment, &str)> {
+    let mut segments: Vec<(Segment, &str)> = Vec::new();
+    let mut start = 0;
+    for (i, c) in password.char_indices() {
+        let kind = SegmentKind::of(c);
+        match segments.last_mut() {
+            Some((segment, text)) if segment.kind == kind => {
+                segment.len += 1;
+                *text = &password[start..i + c.len_utf8()];
+            }
+            _ => {
+                start = i;
+                segments.push((Segment { kind, len: 1 }, &password[i..i + c.len_utf8()]));
+            }
+        }
+    }
+    segments
+}
+
+/// Name of a base structure, e.g. `L6D2S1`.
+pub fn structure_name(segments: &[Segment]) -> String {
+    segments.iter().map(|segment| format!("{}{}", segment.kind.tag(), segment.len)).collect()
+}
+
+/// Parse a base structure name such as `L6D2S1`.
+pub fn parse_structure(name: &str) -> Option<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut chars = name.chars().peekable();
+    while let Some(tag) = chars.next() {
+        let kind = match tag {
+            'L' => SegmentKind::Letters,
+            'D' => SegmentKind::Digits,
+            'S' => SegmentKind::Symbols,
+            _ => return None,
+        };
+        let mut len = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            len.push(digit);
+        }
+        segments.push(Segment { kind, len: len.parse().ok().filter(|&len| len > 0)? });
+    }
+    (!segments.is_empty()).then_some(segments)
+}
+
+/// Base structures and digit and symbol runs learned from a password list,
+/// Weir-style. Letter runs are not learned: `PcfgGenerator` fills them from
+/// the input words. `train --kind pcfg` saves it as JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PcfgModel {
+    /// Passwords the model was trained on
+    pub passwords: u64,
+    /// Lines skipped for being empty, not UTF-8 or holding control characters
+    #[serde(default)]
+    pub skipped: u64,
+    /// Passwords per base structure, e.g. `L6D2S1`
+    pub structures: BTreeMap<String, u64>,
+    /// Count of each digit run, by length
+    pub digits: BTreeMap<usize, BTreeMap<String, u64>>,
+    /// Count of each symbol run, by length
+    pub symbols: BTreeMap<usize, BTreeMap<String, u64>>,
+}
+
+impl PcfgModel {
+    /// Train on a plaintext password list, one password per line.
+    pub fn train(path: &str) -> Result<Self> {
+        let mut model = PcfgModel::default();
+        model.skipped = read_passwords(path, |password| model.add(password))?;
+        Ok(model)
+    }
+
+    /// Count one password; false (and nothing counted) if it is empty or
+    /// holds control characters.
+    pub fn add(&mut self, password: &str) -> bool {
+        if password.is_empty() || password.chars().any(char::is_control) {
+            return false;
+        }
+        let segments = segments(password);
+        for (segment, text) in &segments {
+            let runs = match segment.kind {
+                SegmentKind::Letters => continue,
+                SegmentKind::Digits => &mut self.digits,
+                SegmentKind::Symbols => &mut self.symbols,
+            };
+            *runs.entry(segment.len).or_default().entry(text.to_string()).or_default() += 1;
+        }
+        let structure: Vec<Segment> = segments.iter().map(|(segment, _)| *segment).collect();
+        *self.structures.entry(structure_name(&structure)).or_default() += 1;
+        self.passwords += 1;
+        true
+    }
+
+    /// Load a model saved by `save`.
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::input(path, e))?;
+        let model: PcfgModel =
+            serde_json::from_str(&content).map_err(|e| Error::parse(path, Some(e.line()), e.to_string()))?;
+
+        if let Some(name) = model.structures.keys().find(|name| parse_structure(name).is_none()) {
+            return Err(Error::parse(path, None, format!("invalid base structure {:?}", name)));
+        }
+        for (len, runs) in model.digits.iter().chain(&model.symbols) {
+            if let Some(run) = runs.keys().find(|run| run.chars().count() != *len) {
+                return Err(Error::parse(path, None, format!("run {:?} is not {} characters long", run, len)));
+            }
+        }
+        Ok(model)
+    }
+
+    /// Save the model as JSON.
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut out = BufWriter::new(File::create(path).map_err(|e| Error::output(path, e))?);
+        serde_json::to_writer(&mut out, self).map_err(|e| Error::output(path, e.into()))?;
+        out.flush().map_err(|e| Error::output(path, e))
+    }
+}
+
+/// A base structure ready to expand.
+#[derive(Debug)]
+struct Structure {
+    segments: Vec<Segment>,
+    probability: f64,
+}
+
+/// A base structure with a digit or symbol run chosen for each of those
+/// segments; its letter segments expand to every word variant of their
+/// length. `terminals[i]` indexes the run list of segment `i` (always 0
+/// for letters), and children only advance segments from `pivot` on, so
+/// each pre-terminal is queued once.
+#[derive(Debug, Clone)]
+struct PreTerminal {
+    /// Probability of each candidate it expands to
+    probability: f64,
+    structure: usize,
+    terminals: Vec<usize>,
+    pivot: usize,
+}
+
+impl PartialEq for PreTerminal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PreTerminal {}
+
+impl PartialOrd for PreTerminal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Most probable first; ties go to the more common structure, then the
+/// more common runs.
+impl Ord for PreTerminal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.probability
+            .total_cmp(&other.probability)
+            .then_with(|| other.structure.cmp(&self.structure))
+            .then_with(|| other.terminals.cmp(&self.terminals))
+    }
+}
+
+/// Enumerates candidates from a `PcfgModel` and a word list in descending
+/// probability. Pre-terminals (a structure with its digit and symbol runs
+/// chosen) come off a priority queue most probable first; each expands to
+/// every combination of word variants of the right lengths for its letter
+/// segments. A word of length n has probability 1 / (words of length n),
+/// whatever its variants.
+///
+/// Each pre-terminal is a group, and its level (the negative log2 of its
+/// candidates' probability) is its tier, for the generation pipeline's
+/// progress reporting and run controls.
+#[derive(Debug)]
+pub struct PcfgGenerator {
+    structures: Vec<Structure>,
+    /// Runs of each digit or symbol segment, most probable first
+    runs: HashMap<Segment, Vec<(f64, String)>>,
+    /// Every variant of every word, by length in characters
+    letters: HashMap<usize, Vec<String>>,
+    /// Distinct words by length in characters
+    word_counts: HashMap<usize, usize>,
+    min_len: usize,
+    max_len: usize,
+    filter: CandidateFilter,
+    queue: BinaryHeap<PreTerminal>,
+    current: Option<PreTerminal>,
+    /// Variant index per letter segment of the current pre-terminal; None before the first
+    odometer: Option<Vec<usize>>,
+    /// Tier whose remaining pre-terminals are being skipped
+    skipping: Option<usize>,
+    label: String,
+    candidate: String,
+    accepted: usize,
+}
+
+impl PcfgGenerator {
+    /// Generate from `model`'s structures, filling letter segments with the
+    /// word transforms' variants of `words` (candidate transforms such as
+    /// padding are not used). Structures longer than `max_len` characters,
+    /// or with a letter segment no word fits, are left out.
+    pub fn new(model: &PcfgModel, words: &[String], transforms: &TransformChain, min_len: usize, max_len: usize, filter: CandidateFilter) -> Self {
+        let mut letters: HashMap<usize, Vec<String>> = HashMap::new();
+        let mut word_counts: HashMap<usize, usize> = HashMap::new();
+        let mut seen = std::collections::HashSet::new();
+        for word in words.iter().filter(|word| seen.insert(word.as_str())) {
+            let len = word.chars().count();
+            *word_counts.entry(len).or_default() += 1;
+            // Leet and case keep the length; anything else cannot fill the segment
+            let variants = transforms.word_variants(word).into_iter().filter(|variant| variant.chars().count() == len);
+            letters.entry(len).or_default().extend(variants);
+        }
+
+        let mut runs = HashMap::new();
+        for (kind, by_len) in [(SegmentKind::Digits, &model.digits), (SegmentKind::Symbols, &model.symbols)] {
+            for (&len, counts) in by_len {
+                let total = counts.values().sum::<u64>() as f64;
+                let mut list: Vec<(f64, String)> = counts.iter().map(|(run, &count)| (count as f64 / total, run.clone())).collect();
+                list.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+                runs.insert(Segment { kind, len }, list);
+            }
+        }
+
+        let total = model.passwords.max(1) as f64;
+        let mut structures: Vec<Structure> = model
+            .structures
+            .iter()
+            .filter_map(|(name, &count)| {
+                let segments = parse_structure(name)?;
+                let fillable = segments.iter().all(|segment| match segment.kind {
+                    SegmentKind::Letters => letters.get(&segment.len).is_some_and(|variants| !variants.is_empty()),
+                    _ => runs.get(segment).is_some_and(|list: &Vec<(f64, String)>| !list.is_empty()),
+                });
+                let len: usize = segments.iter().map(|segment| segment.len).sum();
+                (fillable && len <= max_len).then_some(Structure { segments, probability: count as f64 / total })
+            })
+            .collect();
+        structures.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+
+        let mut generator = PcfgGenerator {
+            structures,
+            runs,
+            letters,
+            word_counts,
+            min_len,
+            max_len,
+            filter,
+            queue: BinaryHeap::new(),
+            current: None,
+            odometer: None,
+            skipping: None,
+            label: String::new(),
+            candidate: String::new(),
+            accepted: 0,
+        };
+        for structure in 0..generator.structures.len() {
+            let terminals = vec![0; generator.structures[structure].segments.len()];
+            let root = generator.pre_terminal(structure, terminals, 0);
+            generator.queue.push(root);
+        }
+        generator
+    }
+
+    /// Structures that can be filled from the words.
+    pub fn structures(&self) -> usize {
+        self.structures.len()
+    }
+
+    /// Candidates the whole grammar expands to, before length bounds and
+    /// filters; saturates at `u64::MAX`.
+    pub fn keyspace(&self) -> u64 {
+        self.structures.iter().fold(0u64, |sum, structure| {
+            let count = structure.segments.iter().fold(1u64, |product, segment| {
+                product.saturating_mul(self.choices(segment) as u64)
+            });
+            sum.saturating_add(count)
+        })
+    }
+
+    /// Candidates accepted so far.
+    pub fn accepted(&self) -> usize {
+        self.accepted
+    }
+
+    /// Next accepted candidate, moving through pre-terminals as needed.
+    pub fn next_candidate(&mut self) -> Option<&str> {
+        loop {
+            if self.advance() {
+                return Some(&self.candidate);
+            }
+            self.next_group()?;
+        }
+    }
+
+    fn choices(&self, segment: &Segment) -> usize {
+        match segment.kind {
+            SegmentKind::Letters => self.letters.get(&segment.len).map_or(0, Vec::len),
+            _ => self.runs.get(segment).map_or(0, Vec::len),
+        }
+    }
+
+    fn pre_terminal(&self, structure: usize, terminals: Vec<usize>, pivot: usize) -> PreTerminal {
+        let mut probability = self.structures[structure].probability;
+        for (segment, &terminal) in self.structures[structure].segments.iter().zip(&terminals) {
+            probability *= match segment.kind {
+                SegmentKind::Letters => 1.0 / self.word_counts[&segment.len] as f64,
+                _ => self.runs[segment][terminal].0,
+            };
+        }
+        PreTerminal { probability, structure, terminals, pivot }
+    }
+
+    fn tier(pre_terminal: &PreTerminal) -> usize {
+        (-pre_terminal.probability.log2()).floor().max(0.0) as usize + 1
+    }
+
+    /// Step the odometer over the letter segments of the current
+    /// pre-terminal to the next accepted candidate.
+    fn advance(&mut self) -> bool {
+        let Some(current) = &self.current else {
+            return false;
+        };
+        let segments = &self.structures[current.structure].segments;
+        loop {
+            let odometer = match &mut self.odometer {
+                None => self.odometer.insert(vec![0; segments.len()]),
+                Some(odometer) => {
+                    // Last letter segment varies fastest
+                    let mut i = segments.len();
+                    loop {
+                        if i == 0 {
+                            self.current = None;
+                            return false;
+                        }
+                        i -= 1;
+                        if segments[i].kind != SegmentKind::Letters {
+                            continue;
+                        }
+                        odometer[i] += 1;
+                        if odometer[i] < self.letters[&segments[i].len].len() {
+                            break;
+                        }
+                        odometer[i] = 0;
+                    }
+                    odometer
+                }
+            };
+
+            self.candidate.clear();
+            for (i, segment) in segments.iter().enumerate() {
+                self.candidate.push_str(match segment.kind {
+                    SegmentKind::Letters => &self.letters[&segment.len][odometer[i]],
+                    _ => &self.runs[segment][current.terminals[i]].1,
+                });
+            }
+            if (self.min_len..=self.max_len).contains(&self.candidate.len()) && self.filter.accept(&self.candidate) {
+                self.accepted += 1;
+                return true;
+            }
+        }
+    }
+}
+
+impl CandidateSource for PcfgGenerator {
+    fn next_group(&mut self) -> Option<usize> {
+        loop {
+            let pre_terminal = self.queue.pop()?;
+            let segments = &self.structures[pre_terminal.structure].segments;
+            let children: Vec<PreTerminal> = (pre_terminal.pivot..segments.len())
+                .filter(|&i| segments[i].kind != SegmentKind::Letters && pre_terminal.terminals[i] + 1 < self.choices(&segments[i]))
+                .map(|i| {
+                    let mut terminals = pre_terminal.terminals.clone();
+                    terminals[i] += 1;
+                    self.pre_terminal(pre_terminal.structure, terminals, i)
+                })
+                .collect();
+            self.queue.extend(children);
+
+            let tier = Self::tier(&pre_terminal);
+            if self.skipping.is_some_and(|skipped| tier <= skipped) {
+                continue;
+            }
+            self.skipping = None;
+
+            let segments = &self.structures[pre_terminal.structure].segments;
+            self.label = segments
+                .iter()
+                .zip(&pre_terminal.terminals)
+                .map(|(segment, &terminal)| match segment.kind {
+                    SegmentKind::Letters => format!("L{}", segment.len),
+                    _ => self.runs[segment][terminal].1.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(" + ");
+            self.current = Some(pre_terminal);
+            self.odometer = None;
+            return Some(tier);
+        }
+    }
+
+    fn group(&self) -> Vec<&String> {
+        vec![&self.label]
+    }
+
+    fn next_in_group(&mut self) -> Option<&str> {
+        if self.advance() {
+            Some(&self.candidate)
+        } else {
+            None
+        }
+    }
+
+    fn skip_tier(&mut self) {
+        self.skipping = self.current.take().map(|current| Self::tier(&current));
+    }
+
+    fn candidate_filter(&self) -> &CandidateFilter {
+        &self.filter
+    }
+}
+
+impl Iterator for PcfgGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.next_candidate().map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    fn model(passwords: &[&str]) -> PcfgModel {
+        let mut model = PcfgModel::default();
+        for password in passwords {
+            assert!(model.add(password));
+        }
+        model
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn test_structures_and_runs() {
+        let parts: Vec<(Segment, &str)> = segments("pass12!!é");
+        let names: Vec<&str> = parts.iter().map(|(_, text)| *text).collect();
+        assert_eq!(names, ["pass", "12", "!!", "é"]);
+        let structure: Vec<Segment> = parts.iter().map(|(segment, _)| *segment).collect();
+        assert_eq!(structure_name(&structure), "L4D2S2L1");
+        assert_eq!(parse_structure("L4D2S2L1"), Some(structure));
+        assert_eq!(parse_structure("L4X2"), None);
+        assert_eq!(parse_structure("L0"), None);
+        assert_eq!(parse_structure(""), None);
+
+        let model = model(&["pass12!", "word12!", "dog99", "cat1"]);
+        assert_eq!(model.structures["L4D2S1"], 2);
+        assert_eq!(model.digits[&2]["12"], 2);
+        assert_eq!(model.symbols[&1]["!"], 2);
+    }
+
+    #[test]
+    fn test_candidates_come_in_probability_order() {
+        let model = model(&["abc12", "abc12", "abc12", "abc99", "xyz1", "abcd"]);
+        let transforms = TransformChain::parse("case").unwrap();
+        let generator = PcfgGenerator::new(&model, &words(&["dog", "cat", "bird"]), &transforms, 1, 20, CandidateFilter::default());
+        assert_eq!(generator.structures(), 3);
+        // L3D2: 2 words x 3 case forms x 2 runs; L3D1: 6 x 1; L4: 3
+        assert_eq!(generator.keyspace(), 12 + 6 + 3);
+
+        let candidates: Vec<String> = generator.collect();
+        assert_eq!(candidates.len(), 21);
+        assert_eq!(&candidates[..3], ["DOG12", "Dog12", "dog12"]);
+        let position = |candidate: &str| candidates.iter().position(|c| c == candidate).unwrap();
+        // dog12 1/4, bird 1/6, dog99 1/12
+        assert!(position("dog12") < position("bird"));
+        assert!(position("bird") < position("dog99"));
+    }
+
+    #[test]
+    fn test_skip_tier_drops_the_rest_of_the_level() {
+        let model = model(&["ab1", "ab1", "ab2", "ab3", "ab4"]);
+        let mut generator = PcfgGenerator::new(&model, &words(&["xy"]), &TransformChain::parse("").unwrap(), 1, 20, CandidateFilter::default());
+
+        let mut groups = Vec::new();
+        while let Some(tier) = generator.next_group() {
+            groups.push((tier, generator.group()[0].clone()));
+            if groups.len() == 2 {
+                generator.skip_tier();
+            }
+        }
+        // 1 at 2/5, then 2, 3 and 4 at 1/5 each: one level apart
+        assert_eq!(groups, [(2, "L2 + 1".to_string()), (3, "L2 + 2".to_string())]);
+    }
+
+    #[test]
+    fn test_model_round_trips_and_rejects_bad_files() {
+        let temp = TempPath::new("pcfg.json");
+        let path = temp.as_str();
+        let trained = model(&["secret1!", "s3cret"]);
+        trained.save(path).unwrap();
+        assert_eq!(PcfgModel::load(path).unwrap().structures, trained.structures);
+
+        std::fs::write(path, r#"{"passwords": 1, "structures": {"Q3": 1}, "digits": {}, "symbols": {}}"#).unwrap();
+        assert!(matches!(PcfgModel::load(path), Err(Error::Parse { .. })));
+        std::fs::write(path, r#"{"passwords": 1, "structures": {}, "digits": {"2": {"123": 1}}, "symbols": {}}"#).unwrap();
+        assert!(matches!(PcfgModel::load(path), Err(Error::Parse { .. })));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::error::{Error, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// Built-in password policies modelled on common target systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PolicyPreset {
+    /// Active Directory complexity: 7+ chars, 3 of 4 character classes
+    Ad,
+    /// PCI DSS v4: 12+ chars with both letters and digits
+    PciDss,
+    /// NIST SP 800-63B: 8+ chars, no composition rules, no long runs of one character
+    Nist,
+}
+
+/// Character-class and content requirements a candidate must meet.
+///
+/// Every field is optional so that a policy file or CLI flags only need to
+/// mention the rules they care about.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasswordPolicy {
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub min_upper: usize,
+    pub min_lower: usize,
+    /// Letters of either case
+    pub min_letters: usize,
+    pub min_digits: usize,
+    pub min_symbols: usize,
+    /// Number of distinct classes (upper, lower, digit, symbol) required
+    pub min_classes: usize,
+    /// Longest allowed run of one repeated character
+    pub max_repeat: Option<usize>,
+    /// Substrings that must not appear, compared case-insensitively (e.g. the username)
+    pub forbidden: Vec<String>,
+}
+
+/// Per-class character counts of a candidate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClassCounts {
+    pub upper: usize,
+    pub lower: usize,
+    pub digits: usize,
+    pub symbols: usize,
+}
+
+impl ClassCounts {
+    pub fn of(candidate: &str) -> Self {
+        let mut counts = ClassCounts::default();
+        for ch in candidate.chars() {
+            if ch.is_uppercase() {
+                counts.upper += 1;
+            } else if ch.is_lowercase() {
+                counts.lower += 1;
+            } else if ch.is_ascii_digit() {
+                counts.digits += 1;
+            } else {
+                counts.symbols += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn classes(&self) -> usize {
+        [self.upper, self.lower, self.digits, self.symbols]
+            .iter()
+            .filter(|&&n| n > 0)
+            .count()
+    }
+}
+
+impl PasswordPolicy {
+    pub fn preset(preset: PolicyPreset) -> Self {
+        match preset {
+            PolicyPreset::Ad => PasswordPolicy {
+                min_length: Some(7),
+                min_classes: 3,
+                ..Default::default()
+            },
+            PolicyPreset::PciDss => PasswordPolicy {
+                min_length: Some(12),
+                min_letters: 1,
+                min_digits: 1,
+                ..Default::default()
+            },
+            PolicyPreset::Nist => PasswordPolicy {
+                min_length: Some(8),
+                max_length: Some(64),
+                max_repeat: Some(3),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::input(path, e))?;
+        toml::from_str(&content).map_err(|e| {
+            let line = e.span().map(|span| content[..span.start].matches('\n').count() + 1);
+            Error::parse(path, line, e.message())
+        })
+    }
+
+    /// True when the class counts alone satisfy the composition rules.
+    /// Length, repeat and substring rules are checked by `allows`.
+    pub fn allows_counts(&self, counts: &ClassCounts) -> bool {
+        counts.upper >= self.min_upper
+            && counts.lower >= self.min_lower
+            && counts.upper + counts.lower >= self.min_letters
+            && counts.digits >= self.min_digits
+            && counts.symbols >= self.min_symbols
+            && counts.classes() >= self.min_classes
+    }
+
+    pub fn allows(&self, candidate: &str) -> bool {
+        let length = candidate.chars().count();
+        if self.min_length.is_some_and(|min| length < min) || self.max_length.is_some_and(|max| length > max) {
+            return false;
+        }
+
+        if !self.allows_counts(&ClassCounts::of(candidate)) {
+            return false;
+        }
+
+        if let Some(max_repeat) = self.max_repeat {
+            if longest_run(candidate) > max_repeat {
+                return false;
+            }
+        }
+
+        if !self.forbidden.is_empty() {
+            let lower = candidate.to_lowercase();
+            if self.forbidden.iter().any(|f| !f.is_empty() && lower.contains(&f.to_lowercase())) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// True when every rule depends only on length and per-class counts,
+    /// which add up across concatenated parts, so the combinatorics module
+    /// can predict survivors exactly. Repeat and substring rules look across
+    /// part boundaries and can only be checked on the final candidate.
+    pub fn is_additive(&self) -> bool {
+        self.max_repeat.is_none() && self.forbidden.is_empty()
+    }
+}
+
+fn longest_run(candidate: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous = None;
+
+    for ch in candidate.chars() {
+        if Some(ch) == previous {
+            current += 1;
+        } else {
+            current = 1;
+            previous = Some(ch);
+        }
+        longest = longest.max(current);
+    }
+
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn test_ad_preset() {
+        let policy = PasswordPolicy::preset(PolicyPreset::Ad);
+        assert!(policy.allows("P4ssword"));
+        assert!(policy.allows("p4ssw0rd!"));
+        assert!(!policy.allows("password"));
+        assert!(!policy.allows("P4ss")); // too short
+    }
+
+    #[test]
+    fn test_pci_dss_preset() {
+        let policy = PasswordPolicy::preset(PolicyPreset::PciDss);
+        assert!(policy.allows("adminpassw0rd"));
+        assert!(!policy.allows("adminpassword"));
+        assert!(!policy.allows("4dm1n"));
+    }
+
+    #[test]
+    fn test_repeat_and_forbidden_rules() {
+        let policy = PasswordPolicy {
+            max_repeat: Some(2),
+            forbidden: vec!["Admin".to_string()],
+            ..Default::default()
+        };
+        assert!(policy.allows("passw0rd"));
+        assert!(!policy.allows("paaassword"));
+        assert!(!policy.allows("SuperADMIN1"));
+    }
+
+    #[test]
+    fn test_policy_from_toml() {
+        let policy: PasswordPolicy = toml::from_str("min_upper = 1\nmin_digits = 2\nforbidden = [\"jsmith\"]").unwrap();
+        assert_eq!(policy.min_upper, 1);
+        assert_eq!(policy.min_digits, 2);
+        assert_eq!(policy.forbidden, vec!["jsmith".to_string()]);
+        assert!(toml::from_str::<PasswordPolicy>("min_uppercase = 1").is_err());
+    }
+
+    #[test]
+    fn test_policy_file_errors() {
+        let temp = TempPath::new("policy.toml");
+        let path = temp.as_str();
+        assert!(matches!(PasswordPolicy::from_file(path), Err(Error::Input { .. })));
+
+        std::fs::write(path, "min_upper = 1\nmin_digits = \"two\"\n").unwrap();
+        let err = PasswordPolicy::from_file(path).unwrap_err();
+        assert!(matches!(err, Error::Parse { line: Some(2), .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,351 @@
+use crate::candidates::CandidateSource;
+use crate::error::{Error, Result};
+use crate::filter::CandidateFilter;
+use crate::transform::TransformChain;
+use std::collections::{BTreeMap, HashSet};
+
+/// Elements per chain when no limit is given, as in princeprocessor.
+pub const DEFAULT_MAX_ELEMENTS: usize = 8;
+
+/// Most length combinations kept in memory; each is stored to sort them
+/// by keyspace.
+pub const MAX_CHAINS: u64 = 2_000_000;
+
+/// A combination of element lengths, e.g. 5 + 3 + 4 bytes, and how many
+/// candidates it makes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Chain {
+    lengths: Vec<usize>,
+    keyspace: u64,
+}
+
+impl Chain {
+    /// Level of the chain's candidates: floor(log2 keyspace), as if every
+    /// candidate of a chain were equally likely.
+    fn tier(&self) -> usize {
+        self.keyspace.ilog2() as usize + 1
+    }
+}
+
+/// Enumerates PRINCE-style candidates: chains of elements (the word
+/// transforms' variants of the input words) whose total length is within
+/// the bounds. Unlike word combinations, an element can appear any number
+/// of times in a chain.
+///
+/// Chains are grouped by the lengths of their elements, and the groups are
+/// generated smallest keyspace first. Within a group, elements keep the
+/// input order, last element varying fastest. A group's level (the log2
+/// of its keyspace) is its tier, for the generation pipeline's progress
+/// reporting and run controls. Different length combinations can make the
+/// same candidate ("ad" + "min", "admin"), so duplicates are possible.
+#[derive(Debug)]
+pub struct PrinceGenerator {
+    /// Distinct elements by length in bytes, in input order
+    elements: BTreeMap<usize, Vec<String>>,
+    /// Length combinations, smallest keyspace first
+    chains: Vec<Chain>,
+    next_chain: usize,
+    current: Option<usize>,
+    /// Element index per position of the current chain; None before the first
+    odometer: Option<Vec<usize>>,
+    /// Tier whose remaining chains are being skipped
+    skipping: Option<usize>,
+    filter: CandidateFilter,
+    label: String,
+    candidate: String,
+    accepted: usize,
+}
+
+impl PrinceGenerator {
+    /// Chain the word transforms' variants of `words` (candidate transforms
+    /// such as padding are not used) into candidates of `min_len` to
+    /// `max_len` bytes, with at most `max_elements` elements each (0:
+    /// `DEFAULT_MAX_ELEMENTS`). A Config error if there would be more than
+    /// `MAX_CHAINS` length combinations.
+    pub fn new(words: &[String], transforms: &TransformChain, min_len: usize, max_len: usize, max_elements: usize, filter: CandidateFilter) -> Result<Self> {
+        let mut elements: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        let mut seen = HashSet::new();
+        for word in words {
+            for variant in transforms.word_variants(word) {
+                if !variant.is_empty() && variant.len() <= max_len && seen.insert(variant.clone()) {
+                    elements.entry(variant.len()).or_default().push(variant);
+                }
+            }
+        }
+
+        let sizes: Vec<(usize, u64)> = elements.iter().map(|(&len, list)| (len, list.len() as u64)).collect();
+        let max_elements = if max_elements == 0 { DEFAULT_MAX_ELEMENTS } else { max_elements.min(max_len) };
+        let count = count_chains(&sizes, min_len, max_len, max_elements);
+        if count > MAX_CHAINS {
+            return Err(Error::config(format!(
+                "Too many element length combinations ({}, at most {}); lower --max-length or --max-words",
+                count, MAX_CHAINS
+            )));
+        }
+        let mut chains = Vec::with_capacity(count as usize);
+        collect_chains(&sizes, min_len, max_len, max_elements, &mut Vec::new(), 1, &mut chains);
+        chains.sort_by(|a, b| {
+            a.keyspace
+                .cmp(&b.keyspace)
+                .then_with(|| a.lengths.len().cmp(&b.lengths.len()))
+                .then_with(|| a.lengths.cmp(&b.lengths))
+        });
+
+        Ok(PrinceGenerator {
+            elements,
+            chains,
+            next_chain: 0,
+            current: None,
+            odometer: None,
+            skipping: None,
+            filter,
+            label: String::new(),
+            candidate: String::new(),
+            accepted: 0,
+        })
+    }
+
+    /// Distinct elements chains are made of.
+    pub fn elements(&self) -> usize {
+        self.elements.values().map(Vec::len).sum()
+    }
+
+    /// Length combinations within the bounds.
+    pub fn chains(&self) -> usize {
+        self.chains.len()
+    }
+
+    /// Candidates all chains make, before filters; saturates at `u64::MAX`.
+    pub fn keyspace(&self) -> u64 {
+        self.chains.iter().fold(0u64, |sum, chain| sum.saturating_add(chain.keyspace))
+    }
+
+    /// Candidates accepted so far.
+    pub fn accepted(&self) -> usize {
+        self.accepted
+    }
+
+    /// Next accepted candidate, moving through chains as needed.
+    pub fn next_candidate(&mut self) -> Option<&str> {
+        loop {
+            if self.advance() {
+                return Some(&self.candidate);
+            }
+            self.next_group()?;
+        }
+    }
+
+    /// Step the odometer over the elements of the current chain to the
+    /// next accepted candidate.
+    fn advance(&mut self) -> bool {
+        let Some(current) = self.current else {
+            return false;
+        };
+        let lengths = &self.chains[current].lengths;
+        loop {
+            let odometer = match &mut self.odometer {
+                None => self.odometer.insert(vec![0; lengths.len()]),
+                Some(odometer) => {
+                    // Last element varies fastest
+                    let mut i = lengths.len();
+                    loop {
+                        if i == 0 {
+                            self.current = None;
+                            return false;
+                        }
+                        i -= 1;
+                        odometer[i] += 1;
+                        if odometer[i] < self.elements[&lengths[i]].len() {
+                            break;
+                        }
+                        odometer[i] = 0;
+                    }
+                    odometer
+                }
+            };
+
+            self.candidate.clear();
+            for (len, &index) in lengths.iter().zip(odometer.iter()) {
+                self.candidate.push_str(&self.elements[len][index]);
+            }
+            if self.filter.accept(&self.candidate) {
+                self.accepted += 1;
+                return true;
+            }
+        }
+    }
+}
+
+/// How many sequences `collect_chains` would make, saturating at `u64::MAX`.
+fn count_chains(sizes: &[(usize, u64)], min_len: usize, max_len: usize, max_elements: usize) -> u64 {
+    // ways[t]: sequences of the current element count totalling t bytes
+    let mut ways = vec![0u64; max_len + 1];
+    ways[0] = 1;
+    let mut count = 0u64;
+    for _ in 0..max_elements {
+        let mut next = vec![0u64; max_len + 1];
+        for (total, &n) in ways.iter().enumerate().filter(|(_, &n)| n > 0) {
+            for &(len, _) in sizes.iter().take_while(|(len, _)| total + len <= max_len) {
+                next[total + len] = next[total + len].saturating_add(n);
+            }
+        }
+        ways = next;
+        count = ways[min_len.min(max_len + 1)..].iter().fold(count, |sum, &n| sum.saturating_add(n));
+    }
+    count
+}
+
+/// Every sequence of element lengths from `sizes` (length, elements of
+/// that length) totalling `min_len..=max_len` bytes in at most
+/// `max_elements` elements, extending `prefix`, whose candidates number
+/// `keyspace`.
+fn collect_chains(
+    sizes: &[(usize, u64)],
+    min_len: usize,
+    max_len: usize,
+    max_elements: usize,
+    prefix: &mut Vec<usize>,
+    keyspace: u64,
+    chains: &mut Vec<Chain>,
+) {
+    let total: usize = prefix.iter().sum();
+    if !prefix.is_empty() && total >= min_len {
+        chains.push(Chain { lengths: prefix.clone(), keyspace });
+    }
+    if prefix.len() == max_elements {
+        return;
+    }
+    for &(len, count) in sizes.iter().take_while(|(len, _)| total + len <= max_len) {
+        prefix.push(len);
+        collect_chains(sizes, min_len, max_len, max_elements, prefix, keyspace.saturating_mul(count), chains);
+        prefix.pop();
+    }
+}
+
+impl CandidateSource for PrinceGenerator {
+    fn next_group(&mut self) -> Option<usize> {
+        loop {
+            let chain = self.chains.get(self.next_chain)?;
+            self.next_chain += 1;
+            let tier = chain.tier();
+            if self.skipping.is_some_and(|skipped| tier <= skipped) {
+                continue;
+            }
+            self.skipping = None;
+
+            let lengths: Vec<String> = chain.lengths.iter().map(usize::to_string).collect();
+            self.label = format!("{} bytes (keyspace {})", lengths.join(" + "), chain.keyspace);
+            self.current = Some(self.next_chain - 1);
+            self.odometer = None;
+            return Some(tier);
+        }
+    }
+
+    fn group(&self) -> Vec<&String> {
+        vec![&self.label]
+    }
+
+    fn next_in_group(&mut self) -> Option<&str> {
+        if self.advance() {
+            Some(&self.candidate)
+        } else {
+            None
+        }
+    }
+
+    fn skip_tier(&mut self) {
+        self.skipping = self.current.take().map(|current| self.chains[current].tier());
+    }
+
+    fn candidate_filter(&self) -> &CandidateFilter {
+        &self.filter
+    }
+}
+
+impl Iterator for PrinceGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.next_candidate().map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    fn plain() -> TransformChain {
+        TransformChain::parse("").unwrap()
+    }
+
+    #[test]
+    fn test_elements_repeat_within_length_bounds() {
+        let generator = PrinceGenerator::new(&words(&["ab", "xyz"]), &plain(), 4, 6, 0, CandidateFilter::default()).unwrap();
+        let candidates: Vec<String> = generator.collect();
+        assert_eq!(
+            candidates,
+            ["abab", "abxyz", "xyzab", "xyzxyz", "ababab"],
+            "one candidate per chain; on equal keyspace, fewer elements first"
+        );
+    }
+
+    #[test]
+    fn test_chains_ordered_by_keyspace() {
+        let generator = PrinceGenerator::new(&words(&["a", "b", "c", "hello"]), &plain(), 5, 6, 2, CandidateFilter::default()).unwrap();
+        // 1+5 (3), 5+1 (3), hello (1)
+        assert_eq!(generator.chains(), 3);
+        assert_eq!(generator.keyspace(), 7);
+        assert_eq!(generator.elements(), 4);
+
+        let mut generator = generator;
+        let mut groups = Vec::new();
+        while let Some(tier) = generator.next_group() {
+            groups.push((tier, generator.group()[0].clone()));
+        }
+        assert_eq!(
+            groups,
+            [
+                (1, "5 bytes (keyspace 1)".to_string()),
+                (2, "1 + 5 bytes (keyspace 3)".to_string()),
+                (2, "5 + 1 bytes (keyspace 3)".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_word_variants_are_elements() {
+        let transforms = TransformChain::parse("case").unwrap();
+        let generator = PrinceGenerator::new(&words(&["ab"]), &transforms, 4, 4, 0, CandidateFilter::default()).unwrap();
+        assert_eq!(generator.keyspace(), 9);
+        let candidates: Vec<String> = generator.collect();
+        assert_eq!(&candidates[..3], ["ABAB", "ABAb", "ABab"]);
+        assert_eq!(candidates.iter().collect::<HashSet<_>>().len(), 9);
+    }
+
+    #[test]
+    fn test_chain_count_is_checked_up_front() {
+        let sizes = [(1, 2), (3, 1), (4, 5)];
+        let mut chains = Vec::new();
+        collect_chains(&sizes, 4, 12, 5, &mut Vec::new(), 1, &mut chains);
+        assert_eq!(count_chains(&sizes, 4, 12, 5), chains.len() as u64);
+
+        let words: Vec<String> = (1..=10).map(|len| "x".repeat(len)).collect();
+        let result = PrinceGenerator::new(&words, &plain(), 8, 63, 0, CandidateFilter::default());
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_skip_tier_moves_to_next_level() {
+        let mut generator = PrinceGenerator::new(&words(&["a", "b", "hello"]), &plain(), 5, 7, 3, CandidateFilter::default()).unwrap();
+        let mut tiers = Vec::new();
+        while let Some(tier) = generator.next_group() {
+            tiers.push(tier);
+            generator.skip_tier();
+        }
+        assert_eq!(tiers, [1, 2, 3]);
+    }
+}
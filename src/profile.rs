@@ -0,0 +1,116 @@
+use crate::error::{Error, Result};
+
+/// Facts about a target person that commonly end up in their passwords.
+#[derive(Debug, Clone, Default)]
+pub struct TargetProfile {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub nickname: Option<String>,
+    pub partner: Option<String>,
+    pub children: Vec<String>,
+    pub pets: Vec<String>,
+    pub company: Option<String>,
+    /// Dates as YYYY-MM-DD
+    pub dates: Vec<String>,
+    pub keywords: Vec<String>,
+}
+
+impl TargetProfile {
+    pub fn is_empty(&self) -> bool {
+        self.names().next().is_none() && self.dates.is_empty() && self.keywords.is_empty()
+    }
+
+    fn names(&self) -> impl Iterator<Item = &String> {
+        self.first_name
+            .iter()
+            .chain(&self.last_name)
+            .chain(&self.nickname)
+            .chain(&self.partner)
+            .chain(&self.children)
+            .chain(&self.pets)
+            .chain(&self.company)
+    }
+
+    /// Base words for the generator. Case and leet variants, padding and
+    /// word combinations are left to the normal pipeline, so this only
+    /// lists the distinct pieces: each name (multi-word names also joined),
+    /// first initial plus last name, and year, short year, DDMM and MMDD
+    /// for every date.
+    pub fn words(&self) -> Result<Vec<String>> {
+        let mut words = Vec::new();
+
+        for name in self.names().chain(&self.keywords) {
+            let parts: Vec<String> = name.split_whitespace().map(str::to_lowercase).collect();
+            if parts.len() > 1 {
+                words.push(parts.concat());
+            }
+            words.extend(parts);
+        }
+
+        if let (Some(first), Some(last)) = (&self.first_name, &self.last_name) {
+            if let Some(initial) = first.trim().chars().next() {
+                words.push(format!("{}{}", initial, last.split_whitespace().collect::<String>()).to_lowercase());
+            }
+        }
+
+        for date in &self.dates {
+            let (year, month, day) = parse_date(date)?;
+            words.push(format!("{:04}", year));
+            words.push(format!("{:02}", year % 100));
+            words.push(format!("{:02}{:02}", day, month));
+            words.push(format!("{:02}{:02}", month, day));
+        }
+
+        words.sort();
+        words.dedup();
+        words.retain(|w| !w.is_empty());
+        Ok(words)
+    }
+}
+
+fn parse_date(date: &str) -> Result<(u32, u32, u32)> {
+    let invalid = || Error::config(format!("Invalid date '{}': expected YYYY-MM-DD", date));
+    let parts: Vec<&str> = date.trim().split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 {
+        return Err(invalid());
+    }
+
+    let year: u32 = parts[0].parse().map_err(|_| invalid())?;
+    let month: u32 = parts[1].parse().map_err(|_| invalid())?;
+    let day: u32 = parts[2].parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+    Ok((year, month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_words() {
+        let profile = TargetProfile {
+            first_name: Some("John".to_string()),
+            last_name: Some("Smith".to_string()),
+            pets: vec!["Mr Whiskers".to_string()],
+            dates: vec!["1985-03-12".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            profile.words().unwrap(),
+            vec!["0312", "1203", "1985", "85", "john", "jsmith", "mr", "mrwhiskers", "smith", "whiskers"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_date_is_an_error() {
+        let profile = TargetProfile {
+            dates: vec!["12/03/1985".to_string()],
+            ..Default::default()
+        };
+        assert!(profile.words().is_err());
+        assert!(TargetProfile::default().is_empty());
+    }
+}
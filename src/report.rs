@@ -0,0 +1,156 @@
+use crate::combinatorics::{CombinatorialAnalysis, PolicyEstimate};
+use crate::error::{Error, Result};
+use crate::transform::TransformChain;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io;
+
+/// Candidates per second one core sustains writing uncompressed plaintext,
+/// measured on a release build. Only a rough guide: hashing, compression,
+/// filters and slow disks all lower the real rate.
+pub const BASELINE_RATE: f64 = 3_000_000.0;
+
+/// Machine-readable formats for `--dry-run` reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Json,
+    Yaml,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WordReport {
+    pub word: String,
+    /// Distinct variants the word transforms make of the word
+    pub variants: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SizeEstimate {
+    /// Uncompressed output size, including hashes when hashing output
+    pub raw_bytes: u64,
+    pub compression: &'static str,
+    pub compressed_bytes: u64,
+}
+
+/// Time the job takes at `BASELINE_RATE`: a nominal figure for sizing jobs,
+/// not a measurement of this machine.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeEstimate {
+    /// Candidates in the whole job, which can be more than the capped
+    /// `total_combinations`
+    pub candidates: u64,
+    /// True when `total_combinations` is capped below `candidates`
+    pub total_capped: bool,
+    pub candidates_per_second: f64,
+    /// Always true: the rate is `BASELINE_RATE`, not measured
+    pub nominal_rate: bool,
+    pub seconds: f64,
+}
+
+impl RuntimeEstimate {
+    pub fn nominal(analysis: &CombinatorialAnalysis) -> Self {
+        let candidates = analysis.keyspace();
+        RuntimeEstimate {
+            candidates,
+            total_capped: candidates > analysis.total_combinations,
+            candidates_per_second: BASELINE_RATE,
+            nominal_rate: true,
+            seconds: candidates as f64 / BASELINE_RATE,
+        }
+    }
+}
+
+/// Everything the analysis knows about a job, for orchestration scripts
+/// that size jobs before running them.
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisReport {
+    pub words: Vec<WordReport>,
+    pub analysis: CombinatorialAnalysis,
+    /// None when candidates are not written out (`crack`, `wpa2`)
+    pub output: Option<SizeEstimate>,
+    pub policy: Option<PolicyEstimate>,
+    pub runtime: RuntimeEstimate,
+}
+
+impl AnalysisReport {
+    pub fn new(
+        words: &[String],
+        transforms: &TransformChain,
+        analysis: &CombinatorialAnalysis,
+        output: Option<SizeEstimate>,
+        policy: Option<PolicyEstimate>,
+    ) -> Self {
+        AnalysisReport {
+            words: words
+                .iter()
+                .map(|word| WordReport {
+                    word: word.clone(),
+                    variants: transforms.word_variants(word).len() as u64,
+                })
+                .collect(),
+            analysis: analysis.clone(),
+            output,
+            policy,
+            runtime: RuntimeEstimate::nominal(analysis),
+        }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String> {
+        let rendered = match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self).map_err(io::Error::other),
+            ReportFormat::Yaml => serde_yaml::to_string(self).map_err(io::Error::other),
+        };
+        rendered.map_err(|e| Error::output("report", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combinatorics::{calculate_total_combinations, CombinatorialConfig};
+
+    #[test]
+    fn test_report_formats() {
+        let words = vec!["admin".to_string(), "pass".to_string()];
+        let config = CombinatorialConfig {
+            max_words: 2,
+            transforms: TransformChain::standard(false),
+            ..Default::default()
+        };
+        let analysis = calculate_total_combinations(&words, &config).unwrap();
+        let report = AnalysisReport::new(&words, &config.transforms, &analysis, None, None);
+
+        let json: serde_json::Value = serde_json::from_str(&report.render(ReportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["analysis"]["total_combinations"], analysis.total_combinations);
+        assert_eq!(json["words"][0]["word"], "admin");
+        assert_eq!(json["words"][0]["variants"], config.transforms.word_variants("admin").len());
+        assert_eq!(json["analysis"]["breakdown"]["transforms"], serde_json::json!(["leet", "case"]));
+        assert_eq!(json["analysis"]["breakdown"]["by_word_count"].as_array().unwrap().len(), 2);
+        assert!(json["output"].is_null());
+
+        let yaml = report.render(ReportFormat::Yaml).unwrap();
+        assert!(yaml.contains(&format!("total_combinations: {}", analysis.total_combinations)));
+        assert!(yaml.contains("candidates_per_second:"));
+        assert_eq!(json["runtime"]["candidates"], analysis.total_combinations);
+        assert_eq!(json["runtime"]["total_capped"], false);
+        assert_eq!(json["runtime"]["nominal_rate"], true);
+    }
+
+    #[test]
+    fn test_runtime_covers_capped_total() {
+        // Ten leet-able characters: 1024 variants a word, about 2.6e13 candidates in four-word tiers
+        let words: Vec<String> = ["aeiolsaeio", "eiolsaeioa", "iolsaeioae", "olsaeioaei"].map(String::from).to_vec();
+        let config = CombinatorialConfig {
+            transforms: TransformChain::parse("leet").unwrap(),
+            ..Default::default()
+        };
+        let analysis = calculate_total_combinations(&words, &config).unwrap();
+        let runtime = AnalysisReport::new(&words, &config.transforms, &analysis, None, None).runtime;
+
+        assert_eq!(analysis.total_combinations, 1_000_000_000);
+        assert!(runtime.total_capped);
+        assert_eq!(runtime.candidates, analysis.keyspace());
+        assert!(runtime.candidates > 1024u64.pow(4));
+        assert_eq!(runtime.seconds, runtime.candidates as f64 / BASELINE_RATE);
+    }
+}
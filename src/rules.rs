@@ -0,0 +1,357 @@
+//! A subset of the hashcat rule language, enough for common rule files
+//! such as best64. Positions use hashcat's 0-9, A-Z notation. Functions
+//! whose positions fall outside the word leave it unchanged.
+
+use crate::error::{Error, Result};
+use crate::sink::CandidateSink;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+/// Words read per batch; each batch is expanded by all rules in parallel.
+const BATCH_SIZE: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleOp {
+    Noop,
+    Lower,
+    Upper,
+    Capitalize,
+    InvertCapitalize,
+    ToggleAll,
+    ToggleAt(usize),
+    Reverse,
+    Duplicate,
+    DuplicateN(usize),
+    Reflect,
+    RotateLeft,
+    RotateRight,
+    Append(char),
+    Prepend(char),
+    DeleteFirst,
+    DeleteLast,
+    DeleteAt(usize),
+    Extract(usize, usize),
+    Omit(usize, usize),
+    Insert(usize, char),
+    Overwrite(usize, char),
+    Truncate(usize),
+    Replace(char, char),
+    Purge(char),
+    DuplicateFirst(usize),
+    DuplicateLast(usize),
+    DuplicateAll,
+    SwapFront,
+    SwapBack,
+    Swap(usize, usize),
+}
+
+/// One line of a rule file: functions applied left to right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    ops: Vec<RuleOp>,
+}
+
+impl Rule {
+    pub fn parse(line: &str) -> std::result::Result<Self, String> {
+        let mut chars = line.chars();
+        let mut ops = Vec::new();
+
+        while let Some(function) = chars.next() {
+            let mut arg = || chars.next().ok_or_else(|| format!("function '{}' is missing an argument", function));
+            let op = match function {
+                // Spaces separate functions
+                ' ' => continue,
+                ':' => RuleOp::Noop,
+                'l' => RuleOp::Lower,
+                'u' => RuleOp::Upper,
+                'c' => RuleOp::Capitalize,
+                'C' => RuleOp::InvertCapitalize,
+                't' => RuleOp::ToggleAll,
+                'T' => RuleOp::ToggleAt(position(arg()?)?),
+                'r' => RuleOp::Reverse,
+                'd' => RuleOp::Duplicate,
+                'p' => RuleOp::DuplicateN(position(arg()?)?),
+                'f' => RuleOp::Reflect,
+                '{' => RuleOp::RotateLeft,
+                '}' => RuleOp::RotateRight,
+                '$' => RuleOp::Append(arg()?),
+                '^' => RuleOp::Prepend(arg()?),
+                '[' => RuleOp::DeleteFirst,
+                ']' => RuleOp::DeleteLast,
+                'D' => RuleOp::DeleteAt(position(arg()?)?),
+                'x' => RuleOp::Extract(position(arg()?)?, position(arg()?)?),
+                'O' => RuleOp::Omit(position(arg()?)?, position(arg()?)?),
+                'i' => RuleOp::Insert(position(arg()?)?, arg()?),
+                'o' => RuleOp::Overwrite(position(arg()?)?, arg()?),
+                '\'' => RuleOp::Truncate(position(arg()?)?),
+                's' => RuleOp::Replace(arg()?, arg()?),
+                '@' => RuleOp::Purge(arg()?),
+                'z' => RuleOp::DuplicateFirst(position(arg()?)?),
+                'Z' => RuleOp::DuplicateLast(position(arg()?)?),
+                'q' => RuleOp::DuplicateAll,
+                'k' => RuleOp::SwapFront,
+                'K' => RuleOp::SwapBack,
+                '*' => RuleOp::Swap(position(arg()?)?, position(arg()?)?),
+                other => return Err(format!("unsupported rule function '{}'", other)),
+            };
+            ops.push(op);
+        }
+
+        Ok(Rule { ops })
+    }
+
+    pub fn apply(&self, word: &str) -> String {
+        let mut chars: Vec<char> = word.chars().collect();
+        for op in &self.ops {
+            apply_op(*op, &mut chars);
+        }
+        chars.into_iter().collect()
+    }
+}
+
+/// Load every rule from the given files, skipping blank lines and `#` comments.
+pub fn load_rules(paths: &[String]) -> Result<Vec<Rule>> {
+    let mut rules = Vec::new();
+    for path in paths {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::input(path, e))?;
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = Rule::parse(line).map_err(|e| Error::parse(path, Some(number + 1), e))?;
+            rules.push(rule);
+        }
+    }
+    Ok(rules)
+}
+
+/// Outcome of applying rules to a wordlist.
+#[derive(Debug, Clone, Default)]
+pub struct RulesSummary {
+    /// Words read from the wordlist
+    pub words: u64,
+    /// Lines skipped because they are not valid UTF-8
+    pub invalid_utf8: u64,
+    /// Candidates written
+    pub count: u64,
+}
+
+/// Apply every rule to every word of `wordlist`, word-major like hashcat's
+/// `-r`, and write the non-empty results to `output`, which is finished
+/// at the end.
+pub fn apply_rules(wordlist: &str, rules: &[Rule], mut output: impl CandidateSink) -> Result<RulesSummary> {
+    let mut summary = RulesSummary::default();
+    let mut reader = BufReader::new(File::open(wordlist).map_err(|e| Error::input(wordlist, e))?);
+    let mut line = Vec::new();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+    loop {
+        line.clear();
+        let done = reader.read_until(b'\n', &mut line).map_err(|e| Error::input(wordlist, e))? == 0;
+        while matches!(line.last(), Some(b'\n') | Some(b'\r')) {
+            line.pop();
+        }
+
+        if !line.is_empty() {
+            summary.words += 1;
+            match String::from_utf8(std::mem::take(&mut line)) {
+                Ok(word) => batch.push(word),
+                Err(_) => summary.invalid_utf8 += 1,
+            }
+        }
+
+        if batch.len() >= BATCH_SIZE || (done && !batch.is_empty()) {
+            let candidates: Vec<String> = batch
+                .par_iter()
+                .flat_map_iter(|word| rules.iter().map(move |rule| rule.apply(word)))
+                .filter(|candidate| !candidate.is_empty())
+                .collect();
+            output.write_chunk(&candidates)?;
+            summary.count += candidates.len() as u64;
+            batch.clear();
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    output.finish()?;
+    Ok(summary)
+}
+
+fn position(ch: char) -> std::result::Result<usize, String> {
+    match ch {
+        '0'..='9' => Ok(ch as usize - '0' as usize),
+        'A'..='Z' => Ok(ch as usize - 'A' as usize + 10),
+        _ => Err(format!("invalid position '{}'", ch)),
+    }
+}
+
+fn toggle(ch: char) -> char {
+    if ch.is_uppercase() {
+        ch.to_lowercase().next().unwrap_or(ch)
+    } else {
+        ch.to_uppercase().next().unwrap_or(ch)
+    }
+}
+
+fn apply_op(op: RuleOp, chars: &mut Vec<char>) {
+    let len = chars.len();
+    match op {
+        RuleOp::Noop => {}
+        RuleOp::Lower => *chars = chars.iter().collect::<String>().to_lowercase().chars().collect(),
+        RuleOp::Upper => *chars = chars.iter().collect::<String>().to_uppercase().chars().collect(),
+        RuleOp::Capitalize | RuleOp::InvertCapitalize => {
+            let upper = op == RuleOp::Capitalize;
+            for (i, ch) in chars.iter_mut().enumerate() {
+                let make_upper = (i == 0) == upper;
+                *ch = if make_upper {
+                    ch.to_uppercase().next().unwrap_or(*ch)
+                } else {
+                    ch.to_lowercase().next().unwrap_or(*ch)
+                };
+            }
+        }
+        RuleOp::ToggleAll => chars.iter_mut().for_each(|ch| *ch = toggle(*ch)),
+        RuleOp::ToggleAt(n) => {
+            if let Some(ch) = chars.get_mut(n) {
+                *ch = toggle(*ch);
+            }
+        }
+        RuleOp::Reverse => chars.reverse(),
+        RuleOp::Duplicate => chars.extend_from_within(..),
+        RuleOp::DuplicateN(n) => {
+            for _ in 0..n {
+                chars.extend_from_within(..len);
+            }
+        }
+        RuleOp::Reflect => {
+            let reversed: Vec<char> = chars.iter().rev().copied().collect();
+            chars.extend(reversed);
+        }
+        RuleOp::RotateLeft if len > 0 => chars.rotate_left(1),
+        RuleOp::RotateRight if len > 0 => chars.rotate_right(1),
+        RuleOp::Append(ch) => chars.push(ch),
+        RuleOp::Prepend(ch) => chars.insert(0, ch),
+        RuleOp::DeleteFirst if len > 0 => {
+            chars.remove(0);
+        }
+        RuleOp::DeleteLast => {
+            chars.pop();
+        }
+        RuleOp::DeleteAt(n) if n < len => {
+            chars.remove(n);
+        }
+        RuleOp::Extract(n, m) if n + m <= len => {
+            chars.truncate(n + m);
+            chars.drain(..n);
+        }
+        RuleOp::Omit(n, m) if n + m <= len => {
+            chars.drain(n..n + m);
+        }
+        RuleOp::Insert(n, ch) if n <= len => chars.insert(n, ch),
+        RuleOp::Overwrite(n, ch) if n < len => chars[n] = ch,
+        RuleOp::Truncate(n) => chars.truncate(n),
+        RuleOp::Replace(from, to) => chars.iter_mut().filter(|ch| **ch == from).for_each(|ch| *ch = to),
+        RuleOp::Purge(ch) => chars.retain(|c| *c != ch),
+        RuleOp::DuplicateFirst(n) if len > 0 => {
+            let first = chars[0];
+            chars.splice(0..0, std::iter::repeat_n(first, n));
+        }
+        RuleOp::DuplicateLast(n) if len > 0 => {
+            let last = chars[len - 1];
+            chars.extend(std::iter::repeat_n(last, n));
+        }
+        RuleOp::DuplicateAll => *chars = chars.iter().flat_map(|&ch| [ch, ch]).collect(),
+        RuleOp::SwapFront if len >= 2 => chars.swap(0, 1),
+        RuleOp::SwapBack if len >= 2 => chars.swap(len - 2, len - 1),
+        RuleOp::Swap(n, m) if n < len && m < len => chars.swap(n, m),
+        // Positions outside the word leave it unchanged
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    fn apply(rule: &str, word: &str) -> String {
+        Rule::parse(rule).unwrap().apply(word)
+    }
+
+    #[test]
+    fn test_case_and_order_functions() {
+        assert_eq!(apply(":", "p@ssW0rd"), "p@ssW0rd");
+        assert_eq!(apply("l", "p@ssW0rd"), "p@ssw0rd");
+        assert_eq!(apply("u", "p@ssW0rd"), "P@SSW0RD");
+        assert_eq!(apply("c", "p@ssW0rd"), "P@ssw0rd");
+        assert_eq!(apply("C", "p@ssW0rd"), "p@SSW0RD");
+        assert_eq!(apply("t", "p@ssW0rd"), "P@SSw0RD");
+        assert_eq!(apply("T3", "p@ssW0rd"), "p@sSW0rd");
+        assert_eq!(apply("r", "p@ssW0rd"), "dr0Wss@p");
+        assert_eq!(apply("d", "p@ss"), "p@ssp@ss");
+        assert_eq!(apply("p2", "ab"), "ababab");
+        assert_eq!(apply("f", "abc"), "abccba");
+        assert_eq!(apply("{", "abc"), "bca");
+        assert_eq!(apply("}", "abc"), "cab");
+    }
+
+    #[test]
+    fn test_editing_functions() {
+        assert_eq!(apply("$1 $2 $3", "pass"), "pass123");
+        assert_eq!(apply("^1^2", "pass"), "21pass");
+        assert_eq!(apply("[", "pass"), "ass");
+        assert_eq!(apply("]", "pass"), "pas");
+        assert_eq!(apply("D1", "pass"), "pss");
+        assert_eq!(apply("x13", "password"), "ass");
+        assert_eq!(apply("O12", "password"), "psword");
+        assert_eq!(apply("i4!", "pass"), "pass!");
+        assert_eq!(apply("o0P", "pass"), "Pass");
+        assert_eq!(apply("'3", "password"), "pas");
+        assert_eq!(apply("sa@ss$", "pass"), "p@$$");
+        assert_eq!(apply("@s", "pass"), "pa");
+        assert_eq!(apply("z2", "abc"), "aaabc");
+        assert_eq!(apply("Z2", "abc"), "abccc");
+        assert_eq!(apply("q", "abc"), "aabbcc");
+        assert_eq!(apply("k", "abc"), "bac");
+        assert_eq!(apply("K", "abc"), "acb");
+        assert_eq!(apply("*02", "abc"), "cba");
+        assert_eq!(apply("$ ", "a"), "a ");
+    }
+
+    #[test]
+    fn test_out_of_range_positions_leave_word_unchanged() {
+        assert_eq!(apply("DA", "pass"), "pass");
+        assert_eq!(apply("x35", "pass"), "pass");
+        assert_eq!(apply("*09", "pass"), "pass");
+    }
+
+    #[test]
+    fn test_apply_rules_to_wordlist() {
+        let dir = TempPath::dir("rules");
+        let wordlist = dir.join("words.txt");
+        let output = dir.join("out.txt");
+        std::fs::write(&wordlist, b"admin\r\n\nsecret\n\xff\n").unwrap();
+
+        let rules = vec![Rule::parse(":").unwrap(), Rule::parse("c $1").unwrap(), Rule::parse("'0").unwrap()];
+        let writer = crate::output::OutputWriter::open(&output, false, crate::output::Compression::None, None).unwrap();
+        let summary = apply_rules(&wordlist, &rules, writer).unwrap();
+
+        assert_eq!((summary.words, summary.invalid_utf8, summary.count), (3, 1, 4));
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "admin\nAdmin1\nsecret\nSecret1\n");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Rule::parse("$").is_err());
+        assert!(Rule::parse("Ta").is_err());
+        assert!(Rule::parse("X").is_err());
+
+        let path = TempPath::file("rules.rule", "# comment\nc\n$\n");
+        let err = load_rules(&[path.as_str().to_string()]).unwrap_err();
+        assert!(matches!(err, Error::Parse { line: Some(3), .. }), "{:?}", err);
+    }
+}
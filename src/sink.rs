@@ -0,0 +1,186 @@
+use crate::error::{Error, Result};
+use std::io;
+use std::sync::mpsc::{Sender, SyncSender};
+
+/// Where accepted candidates go. `OutputWriter` covers files, stdout,
+/// compressed, split and hashed output; `Cracker` and `WpaVerifier` check
+/// candidates instead of storing them. `Vec<String>` collects them in
+/// memory and an mpsc sender hands them to another thread.
+pub trait CandidateSink {
+    /// Take a chunk of candidates, in order.
+    fn write_chunk(&mut self, candidates: &[String]) -> Result<()>;
+
+    /// Take a single candidate; exact dedup feeds its merged output this way.
+    fn write_candidate(&mut self, candidate: &str) -> Result<()> {
+        self.write_chunk(&[candidate.to_string()])
+    }
+
+    /// True when nothing more can be gained from further candidates.
+    fn is_done(&self) -> bool {
+        false
+    }
+
+    /// Mode and target shown in the status display.
+    fn describe(&self) -> (&'static str, String) {
+        ("Password Generator", "custom output".to_string())
+    }
+
+    /// Uncompressed bytes written so far, for sinks that write bytes.
+    fn bytes_written(&self) -> Option<u64> {
+        None
+    }
+
+    /// True when candidates are printed to the terminal the status display
+    /// reads keys from, so run controls have to stay off.
+    fn writes_to_terminal(&self) -> bool {
+        false
+    }
+
+    /// Called once after the last candidate, to flush and finalize.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: CandidateSink + ?Sized> CandidateSink for &mut S {
+    fn write_chunk(&mut self, candidates: &[String]) -> Result<()> {
+        (**self).write_chunk(candidates)
+    }
+
+    fn write_candidate(&mut self, candidate: &str) -> Result<()> {
+        (**self).write_candidate(candidate)
+    }
+
+    fn is_done(&self) -> bool {
+        (**self).is_done()
+    }
+
+    fn describe(&self) -> (&'static str, String) {
+        (**self).describe()
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        (**self).bytes_written()
+    }
+
+    fn writes_to_terminal(&self) -> bool {
+        (**self).writes_to_terminal()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        (**self).finish()
+    }
+}
+
+impl<S: CandidateSink + ?Sized> CandidateSink for Box<S> {
+    fn write_chunk(&mut self, candidates: &[String]) -> Result<()> {
+        (**self).write_chunk(candidates)
+    }
+
+    fn write_candidate(&mut self, candidate: &str) -> Result<()> {
+        (**self).write_candidate(candidate)
+    }
+
+    fn is_done(&self) -> bool {
+        (**self).is_done()
+    }
+
+    fn describe(&self) -> (&'static str, String) {
+        (**self).describe()
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        (**self).bytes_written()
+    }
+
+    fn writes_to_terminal(&self) -> bool {
+        (**self).writes_to_terminal()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        (**self).finish()
+    }
+}
+
+/// Collects candidates in memory.
+impl CandidateSink for Vec<String> {
+    fn write_chunk(&mut self, candidates: &[String]) -> Result<()> {
+        self.extend_from_slice(candidates);
+        Ok(())
+    }
+
+    fn write_candidate(&mut self, candidate: &str) -> Result<()> {
+        self.push(candidate.to_string());
+        Ok(())
+    }
+
+    fn describe(&self) -> (&'static str, String) {
+        ("Password Generator", "memory".to_string())
+    }
+}
+
+/// Sends candidates to a receiving thread. A dropped receiver ends the run
+/// the same way a closed pipe does.
+impl CandidateSink for Sender<String> {
+    fn write_chunk(&mut self, candidates: &[String]) -> Result<()> {
+        for candidate in candidates {
+            self.send(candidate.clone()).map_err(|_| receiver_gone())?;
+        }
+        Ok(())
+    }
+
+    fn write_candidate(&mut self, candidate: &str) -> Result<()> {
+        self.send(candidate.to_string()).map_err(|_| receiver_gone())
+    }
+
+    fn describe(&self) -> (&'static str, String) {
+        ("Password Generator", "channel".to_string())
+    }
+}
+
+/// Like `Sender`, but blocks while the channel is full, so a slow consumer
+/// holds back generation instead of buffering without bound.
+impl CandidateSink for SyncSender<String> {
+    fn write_chunk(&mut self, candidates: &[String]) -> Result<()> {
+        for candidate in candidates {
+            self.send(candidate.clone()).map_err(|_| receiver_gone())?;
+        }
+        Ok(())
+    }
+
+    fn write_candidate(&mut self, candidate: &str) -> Result<()> {
+        self.send(candidate.to_string()).map_err(|_| receiver_gone())
+    }
+
+    fn describe(&self) -> (&'static str, String) {
+        ("Password Generator", "channel".to_string())
+    }
+}
+
+fn receiver_gone() -> Error {
+    Error::output("channel", io::Error::new(io::ErrorKind::BrokenPipe, "candidate receiver was dropped"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_vec_sink_collects_in_order() {
+        let mut sink = Vec::new();
+        sink.write_chunk(&["a".to_string(), "b".to_string()]).unwrap();
+        sink.write_candidate("c").unwrap();
+        assert_eq!(sink, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_channel_sink_reports_dropped_receiver() {
+        let (mut sender, receiver) = mpsc::channel();
+        sender.write_chunk(&["a".to_string()]).unwrap();
+        assert_eq!(receiver.recv().unwrap(), "a");
+
+        drop(receiver);
+        assert!(sender.write_candidate("b").unwrap_err().is_broken_pipe());
+    }
+}
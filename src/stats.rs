@@ -0,0 +1,157 @@
+use crate::error::{Error, Result};
+use crate::policy::ClassCounts;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufRead, BufReader};
+
+/// Summary of an existing wordlist, in the spirit of pipal.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WordlistStats {
+    /// Non-empty lines
+    pub lines: u64,
+    pub unique: u64,
+    /// Lines that are not valid UTF-8; they count towards lengths and masks byte by byte
+    pub invalid_utf8: u64,
+    pub min_length: usize,
+    pub max_length: usize,
+    pub average_length: f64,
+    /// Line count per length in characters
+    pub lengths: BTreeMap<usize, u64>,
+    /// Line count per character set (e.g. `loweralphanum`), most common first
+    pub charsets: Vec<(String, u64)>,
+    /// Most common hashcat masks (e.g. `?u?l?l?l?d?d`), most common first
+    pub top_masks: Vec<(String, u64)>,
+}
+
+/// Read a wordlist and summarize it. Uniqueness is tracked with 64-bit
+/// hashes, like the exclusion lists, so large lists stay within memory.
+pub fn wordlist_stats(path: &str, top_masks: usize) -> Result<WordlistStats> {
+    let mut stats = WordlistStats {
+        min_length: usize::MAX,
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+    let mut charsets: HashMap<&'static str, u64> = HashMap::new();
+    let mut masks: HashMap<String, u64> = HashMap::new();
+    let mut total_length = 0u64;
+
+    let mut reader = BufReader::new(File::open(path).map_err(|e| Error::input(path, e))?);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line).map_err(|e| Error::input(path, e))? > 0 {
+        while matches!(line.last(), Some(b'\n') | Some(b'\r')) {
+            line.pop();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        stats.lines += 1;
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&line);
+        if seen.insert(hasher.finish()) {
+            stats.unique += 1;
+        }
+
+        let (length, charset, mask) = match std::str::from_utf8(&line) {
+            Ok(word) => (word.chars().count(), charset_name(&ClassCounts::of(word)), mask_of(word)),
+            Err(_) => {
+                stats.invalid_utf8 += 1;
+                (line.len(), "binary", "?b".repeat(line.len()))
+            }
+        };
+
+        total_length += length as u64;
+        stats.min_length = stats.min_length.min(length);
+        stats.max_length = stats.max_length.max(length);
+        *stats.lengths.entry(length).or_insert(0) += 1;
+        *charsets.entry(charset).or_insert(0) += 1;
+        *masks.entry(mask).or_insert(0) += 1;
+        line.clear();
+    }
+
+    if stats.lines == 0 {
+        stats.min_length = 0;
+    } else {
+        stats.average_length = total_length as f64 / stats.lines as f64;
+    }
+    stats.charsets = most_common(charsets.into_iter().map(|(name, count)| (name.to_string(), count)), usize::MAX);
+    stats.top_masks = most_common(masks.into_iter(), top_masks);
+    Ok(stats)
+}
+
+/// Character set name in pipal's terms: `numeric`, `loweralpha`, `mixedalphaspecialnum`, ...
+fn charset_name(counts: &ClassCounts) -> &'static str {
+    let letters = match (counts.lower > 0, counts.upper > 0) {
+        (true, true) => 2,
+        (false, true) => 1,
+        (true, false) => 0,
+        (false, false) => 3,
+    };
+    let index = letters * 4 + (counts.symbols > 0) as usize * 2 + (counts.digits > 0) as usize;
+    [
+        "loweralpha", "loweralphanum", "loweralphaspecial", "loweralphaspecialnum",
+        "upperalpha", "upperalphanum", "upperalphaspecial", "upperalphaspecialnum",
+        "mixedalpha", "mixedalphanum", "mixedalphaspecial", "mixedalphaspecialnum",
+        "empty", "numeric", "special", "specialnum",
+    ][index]
+}
+
+/// Hashcat mask of a word. Characters outside ASCII are `?b` per UTF-8 byte.
+fn mask_of(word: &str) -> String {
+    let mut mask = String::with_capacity(word.len() * 2);
+    for ch in word.chars() {
+        let class = match ch {
+            'a'..='z' => "?l",
+            'A'..='Z' => "?u",
+            '0'..='9' => "?d",
+            _ if ch.is_ascii() => "?s",
+            _ => {
+                mask.push_str(&"?b".repeat(ch.len_utf8()));
+                continue;
+            }
+        };
+        mask.push_str(class);
+    }
+    mask
+}
+
+fn most_common(counts: impl Iterator<Item = (String, u64)>, limit: usize) -> Vec<(String, u64)> {
+    let mut counts: Vec<(String, u64)> = counts.collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(limit);
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn test_wordlist_stats() {
+        let path = TempPath::file("stats.txt", b"password\r\nPassword1\npassword\n\n123456\nqwerty\n\xff\xfe\n");
+
+        let stats = wordlist_stats(path.as_str(), 2).unwrap();
+        assert_eq!(stats.lines, 6);
+        assert_eq!(stats.unique, 5);
+        assert_eq!(stats.invalid_utf8, 1);
+        assert_eq!((stats.min_length, stats.max_length), (2, 9));
+        assert_eq!(stats.lengths[&6], 2);
+        assert_eq!(stats.charsets[0], ("loweralpha".to_string(), 3));
+        assert_eq!(
+            stats.top_masks,
+            vec![("?l?l?l?l?l?l?l?l".to_string(), 2), ("?b?b".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_charset_names() {
+        assert_eq!(charset_name(&ClassCounts::of("123456")), "numeric");
+        assert_eq!(charset_name(&ClassCounts::of("P@ssw0rd")), "mixedalphaspecialnum");
+        assert_eq!(charset_name(&ClassCounts::of("ADMIN1")), "upperalphanum");
+        assert_eq!(charset_name(&ClassCounts::of("!!")), "special");
+    }
+}
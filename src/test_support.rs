@@ -0,0 +1,59 @@
+//! Fixtures shared by the unit tests.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A file or directory in the system temp directory, removed when dropped,
+/// so a failing assertion does not leave it behind. Paths are unique per
+/// process and per call, and end in `name`.
+pub struct TempPath {
+    path: PathBuf,
+}
+
+impl TempPath {
+    /// A path nothing has been created at yet.
+    pub fn new(name: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("p455w0rd_{}_{}_{}", std::process::id(), id, name));
+        TempPath { path }
+    }
+
+    /// A file holding `content`.
+    pub fn file(name: &str, content: impl AsRef<[u8]>) -> Self {
+        let temp = TempPath::new(name);
+        std::fs::write(&temp.path, content).unwrap();
+        temp
+    }
+
+    /// An empty directory; everything in it goes with it.
+    pub fn dir(name: &str) -> Self {
+        let temp = TempPath::new(name);
+        std::fs::create_dir_all(&temp.path).unwrap();
+        temp
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+
+    /// Path of `name` inside this directory.
+    pub fn join(&self, name: &str) -> String {
+        self.path.join(name).to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = if self.path.is_dir() {
+            std::fs::remove_dir_all(&self.path)
+        } else {
+            std::fs::remove_file(&self.path)
+        };
+    }
+}
@@ -0,0 +1,468 @@
+use crate::error::{Error, Result};
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Characters used to pad combinations at either end.
+pub const SPECIAL_CHARS: [char; 5] = ['!', '@', '#', '$', '%'];
+
+/// Letters replaced by look-alike digits, matched case-insensitively.
+const LEET_REPLACEMENTS: [(char, char); 6] = [
+    ('a', '4'),
+    ('e', '3'),
+    ('i', '1'),
+    ('l', '1'),
+    ('o', '0'),
+    ('s', '5'),
+];
+
+/// Which part of a candidate a transform sees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Each input word, before words are combined. The analysis enumerates
+    /// word outputs, so any transform can be counted exactly.
+    Word,
+    /// Each joined combination of words. There are far too many to
+    /// enumerate, so `count` and `lengths` may only depend on the input's
+    /// length; the analysis calls them with a placeholder of that length.
+    Candidate,
+}
+
+/// One mutation step of the candidate pipeline. The generator uses `apply`
+/// and the combinatorial analysis uses `count` and `lengths`, so a transform
+/// defined once is generated and counted the same way.
+pub trait Transform: Debug + Send + Sync {
+    /// Name used in `--transforms` and in reports.
+    fn name(&self) -> &'static str;
+
+    fn scope(&self) -> Scope;
+
+    /// Call `emit` with every output for `input`, in generation order.
+    fn apply(&self, input: &str, emit: &mut dyn FnMut(&str));
+
+    /// Number of outputs `apply` gives for `input` at each byte length.
+    /// The default runs `apply`; override it when there is a closed form.
+    fn lengths(&self, input: &str) -> BTreeMap<usize, u64> {
+        let mut lengths = BTreeMap::new();
+        self.apply(input, &mut |output| *lengths.entry(output.len()).or_insert(0) += 1);
+        lengths
+    }
+
+    /// Number of outputs `apply` gives for `input`.
+    fn count(&self, input: &str) -> u64 {
+        self.lengths(input).values().fold(0u64, |total, &count| total.saturating_add(count))
+    }
+
+    /// True when no output is shorter than its input, which lets the
+    /// generator skip inputs that are already too long.
+    fn only_lengthens(&self) -> bool {
+        false
+    }
+}
+
+/// Every combination of leet substitutions (admin -> 4dmin, adm1n, 4dm1n).
+/// A word with `MAX_LEET_POSITIONS` or more leet-able characters is left
+/// as it is: its 2^64 combinations could never be generated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Leet;
+
+/// Leet-able characters at which `Leet` stops substituting.
+pub const MAX_LEET_POSITIONS: usize = 64;
+
+impl Leet {
+    /// Positions to substitute and their replacements; none for a word
+    /// with `MAX_LEET_POSITIONS` or more of them.
+    fn replaceable(input: &str) -> Vec<(usize, char)> {
+        let positions: Vec<(usize, char)> = input
+            .char_indices()
+            .filter_map(|(i, ch)| {
+                LEET_REPLACEMENTS
+                    .iter()
+                    .find(|&&(from, _)| from == ch.to_ascii_lowercase())
+                    .map(|&(_, to)| (i, to))
+            })
+            .collect();
+        if positions.len() >= MAX_LEET_POSITIONS {
+            return Vec::new();
+        }
+        positions
+    }
+}
+
+impl Transform for Leet {
+    fn name(&self) -> &'static str {
+        "leet"
+    }
+
+    fn scope(&self) -> Scope {
+        Scope::Word
+    }
+
+    fn apply(&self, input: &str, emit: &mut dyn FnMut(&str)) {
+        let positions = Self::replaceable(input);
+        let mut output = input.as_bytes().to_vec();
+
+        // Bit i of the mask picks the replacement at position i
+        for mask in 0u64..1 << positions.len() {
+            for (bit, &(i, to)) in positions.iter().enumerate() {
+                output[i] = if (mask >> bit) & 1 == 1 { to as u8 } else { input.as_bytes()[i] };
+            }
+            // Only ASCII letters are swapped for ASCII digits, so this stays UTF-8
+            emit(std::str::from_utf8(&output).expect("leet keeps UTF-8 boundaries"));
+        }
+    }
+
+    fn lengths(&self, input: &str) -> BTreeMap<usize, u64> {
+        BTreeMap::from([(input.len(), self.count(input))])
+    }
+
+    fn count(&self, input: &str) -> u64 {
+        // 2^K possible leet combinations
+        1u64 << Self::replaceable(input).len()
+    }
+
+    fn only_lengthens(&self) -> bool {
+        true
+    }
+}
+
+/// Lowercase, Capitalized and UPPERCASE forms, without repeats.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Case;
+
+impl Case {
+    fn forms(input: &str) -> Vec<String> {
+        let lower = input.to_lowercase();
+        let capitalized = capitalize(&lower);
+        let mut forms = vec![lower];
+        for form in [capitalized, input.to_uppercase()] {
+            if !forms.contains(&form) {
+                forms.push(form);
+            }
+        }
+        forms
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+impl Transform for Case {
+    fn name(&self) -> &'static str {
+        "case"
+    }
+
+    fn scope(&self) -> Scope {
+        Scope::Word
+    }
+
+    fn apply(&self, input: &str, emit: &mut dyn FnMut(&str)) {
+        for form in Self::forms(input) {
+            emit(&form);
+        }
+    }
+}
+
+/// Special-character padding on a joined combination: the combination
+/// itself, then single prefixes, single suffixes, then each ordering of 2
+/// to 5 distinct characters as prefix and suffix.
+#[derive(Debug, Clone)]
+pub struct Pad {
+    /// (text, is_prefix) in generation order
+    affixes: Vec<(String, bool)>,
+}
+
+impl Default for Pad {
+    fn default() -> Self {
+        let mut affixes: Vec<(String, bool)> = SPECIAL_CHARS
+            .iter()
+            .map(|c| (c.to_string(), true))
+            .chain(SPECIAL_CHARS.iter().map(|c| (c.to_string(), false)))
+            .collect();
+
+        for k in 2..=SPECIAL_CHARS.len() {
+            for combo in SPECIAL_CHARS.iter().combinations(k) {
+                for perm in combo.iter().permutations(k) {
+                    let text: String = perm.into_iter().copied().collect();
+                    affixes.push((text.clone(), true));
+                    affixes.push((text, false));
+                }
+            }
+        }
+        Pad { affixes }
+    }
+}
+
+impl Transform for Pad {
+    fn name(&self) -> &'static str {
+        "pad"
+    }
+
+    fn scope(&self) -> Scope {
+        Scope::Candidate
+    }
+
+    fn apply(&self, input: &str, emit: &mut dyn FnMut(&str)) {
+        emit(input);
+        let mut output = String::with_capacity(input.len() + SPECIAL_CHARS.len());
+        for (text, prefix) in &self.affixes {
+            output.clear();
+            if *prefix {
+                output.push_str(text);
+                output.push_str(input);
+            } else {
+                output.push_str(input);
+                output.push_str(text);
+            }
+            emit(&output);
+        }
+    }
+
+    fn lengths(&self, input: &str) -> BTreeMap<usize, u64> {
+        let mut lengths = BTreeMap::from([(input.len(), 1)]);
+        for (text, _) in &self.affixes {
+            *lengths.entry(input.len() + text.len()).or_insert(0) += 1;
+        }
+        lengths
+    }
+
+    fn count(&self, _input: &str) -> u64 {
+        1 + self.affixes.len() as u64
+    }
+
+    fn only_lengthens(&self) -> bool {
+        true
+    }
+}
+
+/// The ordered transforms a run applies: word transforms on each word in
+/// turn, then candidate transforms on each combination.
+#[derive(Debug, Clone)]
+pub struct TransformChain {
+    word: Vec<Arc<dyn Transform>>,
+    candidate: Vec<Arc<dyn Transform>>,
+}
+
+impl Default for TransformChain {
+    fn default() -> Self {
+        TransformChain::standard(true)
+    }
+}
+
+impl TransformChain {
+    /// Chain `stages` in order. Words are combined between the two scopes,
+    /// so every word transform has to come before every candidate transform.
+    pub fn new(stages: Vec<Arc<dyn Transform>>) -> Result<Self> {
+        let mut chain = TransformChain { word: Vec::new(), candidate: Vec::new() };
+        for stage in stages {
+            match stage.scope() {
+                Scope::Word => {
+                    if let Some(last) = chain.candidate.last() {
+                        return Err(Error::config(format!(
+                            "'{}' runs on single words, so it must come before '{}', which runs on joined words",
+                            stage.name(),
+                            last.name()
+                        )));
+                    }
+                    chain.word.push(stage);
+                }
+                Scope::Candidate => chain.candidate.push(stage),
+            }
+        }
+        Ok(chain)
+    }
+
+    /// The built-in pipeline: leet, then case, then optional padding.
+    pub fn standard(padding: bool) -> Self {
+        let mut stages: Vec<Arc<dyn Transform>> = vec![Arc::new(Leet), Arc::new(Case)];
+        if padding {
+            stages.push(Arc::new(Pad::default()));
+        }
+        TransformChain::new(stages).expect("word transforms come first")
+    }
+
+    /// Parse a comma-separated list of built-in transforms, e.g. `case,leet,pad`.
+    /// An empty list uses the words as given.
+    pub fn parse(list: &str) -> Result<Self> {
+        let mut stages: Vec<Arc<dyn Transform>> = Vec::new();
+        for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            if stages.iter().any(|stage| stage.name() == name) {
+                return Err(Error::config(format!("transform '{}' is listed more than once", name)));
+            }
+            stages.push(match name {
+                "leet" => Arc::new(Leet),
+                "case" => Arc::new(Case),
+                "pad" => Arc::new(Pad::default()),
+                other => return Err(Error::config(format!("unknown transform '{}' (expected leet, case or pad)", other))),
+            });
+        }
+        TransformChain::new(stages)
+    }
+
+    /// The same chain without the transform called `name`.
+    pub fn without(mut self, name: &str) -> Self {
+        self.word.retain(|stage| stage.name() != name);
+        self.candidate.retain(|stage| stage.name() != name);
+        self
+    }
+
+    /// Transform names in the order they run.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.word.iter().chain(&self.candidate).map(|stage| stage.name()).collect()
+    }
+
+    pub fn find(&self, name: &str) -> Option<&dyn Transform> {
+        self.word.iter().chain(&self.candidate).find(|stage| stage.name() == name).map(Arc::as_ref)
+    }
+
+    /// The distinct outputs of the word transforms for `word`, sorted.
+    pub fn word_variants(&self, word: &str) -> Vec<String> {
+        let mut variants = vec![word.to_string()];
+        for stage in &self.word {
+            let mut next = Vec::with_capacity(variants.len());
+            for variant in &variants {
+                stage.apply(variant, &mut |output| next.push(output.to_string()));
+            }
+            next.sort();
+            next.dedup();
+            variants = next;
+        }
+        variants
+    }
+
+    /// Call `emit` with every candidate the candidate transforms make from
+    /// the combination `base`, in generation order.
+    pub fn apply_candidate(&self, base: &str, emit: &mut dyn FnMut(&str)) {
+        apply_stages(&self.candidate, base, emit);
+    }
+
+    /// Candidates per byte length made from combinations with the byte
+    /// lengths in `bases`.
+    pub fn candidate_lengths(&self, bases: &BTreeMap<usize, u64>) -> BTreeMap<usize, u64> {
+        let mut lengths = bases.clone();
+        for stage in &self.candidate {
+            let mut next = BTreeMap::new();
+            for (&length, &count) in &lengths {
+                for (output, outputs) in stage.lengths(&"x".repeat(length)) {
+                    let entry = next.entry(output).or_insert(0u64);
+                    *entry = entry.saturating_add(count.saturating_mul(outputs));
+                }
+            }
+            lengths = next;
+        }
+        lengths
+    }
+
+    /// True when candidate transforms never shorten a combination.
+    pub fn only_lengthens(&self) -> bool {
+        self.candidate.iter().all(|stage| stage.only_lengthens())
+    }
+}
+
+fn apply_stages(stages: &[Arc<dyn Transform>], input: &str, emit: &mut dyn FnMut(&str)) {
+    match stages.split_first() {
+        None => emit(input),
+        Some((stage, rest)) => stage.apply(input, &mut |output| apply_stages(rest, output, emit)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(transform: &dyn Transform, input: &str) -> Vec<String> {
+        let mut outputs = Vec::new();
+        transform.apply(input, &mut |output| outputs.push(output.to_string()));
+        outputs
+    }
+
+    #[test]
+    fn test_leet_count() {
+        // Word with no replaceable characters
+        assert_eq!(Leet.count("xyz"), 1);
+
+        // Word with one replaceable character
+        assert_eq!(Leet.count("a"), 2);
+        assert_eq!(Leet.count("e"), 2);
+        assert_eq!(Leet.count("i"), 2);
+
+        // Word with multiple replaceable characters
+        assert_eq!(Leet.count("admin"), 4); // a and i -> 2^2
+        assert_eq!(Leet.count("password"), 16); // a, s, s, o -> 2^4
+        assert_eq!(Leet.count("hello"), 16); // e, l, l, o -> 2^4
+        assert_eq!(Leet.count("aeiou"), 16); // a, e, i, o -> 2^4
+
+        // Case insensitive
+        assert_eq!(Leet.count("ADMIN"), 4);
+        assert_eq!(Leet.count("Admin"), 4);
+        assert_eq!(Leet.count("PASSWORD"), 16);
+
+        assert_eq!(outputs(&Leet, "Admin"), ["Admin", "4dmin", "Adm1n", "4dm1n"]);
+    }
+
+    #[test]
+    fn test_case_forms() {
+        assert_eq!(outputs(&Case, "aDmin"), ["admin", "Admin", "ADMIN"]);
+        assert_eq!(outputs(&Case, "4dmin"), ["4dmin", "4DMIN"]);
+        assert_eq!(outputs(&Case, "1234"), ["1234"]);
+        assert_eq!(Case.count("4dmin"), 2);
+    }
+
+    #[test]
+    fn test_pad_variants() {
+        let pad = Pad::default();
+        // No padding, 5 prefixes, 5 suffixes, and each ordering of 2-5 chars at either end
+        assert_eq!(pad.count("x"), 1 + 5 + 5 + 2 * (20 + 60 + 120 + 120));
+
+        let padded = outputs(&pad, "x");
+        assert_eq!(padded.len() as u64, pad.count("x"));
+        assert_eq!(&padded[..3], ["x", "!x", "@x"]);
+        assert!(padded.contains(&"x%$#@!".to_string()));
+    }
+
+    #[test]
+    fn test_counts_match_apply() {
+        // The closed forms must agree with enumerating
+        let default_lengths = |transform: &dyn Transform, input: &str| {
+            let mut lengths = BTreeMap::new();
+            transform.apply(input, &mut |output| *lengths.entry(output.len()).or_insert(0u64) += 1);
+            lengths
+        };
+        let transforms: [&dyn Transform; 3] = [&Leet, &Case, &Pad::default()];
+        for transform in transforms {
+            for input in ["", "a", "Password", "r00t", "ßeta", &"a".repeat(MAX_LEET_POSITIONS)] {
+                assert_eq!(transform.lengths(input), default_lengths(transform, input), "{} {}", transform.name(), input);
+                assert_eq!(transform.count(input), outputs(transform, input).len() as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chain_parsing_and_order() {
+        let chain = TransformChain::parse("case, leet,pad").unwrap();
+        assert_eq!(chain.names(), ["case", "leet", "pad"]);
+        assert_eq!(TransformChain::parse("").unwrap().word_variants("Admin"), ["Admin"]);
+        assert!(TransformChain::parse("pad,leet").unwrap_err().to_string().contains("must come before"));
+        assert!(TransformChain::parse("leet,leet").is_err());
+        assert!(TransformChain::parse("rot13").is_err());
+        assert_eq!(TransformChain::default().without("pad").names(), ["leet", "case"]);
+
+        // Either order of leet and case gives the same variants
+        let standard = TransformChain::standard(false);
+        assert_eq!(chain.word_variants("Admin"), standard.word_variants("admin"));
+        assert_eq!(standard.word_variants("admin").len(), 10);
+
+        let mut candidates = Vec::new();
+        chain.apply_candidate("ab", &mut |candidate| candidates.push(candidate.to_string()));
+        assert_eq!(candidates.len() as u64, Pad::default().count("ab"));
+        let lengths = chain.candidate_lengths(&BTreeMap::from([(2, 3)]));
+        assert_eq!(lengths.values().sum::<u64>(), 3 * candidates.len() as u64);
+        assert_eq!(lengths[&2], 3);
+    }
+}
@@ -1,7 +1,9 @@
-use crate::args::Args;
-use rayon::prelude::*;
+use crate::args::WordArgs;
+use crate::error::{Error, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 
-pub fn get_words(args: &Args) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+pub fn get_words(args: &WordArgs) -> Result<Vec<String>> {
     let mut words = Vec::new();
 
     // Add words from arguments
@@ -9,7 +11,7 @@ pub fn get_words(args: &Args) -> Result<Vec<String>, Box<dyn std::error::Error>>
 
     // Add words from input file if provided
     if let Some(input_file) = &args.input {
-        let content = std::fs::read_to_string(input_file)?;
+        let content = std::fs::read_to_string(input_file).map_err(|e| Error::input(input_file, e))?;
         for line in content.lines() {
             let line = line.trim();
             if !line.is_empty() {
@@ -31,95 +33,22 @@ pub fn get_words(args: &Args) -> Result<Vec<String>, Box<dyn std::error::Error>>
     Ok(words)
 }
 
-pub fn create_word_variants(word: &str) -> Vec<String> {
-    let mut variants = Vec::new();
-    let lower = word.to_lowercase();
-
-    // Generate all possible l33t combinations for this word
-    let leet_variants = generate_all_leet_for_word(&lower);
-
-    // For each l33t variant, add different capitalizations using parallel processing
-    let capitalization_variants: Vec<String> = leet_variants
-        .par_iter() // Use parallel iterator for capitalization
-        .flat_map(|leet_word| {
-            vec![
-                leet_word.clone(),                    // lowercase
-                capitalize_word(leet_word),           // Capitalized
-                leet_word.to_uppercase(),            // UPPERCASE
-            ]
-        })
-        .collect();
-
-    variants.extend(capitalization_variants);
-
-    // Remove duplicates
-    variants.sort();
-    variants.dedup();
-    variants
-}
-
-fn generate_all_leet_for_word(word: &str) -> Vec<String> {
-    let replacements = [
-        ('a', '4'),
-        ('e', '3'),
-        ('i', '1'),
-        ('l', '1'),
-        ('o', '0'),
-        ('s', '5'),
-    ];
-
-    let chars: Vec<char> = word.chars().collect();
-    let mut results = Vec::new();
-
-    // Find all positions that can be replaced
-    let replaceable_positions: Vec<(usize, char, char)> = chars
-        .iter()
-        .enumerate()
-        .filter_map(|(i, &ch)| {
-            replacements.iter()
-                .find(|&&(from, _)| from == ch)
-                .map(|&(_, to)| (i, ch, to))
-        })
-        .collect();
-
-    if replaceable_positions.is_empty() {
-        return vec![word.to_string()];
-    }
-
-    // Generate all combinations using bit patterns
-    let max_combinations = 1 << replaceable_positions.len();
-
-    for combination in 0..max_combinations {
-        let mut result_chars = chars.clone();
-
-        for (bit_pos, &(char_pos, _original, replacement)) in replaceable_positions.iter().enumerate() {
-            if (combination >> bit_pos) & 1 == 1 {
-                result_chars[char_pos] = replacement;
-            }
+/// Feed each line of a plaintext password list to `add`, for training.
+/// Returns how many lines were skipped: those that are not UTF-8 and those
+/// `add` returned false for.
+pub fn read_passwords(path: &str, mut add: impl FnMut(&str) -> bool) -> Result<u64> {
+    let mut skipped = 0;
+    let mut reader = BufReader::new(File::open(path).map_err(|e| Error::input(path, e))?);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line).map_err(|e| Error::input(path, e))? > 0 {
+        while matches!(line.last(), Some(b'\n') | Some(b'\r')) {
+            line.pop();
         }
-
-        results.push(result_chars.iter().collect());
-    }
-
-    results
-}
-
-fn capitalize_word(word: &str) -> String {
-    if word.is_empty() {
-        return String::new();
-    }
-
-    let mut chars = word.chars();
-    if let Some(first) = chars.next() {
-        let uppercase_first = first.to_uppercase().collect::<String>();
-        if uppercase_first.len() == 1 && uppercase_first.starts_with(first) {
-            // No change needed, use Cow to avoid allocation
-            word.to_string()
-        } else {
-            // Capitalization needed
-            uppercase_first + &chars.collect::<String>()
+        match std::str::from_utf8(&line) {
+            Ok(password) if add(password) => {}
+            _ => skipped += 1,
         }
-    } else {
-        String::new()
+        line.clear();
     }
-}
\ No newline at end of file
+    Ok(skipped)
+}
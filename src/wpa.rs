@@ -0,0 +1,347 @@
+use crate::error::{self, Error};
+use crate::hashing::{from_hex, to_hex};
+use crate::sink::CandidateSink;
+use aes::Aes128;
+use cmac::Cmac;
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use rayon::prelude::*;
+use sha1::Sha1;
+use sha2::Sha256;
+
+/// PBKDF2 iteration count fixed by IEEE 802.11i.
+const PMK_ITERATIONS: u32 = 4096;
+const PTK_LABEL: &[u8] = b"Pairwise key expansion";
+const PMKID_LABEL: &[u8] = b"PMK Name";
+
+/// Offsets into an EAPOL-Key frame, counted from the 802.1X header.
+const KEY_INFO: std::ops::Range<usize> = 5..7;
+const KEY_NONCE: std::ops::Range<usize> = 17..49;
+const KEY_MIC: std::ops::Range<usize> = 81..97;
+const MIN_EAPOL_LEN: usize = 99;
+
+/// Candidates per chunk when verifying handshakes. A PMK takes thousands of
+/// SHA-1 rounds, so smaller chunks keep the status display and the stop on
+/// a match responsive.
+pub const MAX_CHUNK_SIZE: usize = 1024;
+
+pub type Pmk = [u8; 32];
+
+/// Derive the pairwise master key for a passphrase and network name.
+pub fn pmk(passphrase: &str, essid: &[u8]) -> Pmk {
+    let mut pmk = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha1>(passphrase.as_bytes(), essid, PMK_ITERATIONS, &mut pmk);
+    pmk
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Capture {
+    /// PMKID from the first message of the handshake (`WPA*01`)
+    Pmkid,
+    /// MIC over the station's EAPOL-Key frame (`WPA*02`)
+    Eapol {
+        /// Key descriptor version: 1 = HMAC-MD5, 2 = HMAC-SHA1, 3 = AES-CMAC
+        key_version: u8,
+        anonce: Vec<u8>,
+        snonce: Vec<u8>,
+        /// Frame with the MIC field zeroed, as the MIC was computed
+        eapol: Vec<u8>,
+    },
+}
+
+/// One hashcat mode 22000 hash line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handshake {
+    capture: Capture,
+    /// PMKID or MIC to reproduce
+    target: Vec<u8>,
+    mac_ap: Vec<u8>,
+    mac_sta: Vec<u8>,
+    essid: Vec<u8>,
+}
+
+impl Handshake {
+    /// Parse a `WPA*01*pmkid*mac_ap*mac_sta*essid***` or
+    /// `WPA*02*mic*mac_ap*mac_sta*essid*anonce*eapol*message_pair` line.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.trim().split('*').collect();
+        if fields.len() < 6 || fields[0] != "WPA" {
+            return Err("not a hashcat 22000 (WPA*01/WPA*02) line".to_string());
+        }
+
+        let hex_field = |index: usize, name: &str, len: Option<usize>| -> Result<Vec<u8>, String> {
+            let bytes = fields
+                .get(index)
+                .and_then(|field| from_hex(field))
+                .ok_or_else(|| format!("invalid {} field", name))?;
+            match len {
+                Some(len) if bytes.len() != len => Err(format!("{} must be {} bytes", name, len)),
+                _ => Ok(bytes),
+            }
+        };
+
+        let target = hex_field(2, "PMKID/MIC", Some(16))?;
+        let mac_ap = hex_field(3, "AP MAC", Some(6))?;
+        let mac_sta = hex_field(4, "station MAC", Some(6))?;
+        let essid = hex_field(5, "ESSID", None)?;
+        if essid.len() > 32 {
+            return Err("ESSID must be at most 32 bytes".to_string());
+        }
+
+        let capture = match fields[1] {
+            "01" => Capture::Pmkid,
+            "02" => {
+                let anonce = hex_field(6, "ANonce", Some(32))?;
+                let mut eapol = hex_field(7, "EAPOL", None)?;
+                if eapol.len() < MIN_EAPOL_LEN {
+                    return Err("EAPOL frame is too short".to_string());
+                }
+                let key_version = eapol[KEY_INFO.end - 1] & 0x07;
+                if !(1..=3).contains(&key_version) {
+                    return Err(format!("unsupported key descriptor version {}", key_version));
+                }
+                let snonce = eapol[KEY_NONCE].to_vec();
+                eapol[KEY_MIC].fill(0);
+                Capture::Eapol { key_version, anonce, snonce, eapol }
+            }
+            other => return Err(format!("unknown hash type WPA*{}", other)),
+        };
+
+        Ok(Handshake { capture, target, mac_ap, mac_sta, essid })
+    }
+
+    pub fn essid(&self) -> &[u8] {
+        &self.essid
+    }
+
+    /// True if `pmk` reproduces the captured PMKID or MIC.
+    pub fn verify(&self, pmk: &Pmk) -> bool {
+        match &self.capture {
+            Capture::Pmkid => {
+                let mut mac = <Hmac<Sha1>>::new_from_slice(pmk).expect("HMAC accepts any key length");
+                mac.update(PMKID_LABEL);
+                mac.update(&self.mac_ap);
+                mac.update(&self.mac_sta);
+                mac.finalize().into_bytes()[..16] == self.target[..]
+            }
+            Capture::Eapol { key_version, anonce, snonce, eapol } => {
+                let kck = self.kck(pmk, *key_version, anonce, snonce);
+                let mic = match key_version {
+                    1 => hmac_digest::<Hmac<Md5>>(&kck, eapol),
+                    2 => hmac_digest::<Hmac<Sha1>>(&kck, eapol),
+                    _ => hmac_digest::<Cmac<Aes128>>(&kck, eapol),
+                };
+                mic[..16] == self.target[..]
+            }
+        }
+    }
+
+    /// Key confirmation key: the first 16 bytes of the pairwise transient key.
+    fn kck(&self, pmk: &Pmk, key_version: u8, anonce: &[u8], snonce: &[u8]) -> Vec<u8> {
+        let (mac_lo, mac_hi) = ordered(&self.mac_ap, &self.mac_sta);
+        let (nonce_lo, nonce_hi) = ordered(anonce, snonce);
+
+        let ptk_block = if key_version == 3 {
+            // 802.11 KDF-SHA256: counter, label, context, PTK length in bits
+            let mut mac = <Hmac<Sha256>>::new_from_slice(pmk).expect("HMAC accepts any key length");
+            mac.update(&1u16.to_le_bytes());
+            mac.update(PTK_LABEL);
+            for part in [mac_lo, mac_hi, nonce_lo, nonce_hi] {
+                mac.update(part);
+            }
+            mac.update(&384u16.to_le_bytes());
+            mac.finalize().into_bytes().to_vec()
+        } else {
+            // 802.11i PRF-512; the KCK lives entirely in the first block
+            let mut mac = <Hmac<Sha1>>::new_from_slice(pmk).expect("HMAC accepts any key length");
+            mac.update(PTK_LABEL);
+            mac.update(&[0]);
+            for part in [mac_lo, mac_hi, nonce_lo, nonce_hi] {
+                mac.update(part);
+            }
+            mac.update(&[0]);
+            mac.finalize().into_bytes().to_vec()
+        };
+
+        ptk_block[..16].to_vec()
+    }
+
+    /// The hash line as it appears in a hashcat potfile, with the passphrase appended.
+    pub fn potfile_line(&self, passphrase: &str) -> String {
+        format!(
+            "{}:{}:{}:{}:{}",
+            to_hex(&self.target),
+            to_hex(&self.mac_ap),
+            to_hex(&self.mac_sta),
+            String::from_utf8_lossy(&self.essid),
+            passphrase
+        )
+    }
+}
+
+fn hmac_digest<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key).expect("16-byte KCK is a valid key");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn ordered<'a>(a: &'a [u8], b: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Captured handshakes that generated passphrases are verified against.
+///
+/// Each candidate costs one PBKDF2 derivation per distinct ESSID, so chunks
+/// are spread across all cores with rayon.
+pub struct WpaVerifier {
+    handshakes: Vec<Handshake>,
+    found: Vec<(usize, String)>,
+}
+
+impl WpaVerifier {
+    /// Load every hash line in a hashcat 22000 file; blank lines are skipped.
+    pub fn load(path: &str) -> error::Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::input(path, e))?;
+
+        let mut handshakes = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let handshake = Handshake::parse(line).map_err(|e| Error::parse(path, Some(number + 1), e))?;
+            handshakes.push(handshake);
+        }
+
+        if handshakes.is_empty() {
+            return Err(Error::parse(path, None, "no handshakes found"));
+        }
+        Ok(WpaVerifier { handshakes, found: Vec::new() })
+    }
+
+    pub fn handshake_count(&self) -> usize {
+        self.handshakes.len()
+    }
+
+    /// Try every candidate of a chunk in parallel and record each
+    /// handshake it unlocks.
+    pub fn check_chunk(&mut self, candidates: &[String]) {
+        let handshakes = &self.handshakes;
+        let found = &self.found;
+        let matches: Vec<(usize, &String)> = candidates
+            .par_iter()
+            .filter(|candidate| (8..=63).contains(&candidate.len()))
+            .flat_map_iter(|candidate| {
+                let mut pmks: Vec<(&[u8], Pmk)> = Vec::new();
+                handshakes
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| !found.iter().any(|(done, _)| done == index))
+                    .filter_map(|(index, handshake)| {
+                        // Handshakes from one network share the PMK
+                        let pmk = match pmks.iter().find(|(essid, _)| *essid == handshake.essid()) {
+                            Some((_, pmk)) => *pmk,
+                            None => {
+                                let pmk = pmk(candidate, handshake.essid());
+                                pmks.push((handshake.essid(), pmk));
+                                pmk
+                            }
+                        };
+                        handshake.verify(&pmk).then_some((index, candidate))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for (index, candidate) in matches {
+            if !self.found.iter().any(|(done, _)| *done == index) {
+                self.found.push((index, candidate.clone()));
+            }
+        }
+    }
+
+    pub fn check_candidate(&mut self, candidate: &str) {
+        self.check_chunk(&[candidate.to_string()]);
+    }
+
+    /// True once every handshake has been unlocked.
+    pub fn is_done(&self) -> bool {
+        self.found.len() == self.handshakes.len()
+    }
+
+    /// `(handshake, passphrase)` pairs recovered, in order found.
+    pub fn found(&self) -> impl Iterator<Item = (&Handshake, &str)> {
+        self.found
+            .iter()
+            .map(|(index, passphrase)| (&self.handshakes[*index], passphrase.as_str()))
+    }
+}
+
+impl CandidateSink for WpaVerifier {
+    fn write_chunk(&mut self, candidates: &[String]) -> error::Result<()> {
+        self.check_chunk(candidates);
+        Ok(())
+    }
+
+    fn write_candidate(&mut self, candidate: &str) -> error::Result<()> {
+        self.check_candidate(candidate);
+        Ok(())
+    }
+
+    fn is_done(&self) -> bool {
+        WpaVerifier::is_done(self)
+    }
+
+    fn describe(&self) -> (&'static str, String) {
+        ("WPA Verification", format!("{} handshakes", self.handshake_count()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example hashes from the hashcat wiki, both for the passphrase "hashcat!"
+    const PMKID_LINE: &str = "WPA*01*4d4fe7aac3a2cecab195321ceb99a7d0*fc690c158264*f4747f87f9f4*686173686361742d6573736964***";
+    const EAPOL_LINE: &str = "WPA*02*024022795224bffca545276c3762686f*6466b38ec3fc*225edc49b7aa*54502d4c494e4b5f484153484341545f54455354*10e3be3b005a629e89de088d6a2fdc489db83ad4764f2d186b9cde15446e972e*0103007502010a0000000000000000000148ce2ccba9c1fda130ff2fbbfb4fd3b063d1a93920b0f7df54a5cbf787b16171000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001630140100000fac040100000fac040100000fac028000*a2";
+
+    #[test]
+    fn test_pmk_matches_ieee_vector() {
+        assert_eq!(
+            to_hex(&pmk("password", b"IEEE")),
+            "f42c6fc52df0ebef9ebb4b90b38a5f902e83fe1b135a70e23aed762e9710a12e"
+        );
+    }
+
+    #[test]
+    fn test_verify_pmkid_and_eapol() {
+        for line in [PMKID_LINE, EAPOL_LINE] {
+            let handshake = Handshake::parse(line).unwrap();
+            assert!(handshake.verify(&pmk("hashcat!", handshake.essid())));
+            assert!(!handshake.verify(&pmk("hashcat?", handshake.essid())));
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_lines() {
+        assert!(Handshake::parse("WPA*03*00").is_err());
+        assert!(Handshake::parse("5f4dcc3b5aa765d61d8327deb882cf99").is_err());
+        assert!(Handshake::parse("WPA*02*024022795224bffca545276c3762686f*6466b38ec3fc*225edc49b7aa*5450*00*0103*a2").is_err());
+    }
+
+    #[test]
+    fn test_verifier_stops_once_found() {
+        let mut verifier = WpaVerifier {
+            handshakes: vec![Handshake::parse(PMKID_LINE).unwrap()],
+            found: Vec::new(),
+        };
+        verifier.check_chunk(&["short".to_string(), "password".to_string(), "hashcat!".to_string()]);
+        assert!(verifier.is_done());
+        let (handshake, passphrase) = verifier.found().next().unwrap();
+        assert_eq!(passphrase, "hashcat!");
+        assert!(handshake.potfile_line(passphrase).ends_with(":hashcat-essid:hashcat!"));
+    }
+}
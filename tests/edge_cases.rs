@@ -1,11 +1,14 @@
 use p455w0rd::combinatorics::{calculate_total_combinations, CombinatorialConfig};
+use p455w0rd::transform::TransformChain;
+use p455w0rd::Error;
 
 #[test]
 fn test_empty_word_list() {
     let words = vec![];
     let config = CombinatorialConfig {
         max_words: 2,
-        include_special_chars: false,
+        transforms: TransformChain::standard(false),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -17,7 +20,8 @@ fn test_single_character_words() {
     let words = vec!["a".to_string(), "b".to_string(), "c".to_string()];
     let config = CombinatorialConfig {
         max_words: 2,
-        include_special_chars: false,
+        transforms: TransformChain::standard(false),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -32,7 +36,8 @@ fn test_words_with_numbers() {
     let words = vec!["admin123".to_string(), "pass456".to_string()];
     let config = CombinatorialConfig {
         max_words: 2,
-        include_special_chars: false,
+        transforms: TransformChain::standard(false),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -47,7 +52,8 @@ fn test_unicode_words() {
     let words = vec!["café".to_string(), "naïve".to_string()];
     let config = CombinatorialConfig {
         max_words: 2,
-        include_special_chars: false,
+        transforms: TransformChain::standard(false),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -62,7 +68,8 @@ fn test_very_long_words() {
     let words = vec!["a".repeat(10), "b".repeat(10)]; // Use reasonable length
     let config = CombinatorialConfig {
         max_words: 2,
-        include_special_chars: false,
+        transforms: TransformChain::standard(false),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -77,7 +84,8 @@ fn test_duplicate_words() {
     let words = vec!["admin".to_string(), "admin".to_string(), "password".to_string()];
     let config = CombinatorialConfig {
         max_words: 2,
-        include_special_chars: false,
+        transforms: TransformChain::standard(false),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -92,7 +100,8 @@ fn test_max_words_zero() {
     let words = vec!["admin".to_string(), "password".to_string()];
     let config = CombinatorialConfig {
         max_words: 0, // Should be treated as unlimited
-        include_special_chars: false,
+        transforms: TransformChain::standard(false),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -107,7 +116,8 @@ fn test_max_words_exceeds_word_count() {
     let words = vec!["admin".to_string(), "password".to_string()];
     let config = CombinatorialConfig {
         max_words: 5, // More words than available
-        include_special_chars: false,
+        transforms: TransformChain::standard(false),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -122,7 +132,8 @@ fn test_no_leetable_characters() {
     let words = vec!["xyz".to_string(), "qwrt".to_string()];
     let config = CombinatorialConfig {
         max_words: 2,
-        include_special_chars: false,
+        transforms: TransformChain::standard(false),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -137,7 +148,8 @@ fn test_special_chars_only() {
     let words = vec!["admin".to_string()];
     let config = CombinatorialConfig {
         max_words: 1,
-        include_special_chars: true,
+        transforms: TransformChain::standard(true),
+        ..Default::default()
     };
 
     let result = calculate_total_combinations(&words, &config);
@@ -145,4 +157,18 @@ fn test_special_chars_only() {
     let analysis = result.unwrap();
     // Should handle special characters correctly
     assert!(analysis.total_combinations > 0);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_min_length_above_max_length() {
+    let words = vec!["admin".to_string()];
+    let config = CombinatorialConfig {
+        min_len: 12,
+        max_len: 8,
+        ..Default::default()
+    };
+
+    let err = calculate_total_combinations(&words, &config).unwrap_err();
+    assert!(matches!(err, Error::Config(_)));
+    assert_eq!(err.exit_code(), p455w0rd::error::EXIT_CONFIG);
+}